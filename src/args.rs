@@ -1,4 +1,13 @@
-use clap::Parser;
+use crate::color::Palette;
+use crate::config::Config;
+use crate::error::{Result, YetiError};
+use crate::git::{self, StageMode};
+use clap::{Parser, Subcommand};
+use std::convert::Infallible;
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
 
 pub const MASCOT_LINES: [&str; 9] = [
     "┏━━━━━━━━━━━┓",
@@ -14,19 +23,205 @@ pub const MASCOT_LINES: [&str; 9] = [
 
 pub const MASCOT_MINI: &str = "┌──┐┌──┐ ▌▌▌";
 
-pub fn print_help() {
-    let orange = "\x1b[38;5;208m";
-    let yellow = "\x1b[38;5;214m";
-    let green = "\x1b[38;5;142m";
-    let blue = "\x1b[38;5;109m";
-    let dim = "\x1b[38;5;246m";
-    let bold = "\x1b[1m";
-    let reset = "\x1b[0m";
+#[derive(Clone, Copy)]
+enum Tone {
+    Green,
+    Yellow,
+    Blue,
+}
+
+// (flag, description, tone) — one line each on the help screen.
+const HELP_OPTIONS: &[(&str, &str, Tone)] = &[
+    ("--dry-run", "preview commit, no write", Tone::Green),
+    ("--commit", "commit even with default_dry_run", Tone::Green),
+    (
+        "--changelog",
+        "also print a CHANGELOG.md entry",
+        Tone::Green,
+    ),
+    (
+        "--summary-line",
+        "print one parseable result line",
+        Tone::Green,
+    ),
+    (
+        "--out <PATH>",
+        "also write the message to PATH",
+        Tone::Green,
+    ),
+    ("--fd <N>", "also write the message to fd N", Tone::Green),
+    ("--verbose", "print provider replies on exit", Tone::Green),
+    ("--force", "skip large-file/line-ending checks", Tone::Green),
+    ("--amend", "rewrite HEAD with staged changes", Tone::Green),
+    ("--all", "stage all, untracked too (default)", Tone::Green),
+    ("--tracked-only", "stage tracked files only", Tone::Green),
+    ("--staged-only", "commit the index as-is", Tone::Green),
+    (
+        "--stage-prompt",
+        "offer to stage when nothing is",
+        Tone::Green,
+    ),
+    ("--select", "pick the files to stage", Tone::Green),
+    ("--reset-key", "force API key re-entry", Tone::Yellow),
+    ("--allow-no-key", "use base_url without a key", Tone::Yellow),
+    ("--reset-cache", "wipe stored config", Tone::Yellow),
+    (
+        "--sign[=KEYID]",
+        "GPG-sign, optionally with a key",
+        Tone::Green,
+    ),
+    ("--tag <NAME>", "tag the new commit as NAME", Tone::Green),
+    (
+        "--tag-message <MSG>",
+        "tag message, not the title",
+        Tone::Green,
+    ),
+    ("--key-env <NAME>", "read API key from $NAME", Tone::Yellow),
+    (
+        "--api-key <KEY>",
+        "use KEY (insecure: visible in ps/history)",
+        Tone::Yellow,
+    ),
+    ("--model <NAME>", "use NAME for this run only", Tone::Yellow),
+    ("--type <TYPE>", "force the commit type", Tone::Yellow),
+    ("--scope <NAME>", "force the commit scope", Tone::Yellow),
+    (
+        "--temperature <T>",
+        "sample at T (0-2) this run",
+        Tone::Yellow,
+    ),
+    ("--stage-hook <CMD>", "run CMD before staging", Tone::Yellow),
+    ("--no-verify", "skip git commit hooks", Tone::Yellow),
+    (
+        "--profile <NAME>",
+        "use [profiles.NAME] from config",
+        Tone::Yellow,
+    ),
+    (
+        "--wrap <N>",
+        "wrap body at N columns, 0 = off",
+        Tone::Yellow,
+    ),
+    ("--pick <N>", "write N messages, pick one", Tone::Yellow),
+    (
+        "--context-commits <N>",
+        "show the last N commit diffs",
+        Tone::Yellow,
+    ),
+    (
+        "--fixes-file <PATH>",
+        "failing output this change fixes",
+        Tone::Yellow,
+    ),
+    (
+        "--include-ignored",
+        "flag staged files .gitignore matches",
+        Tone::Yellow,
+    ),
+    ("--edit-config", "open config in $EDITOR", Tone::Blue),
+    (
+        "--print-config",
+        "show settings and their origin",
+        Tone::Blue,
+    ),
+    (
+        "--list-staged",
+        "print the staged file table and exit",
+        Tone::Blue,
+    ),
+    ("--show-history", "list commits yeti logged", Tone::Blue),
+    ("--clear-history", "delete the commit logs", Tone::Blue),
+    ("--no-color", "disable colors (like NO_COLOR)", Tone::Blue),
+    ("--plain-help", "ASCII-only help, no colors", Tone::Blue),
+    ("-h, --help", "show this screen", Tone::Blue),
+    ("-V, --version", "print version", Tone::Blue),
+];
+
+// (command, description) — subcommands listed above the options.
+const HELP_COMMANDS: &[(&str, &str)] = &[("check", "inspect staged changes, no API/commit")];
+
+pub fn print_help(plain: bool) {
+    print!("{}", render_help(plain, &Palette::detect()));
+}
+
+/// Plain help is used on request, under `NO_COLOR`/`--no-color`, or when the locale can't render the art.
+pub fn wants_plain_help(args: &Args) -> bool {
+    args.plain_help
+        || args.no_color
+        || std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty())
+        || !locale_is_unicode(locale_value().as_deref())
+}
+
+fn locale_value() -> Option<String> {
+    ["LC_ALL", "LC_CTYPE", "LANG"]
+        .iter()
+        .filter_map(|key| std::env::var(key).ok())
+        .find(|v| !v.is_empty())
+}
+
+fn locale_is_unicode(locale: Option<&str>) -> bool {
+    match locale {
+        // No locale at all is the norm on Windows and most CI images; assume UTF-8.
+        None => true,
+        Some(value) => {
+            let value = value.to_ascii_lowercase();
+            value.contains("utf-8") || value.contains("utf8")
+        }
+    }
+}
+
+fn render_help(plain: bool, palette: &Palette) -> String {
+    if plain {
+        render_plain_help()
+    } else {
+        render_color_help(palette)
+    }
+}
+
+fn help_flag_width() -> usize {
+    HELP_OPTIONS
+        .iter()
+        .map(|(flag, _, _)| flag.len())
+        .chain(HELP_COMMANDS.iter().map(|(cmd, _)| cmd.len()))
+        .max()
+        .unwrap_or(0)
+        + 3
+}
 
-    let o = orange;
-    let b = bold;
-    let r = reset;
-    let d = dim;
+fn render_plain_help() -> String {
+    let width = help_flag_width();
+    let mut out = String::new();
+
+    out.push('\n');
+    out.push_str("  yeti - domesticate your diff\n");
+    out.push_str("  AI-powered git commits\n");
+    out.push('\n');
+    out.push_str("  USAGE   yeti [COMMAND] [OPTIONS]\n");
+    out.push('\n');
+    out.push_str("  COMMANDS\n");
+    out.push('\n');
+    for (cmd, desc) in HELP_COMMANDS {
+        out.push_str(&format!("  {:<width$}{}\n", cmd, desc, width = width));
+    }
+    out.push('\n');
+    out.push_str("  OPTIONS\n");
+    out.push('\n');
+    for (flag, desc, _) in HELP_OPTIONS {
+        out.push_str(&format!("  {:<width$}{}\n", flag, desc, width = width));
+    }
+    out.push('\n');
+    out.push_str("  config -> ~/.config/yeti/config.toml\n");
+    out.push('\n');
+    out
+}
+
+fn render_color_help(p: &Palette) -> String {
+    let o = p.orange;
+    let b = p.bold;
+    let r = p.reset;
+    let d = p.dim;
+    let width = help_flag_width();
+    let mut out = String::new();
 
     // Face: all heavy box-drawing, no mixed weights
     // teeth sit directly between ┃ walls — no inner box needed
@@ -40,72 +235,70 @@ pub fn print_help() {
     //  ┣━━━━━━━━━━━┫   tagline     ← lip: flush heavy divider
     //  ┃ ▌▌▌▌▌▌▌▌▌ ┃               ← teeth between outer walls
     //  ┗━━━━━━━━━━━┛
+    let title = [
+        "██╗   ██╗███████╗████████╗██╗",
+        "╚██╗ ██╔╝██╔════╝╚══██╔══╝██║",
+        " ╚████╔╝ █████╗     ██║   ██║",
+        "  ╚██╔╝  ██╔══╝     ██║   ██║",
+        "   ██║   ███████╗   ██║   ██║",
+        "   ╚═╝   ╚══════╝   ╚═╝   ╚═╝",
+    ];
 
-    println!();
-    println!(
-        "  {o}{b}{}{r}  {o}{b}██╗   ██╗███████╗████████╗██╗{r}",
-        MASCOT_LINES[0]
-    );
-    println!(
-        "  {o}{b}{}{r}  {o}{b}╚██╗ ██╔╝██╔════╝╚══██╔══╝██║{r}",
-        MASCOT_LINES[1]
-    );
-    println!(
-        "  {o}{b}{}{r}  {o}{b} ╚████╔╝ █████╗     ██║   ██║{r}",
-        MASCOT_LINES[2]
-    );
-    println!(
-        "  {o}{b}{}{r}  {o}{b}  ╚██╔╝  ██╔══╝     ██║   ██║{r}",
-        MASCOT_LINES[3]
-    );
-    println!(
-        "  {o}{b}{}{r}  {o}{b}   ██║   ███████╗   ██║   ██║{r}",
-        MASCOT_LINES[4]
-    );
-    println!(
-        "  {o}{b}{}{r}  {o}{b}   ╚═╝   ╚══════╝   ╚═╝   ╚═╝{r}",
-        MASCOT_LINES[5]
-    );
-    println!(
-        "  {o}{b}{}{r}  {d}domesticate your diff{r}",
+    out.push('\n');
+    for (mascot, title_line) in MASCOT_LINES.iter().zip(title) {
+        out.push_str(&format!("  {o}{b}{mascot}{r}  {o}{b}{title_line}{r}\n"));
+    }
+    out.push_str(&format!(
+        "  {o}{b}{}{r}  {d}domesticate your diff{r}\n",
         MASCOT_LINES[6]
-    );
-    println!(
-        "  {o}{b}{}{r}  {d}AI-powered git commits{r}",
+    ));
+    out.push_str(&format!(
+        "  {o}{b}{}{r}  {d}AI-powered git commits{r}\n",
         MASCOT_LINES[7]
-    );
-    println!("  {o}{b}{}{r}", MASCOT_LINES[8]);
-    println!();
+    ));
+    out.push_str(&format!("  {o}{b}{}{r}\n", MASCOT_LINES[8]));
+    out.push('\n');
 
     // Usage
-    println!("{b}  {o}USAGE{r}   {d}yeti{r} {b}[OPTIONS]{r}");
-    println!();
+    out.push_str(&format!(
+        "{b}  {o}USAGE{r}   {d}yeti{r} {b}[COMMAND] [OPTIONS]{r}\n"
+    ));
+    out.push('\n');
+
+    // Commands
+    out.push_str(&format!("{b}  {o}COMMANDS{r}\n"));
+    out.push('\n');
+    for (cmd, desc) in HELP_COMMANDS {
+        out.push_str(&format!(
+            "  {g}{b}{:<width$}{r}{d}{desc}{r}\n",
+            cmd,
+            g = p.green,
+            width = width
+        ));
+    }
+    out.push('\n');
 
     // Options — concise single-line each
-    println!("{b}  {o}OPTIONS{r}");
-    println!();
-    println!(
-        "  {g}{b}--dry-run{r}       {d}preview commit, no write{r}",
-        g = green
-    );
-    println!(
-        "  {y}{b}--reset-key{r}     {d}force API key re-entry{r}",
-        y = yellow
-    );
-    println!(
-        "  {y}{b}--reset-cache{r}   {d}wipe stored config{r}",
-        y = yellow
-    );
-    println!(
-        "  {b2}{b}-h, --help{r}      {d}show this screen{r}",
-        b2 = blue
-    );
-    println!("  {b2}{b}-V, --version{r}   {d}print version{r}", b2 = blue);
-    println!();
+    out.push_str(&format!("{b}  {o}OPTIONS{r}\n"));
+    out.push('\n');
+    for (flag, desc, tone) in HELP_OPTIONS {
+        let color = match tone {
+            Tone::Green => p.green,
+            Tone::Yellow => p.yellow,
+            Tone::Blue => p.blue,
+        };
+        out.push_str(&format!(
+            "  {color}{b}{:<width$}{r}{d}{desc}{r}\n",
+            flag,
+            width = width
+        ));
+    }
+    out.push('\n');
 
     // Footer
-    println!("{d}  config → ~/.config/yeti/config.toml{r}");
-    println!();
+    out.push_str(&format!("{d}  config → ~/.config/yeti/config.toml{r}\n"));
+    out.push('\n');
+    out
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -117,6 +310,9 @@ pub fn print_help() {
     long_about = "A beast that camps between your working directory and Git, sniffing through messy diffs and leaving behind clean, intentional history."
 )]
 pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
     /// Show this help screen
     #[arg(short, long, action = clap::ArgAction::SetTrue)]
     pub help: bool,
@@ -124,6 +320,94 @@ pub struct Args {
     #[arg(long, help = "Sniff around without leaving tracks (preview only)")]
     pub dry_run: bool,
 
+    #[arg(
+        long,
+        conflicts_with = "dry_run",
+        help = "Write the commit even when default_dry_run is set in config"
+    )]
+    pub commit: bool,
+
+    #[arg(
+        long,
+        help = "After committing, print a Keep-a-Changelog entry (### Added/### Fixed/...) to stdout"
+    )]
+    pub changelog: bool,
+
+    #[arg(
+        long,
+        help = "After exiting, print to stderr what the model replied to the key and readiness checks"
+    )]
+    pub verbose: bool,
+
+    #[arg(
+        long,
+        help = "After exiting, print one line instead of the summary box: yeti|branch|sha|N files|+A -D|title"
+    )]
+    pub summary_line: bool,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Also write the final message to PATH, apart from anything printed on stdout (for editor integrations)"
+    )]
+    pub out: Option<PathBuf>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        conflicts_with = "out",
+        help = "Also write the final message to the already open file descriptor N (Unix)"
+    )]
+    pub fd: Option<u32>,
+
+    #[arg(
+        long,
+        help = "Commit without stopping for staged files above max_file_mb/max_total_mb or line-ending-only changes"
+    )]
+    pub force: bool,
+
+    #[arg(
+        long,
+        help = "Fold staged changes into HEAD and regenerate its message; exits early when nothing new is staged"
+    )]
+    pub amend: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["tracked_only", "staged_only"],
+        help = "Stage every change, untracked files included (default)"
+    )]
+    pub all: bool,
+
+    #[arg(
+        long,
+        conflicts_with = "staged_only",
+        help = "Stage edits to tracked files only; leave untracked files alone"
+    )]
+    pub tracked_only: bool,
+
+    #[arg(long, help = "Commit only what is already staged")]
+    pub staged_only: bool,
+
+    #[arg(
+        long,
+        help = "When nothing is staged but the work tree has changes, offer to stage them instead of stopping"
+    )]
+    pub stage_prompt: bool,
+
+    #[arg(
+        long,
+        conflicts_with_all = ["staged_only", "tracked_only"],
+        help = "Pick which changed files to stage from a checklist before generating"
+    )]
+    pub select: bool,
+
+    #[arg(
+        long,
+        help = "Use base_url without an API key: no key prompt and no Authorization header, even with a key set"
+    )]
+    pub allow_no_key: bool,
+
     #[arg(long, help = "Reset your scent (force API key re-entry)")]
     pub reset_key: bool,
 
@@ -132,4 +416,463 @@ pub struct Args {
         help = "Clear local yeti cache/config (removes stored key and settings)"
     )]
     pub reset_cache: bool,
+
+    #[arg(
+        long,
+        value_name = "KEYID",
+        num_args = 0..=1,
+        require_equals = true,
+        default_missing_value = "",
+        help = "GPG-sign the commit, optionally with a specific key id"
+    )]
+    pub sign: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "After committing, create annotated tag NAME on the new commit (skipped on --dry-run)"
+    )]
+    pub tag: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "MSG",
+        requires = "tag",
+        help = "Message for --tag instead of the generated title"
+    )]
+    pub tag_message: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Read the API key from this environment variable instead of CEREBRAS_API_KEY"
+    )]
+    pub key_env: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "KEY",
+        help = "Use this API key for this run only, over env and config. Insecure: other users can see it in ps and shell history; prefer --key-env outside CI"
+    )]
+    pub api_key: Option<SecretArg>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Generate with this model for this run, over config and the default; not saved"
+    )]
+    pub model: Option<String>,
+
+    #[arg(
+        long = "type",
+        value_name = "TYPE",
+        help = "Use this conventional type (feat, fix, ...) in the title, keeping the generated summary"
+    )]
+    pub commit_type: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Use this scope in the title, keeping the generated summary"
+    )]
+    pub scope: Option<String>,
+
+    #[arg(
+        long,
+        value_name = "T",
+        allow_hyphen_values = true,
+        help = "Sampling temperature from 0 to 2 for this run, over temperature in the config and the 0 default"
+    )]
+    pub temperature: Option<f32>,
+
+    #[arg(
+        long,
+        value_name = "CMD",
+        help = "Run this shell command (e.g. a formatter) before staging, over pre_stage_command; a failure aborts"
+    )]
+    pub stage_hook: Option<String>,
+
+    #[arg(
+        long,
+        help = "Skip pre-commit and commit-msg hooks for this commit, over skip_hooks"
+    )]
+    pub no_verify: bool,
+
+    #[arg(
+        long,
+        value_name = "NAME",
+        help = "Apply the [profiles.NAME] table from the config over the top-level settings"
+    )]
+    pub profile: Option<String>,
+
+    #[arg(
+        long,
+        help = "Mark staged files that .gitignore would exclude (force-added files)"
+    )]
+    pub include_ignored: bool,
+
+    #[arg(
+        long,
+        value_name = "N",
+        help = "Wrap the message body at N columns for this commit (0 disables), over wrap_width in the config"
+    )]
+    pub wrap: Option<usize>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u8).range(2..=8),
+        help = "Generate N messages (2-8) concurrently at slightly different temperatures and pick one from a list"
+    )]
+    pub pick: Option<u8>,
+
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 0,
+        help = "Include the diffs of the last N commits as background for the model, within a small budget"
+    )]
+    pub context_commits: usize,
+
+    #[arg(
+        long,
+        value_name = "PATH",
+        help = "Failing test or lint output that this change fixes; framed as the failure in the prompt and nudges a fix type"
+    )]
+    pub fixes_file: Option<PathBuf>,
+
+    #[arg(
+        long,
+        help = "Open the config file in $EDITOR, creating a commented template if missing"
+    )]
+    pub edit_config: bool,
+
+    #[arg(
+        long,
+        help = "Print the effective config, noting whether each value is global, repo or profile"
+    )]
+    pub print_config: bool,
+
+    #[arg(
+        long,
+        help = "Print the staged file table and exit, without calling the API or committing"
+    )]
+    pub list_staged: bool,
+
+    #[arg(
+        long,
+        help = "List the commits yeti logged (needs history_scope = \"repo\" or \"global\")"
+    )]
+    pub show_history: bool,
+
+    #[arg(
+        long,
+        help = "Delete the global commit log and this repository's, then exit"
+    )]
+    pub clear_history: bool,
+
+    #[arg(
+        long,
+        help = "Disable colored output everywhere, same as setting NO_COLOR"
+    )]
+    pub no_color: bool,
+
+    #[arg(long, help = "Show an ASCII-only help screen without colors")]
+    pub plain_help: bool,
+}
+
+/// A secret taken from the command line; `Debug` redacts it so printing
+/// `Args` never leaks the key.
+#[derive(Clone)]
+pub struct SecretArg(String);
+
+impl SecretArg {
+    pub fn expose(&self) -> &str {
+        &self.0
+    }
+}
+
+impl FromStr for SecretArg {
+    type Err = Infallible;
+
+    fn from_str(s: &str) -> std::result::Result<Self, Self::Err> {
+        Ok(Self(s.to_string()))
+    }
+}
+
+impl fmt::Debug for SecretArg {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SecretArg(***)")
+    }
+}
+
+impl Args {
+    /// Per-run overrides layered over the loaded config; never persisted.
+    pub fn apply_to(&self, config: &mut Config) {
+        if let Some(name) = self.key_env.clone() {
+            config.key_env = Some(name);
+        }
+        if self.include_ignored {
+            config.include_ignored = Some(true);
+        }
+        if let Some(width) = self.wrap {
+            config.wrap_width = Some(width);
+        }
+        if let Some(model) = self.model.clone() {
+            config.model = Some(model);
+        }
+        if let Some(temperature) = self.temperature {
+            config.temperature = Some(temperature);
+        }
+        if let Some(command) = self.stage_hook.clone() {
+            config.pre_stage_command = Some(command);
+        }
+        if self.no_verify {
+            config.skip_hooks = Some(true);
+        }
+        if self.allow_no_key {
+            config.allow_no_key = Some(true);
+        }
+    }
+
+    /// `--dry-run` always previews; otherwise `default_dry_run` does unless
+    /// `--commit` asks for a real commit.
+    pub fn is_dry_run(&self, config: &Config) -> bool {
+        self.dry_run || (config.default_dry_run() && !self.commit)
+    }
+
+    pub fn stage_mode(&self) -> StageMode {
+        if self.staged_only {
+            StageMode::StagedOnly
+        } else if self.tracked_only {
+            StageMode::Tracked
+        } else {
+            StageMode::All
+        }
+    }
+
+    /// Where `--out` or `--fd` sends the final message; a descriptor is
+    /// reached through `/dev/fd`.
+    pub fn message_out(&self) -> Option<PathBuf> {
+        self.out
+            .clone()
+            .or_else(|| self.fd.map(|fd| PathBuf::from(format!("/dev/fd/{}", fd))))
+    }
+
+    /// Rejects malformed flag values before the TUI takes over the terminal.
+    pub fn validate(&self) -> Result<()> {
+        if cfg!(not(unix)) && self.fd.is_some() {
+            return Err(YetiError::InvalidArgs(
+                "--fd needs /dev/fd, which only Unix systems have; use --out".into(),
+            ));
+        }
+        if let Some(key_id) = self.sign.as_deref() {
+            git::validate_signing_key(key_id)?;
+        }
+        if let Some(name) = self.tag.as_deref() {
+            git::validate_tag_name(name)?;
+        }
+        if let Some(kind) = self.commit_type.as_deref()
+            && !crate::prompt::COMMIT_TYPES.contains(&kind)
+        {
+            return Err(YetiError::InvalidArgs(format!(
+                "unknown --type {}; use one of {}",
+                kind,
+                crate::prompt::COMMIT_TYPES.join(", ")
+            )));
+        }
+        if let Some(scope) = self.scope.as_deref()
+            && (scope.is_empty()
+                || !scope
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_'))
+        {
+            return Err(YetiError::InvalidArgs(format!(
+                "--scope {:?} must be letters, digits, '-' or '_'",
+                scope
+            )));
+        }
+        if let Some(t) = self.temperature
+            && !(0.0..=crate::cerebras::MAX_TEMPERATURE).contains(&t)
+        {
+            return Err(YetiError::InvalidArgs(format!(
+                "--temperature {} is out of range; use a value from 0 to {}",
+                t,
+                crate::cerebras::MAX_TEMPERATURE
+            )));
+        }
+        if let Some(path) = &self.fixes_file
+            && !path.is_file()
+        {
+            return Err(YetiError::InvalidArgs(format!(
+                "--fixes-file {} is not a readable file",
+                path.display()
+            )));
+        }
+        Ok(())
+    }
+}
+
+/// Writes the final message for `--out`/`--fd`, ending in a newline.
+pub fn write_message_out(path: &Path, message: &str) -> Result<()> {
+    fs::write(path, format!("{}\n", message.trim_end())).map_err(|e| {
+        YetiError::IoError(format!(
+            "Could not write the message to {}: {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+#[derive(Subcommand, Debug, Clone)]
+pub enum Command {
+    /// Inspect staged changes and hints without calling the API or committing
+    Check,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Args, locale_is_unicode, render_help, wants_plain_help, write_message_out};
+    use crate::color::{Palette, color_enabled_for};
+    use crate::config::Config;
+    use crate::git::StageMode;
+    use crate::tui::render_file_table;
+    use clap::Parser;
+
+    #[test]
+    fn no_color_flag_strips_ansi_from_help_and_tables() {
+        let args = Args::try_parse_from(["yeti", "--no-color"]).unwrap();
+        let palette = Palette::for_color(color_enabled_for(args.no_color, None, true));
+
+        assert!(wants_plain_help(&args));
+        assert!(!render_help(false, &palette).contains('\x1b'));
+        assert!(!render_file_table(&[], &palette).contains('\x1b'));
+
+        let colored = Palette::for_color(color_enabled_for(false, None, true));
+        assert!(render_help(false, &colored).contains('\x1b'));
+    }
+
+    #[test]
+    fn wrap_flag_overrides_configured_width() {
+        let configured = || Config {
+            wrap_width: Some(100),
+            ..Config::default()
+        };
+
+        let mut config = configured();
+        Args::try_parse_from(["yeti", "--wrap", "50"])
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(config.wrap_width(), Some(50));
+
+        let mut config = configured();
+        Args::try_parse_from(["yeti", "--wrap", "0"])
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(config.wrap_width(), None);
+
+        let mut config = configured();
+        Args::try_parse_from(["yeti"])
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(config.wrap_width(), Some(100));
+        assert_eq!(Config::default().wrap_width(), Some(72));
+    }
+
+    #[test]
+    fn default_dry_run_previews_until_commit_is_passed() {
+        let cautious = Config {
+            default_dry_run: Some(true),
+            ..Config::default()
+        };
+        let parse = |argv: &[&str]| Args::try_parse_from(argv).unwrap();
+
+        assert!(parse(&["yeti"]).is_dry_run(&cautious));
+        assert!(!parse(&["yeti", "--commit"]).is_dry_run(&cautious));
+        assert!(!parse(&["yeti"]).is_dry_run(&Config::default()));
+        assert!(parse(&["yeti", "--dry-run"]).is_dry_run(&Config::default()));
+        assert!(Args::try_parse_from(["yeti", "--commit", "--dry-run"]).is_err());
+    }
+
+    #[test]
+    fn api_key_flag_is_redacted_in_debug_output() {
+        let args = Args::try_parse_from(["yeti", "--api-key", "csk-secret"]).unwrap();
+
+        assert_eq!(
+            args.api_key.as_ref().map(|k| k.expose()),
+            Some("csk-secret")
+        );
+        assert!(!format!("{:?}", args).contains("csk-secret"));
+    }
+
+    #[test]
+    fn plain_help_is_ascii_without_escape_sequences() {
+        let help = render_help(true, &Palette::ansi());
+
+        assert!(help.contains("--dry-run"));
+        assert!(!help.contains('\x1b'));
+        assert!(help.is_ascii());
+    }
+
+    #[test]
+    fn staging_flags_map_to_stage_modes() {
+        let mode = |flags: &[&str]| {
+            let argv = std::iter::once("yeti").chain(flags.iter().copied());
+            Args::try_parse_from(argv).map(|a| a.stage_mode())
+        };
+
+        assert_eq!(mode(&[]).unwrap(), StageMode::All);
+        assert_eq!(mode(&["--all"]).unwrap(), StageMode::All);
+        assert_eq!(mode(&["--tracked-only"]).unwrap(), StageMode::Tracked);
+        assert_eq!(mode(&["--staged-only"]).unwrap(), StageMode::StagedOnly);
+        assert!(mode(&["--all", "--staged-only"]).is_err());
+        assert!(mode(&["--tracked-only", "--staged-only"]).is_err());
+    }
+
+    #[test]
+    fn locale_detection_recognizes_utf8_variants() {
+        assert!(locale_is_unicode(None));
+        assert!(locale_is_unicode(Some("en_US.UTF-8")));
+        assert!(locale_is_unicode(Some("C.utf8")));
+        assert!(!locale_is_unicode(Some("C")));
+        assert!(!locale_is_unicode(Some("POSIX")));
+    }
+
+    #[test]
+    fn colorless_palette_strips_escape_sequences_from_full_help() {
+        let help = render_help(false, &Palette::plain());
+
+        assert!(help.contains("OPTIONS"));
+        assert!(!help.contains('\x1b'));
+    }
+
+    #[test]
+    fn out_flag_writes_only_the_message_to_its_file() {
+        let path = std::env::temp_dir().join(format!("yeti-out-test-{}", std::process::id()));
+        let args =
+            Args::try_parse_from(["yeti".as_ref(), "--out".as_ref(), path.as_os_str()]).unwrap();
+        let out = args.message_out().unwrap();
+        assert_eq!(out, path);
+
+        write_message_out(&out, "fix: keep the stage\n\n- explain why\n\n").unwrap();
+        assert_eq!(
+            std::fs::read_to_string(&path).unwrap(),
+            "fix: keep the stage\n\n- explain why\n"
+        );
+        let _ = std::fs::remove_file(&path);
+
+        let args = Args::try_parse_from(["yeti", "--fd", "3"]).unwrap();
+        assert_eq!(
+            args.message_out().unwrap(),
+            std::path::Path::new("/dev/fd/3")
+        );
+        assert_eq!(args.validate().is_ok(), cfg!(unix));
+        assert!(Args::try_parse_from(["yeti", "--fd", "3", "--out", "msg"]).is_err());
+        assert!(
+            Args::try_parse_from(["yeti"])
+                .unwrap()
+                .message_out()
+                .is_none()
+        );
+    }
 }
@@ -1,12 +1,41 @@
-use crate::config::Config;
 use crate::error::{Result, YetiError};
-use crate::prompt::SYSTEM_PROMPT;
+use crate::prompt::{is_conventional_title, split_gitmoji};
+use crate::provider::Generation;
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::time::Duration;
 
-const API_URL: &str = "https://api.cerebras.ai/v1/chat/completions";
+pub const API_URL: &str = "https://api.cerebras.ai/v1/chat/completions";
 const REQUEST_TIMEOUT_SECS: u64 = 60;
+const MAX_TITLE_CHARS: usize = 72;
+pub const DEFAULT_MAX_RETRIES: u32 = 3;
+const RETRY_BASE_MS: u64 = 500;
+// A Retry-After longer than this is treated as "give up", not "wait".
+const MAX_RETRY_AFTER_SECS: u64 = 30;
+// Backoff sleeps in slices this long so a cancel is noticed promptly.
+const RETRY_SLICE_MS: u64 = 50;
+const REPLY_SNIPPET_CHARS: usize = 60;
+/// How much of a non-JSON error body is kept in the message.
+const ERROR_BODY_CHARS: usize = 300;
+
+/// Where API calls go: the run's shared agent, the chat completions URL, and
+/// how many times a 429/5xx is retried before giving up.
+#[derive(Clone, Copy)]
+pub struct Endpoint<'a> {
+    pub agent: &'a ureq::Agent,
+    pub url: &'a str,
+    pub max_retries: u32,
+}
+
+/// `{base_url}/chat/completions` for an OpenAI-compatible gateway, else Cerebras.
+pub fn chat_url(base_url: Option<&str>) -> String {
+    match base_url.map(|base| base.trim().trim_end_matches('/')) {
+        Some(base) if !base.is_empty() => format!("{}/chat/completions", base),
+        _ => API_URL.to_string(),
+    }
+}
 
 #[derive(Debug, Serialize)]
 struct ChatRequest {
@@ -17,6 +46,8 @@ struct ChatRequest {
     #[serde(skip_serializing_if = "Option::is_none")]
     max_completion_tokens: Option<u32>,
     stream: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    n: Option<u8>,
 }
 
 #[derive(Debug, Serialize)]
@@ -33,6 +64,8 @@ struct StreamResponse {
 #[derive(Debug, Deserialize)]
 struct StreamChoice {
     delta: Delta,
+    #[serde(default)]
+    finish_reason: Option<String>,
 }
 
 #[derive(Debug, Deserialize)]
@@ -41,6 +74,24 @@ struct Delta {
     content: Option<String>,
 }
 
+#[derive(Debug, Deserialize)]
+struct ChatResponse {
+    choices: Vec<ChatChoice>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChatChoice {
+    message: ChoiceMessage,
+    #[serde(default)]
+    finish_reason: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChoiceMessage {
+    #[serde(default)]
+    content: Option<String>,
+}
+
 #[derive(Debug, Deserialize)]
 struct StreamErrorResponse {
     error: StreamError,
@@ -51,53 +102,198 @@ struct StreamError {
     message: String,
 }
 
-pub fn generate_commit_message(
-    api_key: &str,
+#[derive(Debug, Deserialize)]
+struct ModelsResponse {
+    data: Vec<ModelEntry>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ModelEntry {
+    id: String,
+}
+
+#[derive(Debug, Clone)]
+pub struct Completion {
+    pub content: String,
+    /// The provider stopped because it hit `max_completion_tokens`.
+    pub truncated: bool,
+}
+
+/// Highest sampling temperature accepted from `--temperature` or the config.
+pub const MAX_TEMPERATURE: f32 = 2.0;
+
+/// Floor on the temperature of a `candidates` request: identical samples at
+/// temperature 0 would make the extra choices pointless.
+pub const BEST_OF_MIN_TEMPERATURE: f32 = 0.5;
+
+/// The streaming request `generate_commit_message` sends.
+fn stream_request(
+    system_prompt: &str,
     model: &str,
     user_prompt: &str,
-    on_chunk: impl Fn(&str),
-) -> Result<String> {
-    let request = ChatRequest {
+    max_completion_tokens: u32,
+    temperature: f32,
+) -> ChatRequest {
+    ChatRequest {
         model: model.to_string(),
         messages: vec![
             Message {
                 role: "system".to_string(),
-                content: SYSTEM_PROMPT.to_string(),
+                content: system_prompt.to_string(),
             },
             Message {
                 role: "user".to_string(),
                 content: user_prompt.to_string(),
             },
         ],
-        temperature: Some(0.0),
-        max_completion_tokens: Some(500),
+        temperature: Some(temperature),
+        max_completion_tokens: Some(max_completion_tokens),
         stream: true,
-    };
+        n: None,
+    }
+}
 
-    let body = serde_json::to_string(&request)?;
+pub fn generate_commit_message(
+    endpoint: Endpoint<'_>,
+    api_key: &str,
+    generation: &Generation<'_>,
+    cancel: &AtomicBool,
+    on_chunk: impl Fn(&str),
+) -> Result<Completion> {
+    let body = serde_json::to_string(&stream_request(
+        generation.system_prompt,
+        generation.model,
+        generation.user_prompt,
+        generation.max_completion_tokens,
+        generation.temperature,
+    ))?;
 
-    let agent = http_agent();
-    let response = agent
-        .post(API_URL)
-        .header("Authorization", &format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .send(&body)
-        .map_err(handle_ureq_error)?;
+    let response = post_chat(endpoint, api_key, &body, cancel)?;
 
     let status = response.status();
     if !status.is_success() {
-        let status_code = status.as_u16();
         let body_text = response.into_body().read_to_string().unwrap_or_default();
-        return Err(YetiError::ApiError {
-            status: status_code,
-            message: body_text,
-        });
+        return Err(api_error(status.as_u16(), &body_text));
     }
 
-    let mut full_content = String::new();
     let reader = BufReader::new(response.into_body().into_reader());
+    read_stream(reader, cancel, on_chunk)
+}
+
+/// The non-streaming request `generate_best_of` sends, at the configured
+/// temperature raised to `BEST_OF_MIN_TEMPERATURE`.
+fn best_of_request(generation: &Generation<'_>) -> ChatRequest {
+    ChatRequest {
+        model: generation.model.to_string(),
+        messages: vec![
+            Message {
+                role: "system".to_string(),
+                content: generation.system_prompt.to_string(),
+            },
+            Message {
+                role: "user".to_string(),
+                content: generation.user_prompt.to_string(),
+            },
+        ],
+        temperature: Some(generation.temperature.max(BEST_OF_MIN_TEMPERATURE)),
+        max_completion_tokens: Some(generation.max_completion_tokens),
+        stream: false,
+        n: Some(generation.candidates),
+    }
+}
+
+/// Requests `n` completions in one non-streaming call and keeps the best-scoring one.
+pub fn generate_best_of(
+    endpoint: Endpoint<'_>,
+    api_key: &str,
+    generation: &Generation<'_>,
+    cancel: &AtomicBool,
+) -> Result<Completion> {
+    let body = serde_json::to_string(&best_of_request(generation))?;
+
+    let response = post_chat(endpoint, api_key, &body, cancel)?;
+
+    let status = response.status();
+    let body_text = response
+        .into_body()
+        .read_to_string()
+        .map_err(|e| YetiError::NetworkError(e.to_string()))?;
+    if !status.is_success() {
+        return Err(api_error(status.as_u16(), &body_text));
+    }
+
+    let parsed: ChatResponse = serde_json::from_str(&body_text)?;
+    let candidates = parsed
+        .choices
+        .into_iter()
+        .filter_map(|choice| {
+            let content = choice.message.content?;
+            Some(Completion {
+                truncated: choice.finish_reason.as_deref() == Some("length"),
+                content,
+            })
+        })
+        .collect();
+
+    pick_best_candidate(candidates).ok_or_else(|| YetiError::ApiError {
+        status: 500,
+        message: "API returned an empty response".to_string(),
+    })
+}
+
+/// Higher is better: a conventional title within 50 chars, a body, and no
+/// token-limit cut-off.
+fn score_candidate(candidate: &Completion) -> i32 {
+    if candidate.content.trim().is_empty() {
+        return i32::MIN;
+    }
+    let (title, body) = sanitize_message(&candidate.content, &[]);
+    let mut score = 0;
+    if is_conventional_title(&title) {
+        score += 100;
+    }
+    let title_len = title.chars().count() as i32;
+    score -= (title_len - 50).max(0) * 2;
+    if body.is_some() {
+        score += 10;
+    }
+    if candidate.truncated {
+        score -= 50;
+    }
+    score
+}
+
+/// Best candidate by `score_candidate`; ties go to the earliest.
+fn pick_best_candidate(candidates: Vec<Completion>) -> Option<Completion> {
+    let mut best: Option<(i32, Completion)> = None;
+    for candidate in candidates {
+        let score = score_candidate(&candidate);
+        if score == i32::MIN {
+            continue;
+        }
+        if best.as_ref().is_none_or(|(top, _)| score > *top) {
+            best = Some((score, candidate));
+        }
+    }
+    best.map(|(_, candidate)| candidate)
+}
+
+/// Stops consuming as soon as `cancel` is set, returning whatever arrived so far.
+fn read_stream(
+    reader: impl BufRead,
+    cancel: &AtomicBool,
+    on_chunk: impl Fn(&str),
+) -> Result<Completion> {
+    let mut full_content = String::new();
+    let mut truncated = false;
 
     for line_result in reader.lines() {
+        if cancel.load(Ordering::Relaxed) {
+            return Ok(Completion {
+                content: full_content,
+                truncated,
+            });
+        }
         let line = match line_result {
             Ok(l) => l,
             Err(e) => return Err(YetiError::NetworkError(e.to_string())),
@@ -120,11 +316,14 @@ pub fn generate_commit_message(
         let stream_resp = serde_json::from_str::<StreamResponse>(data);
         match stream_resp {
             Ok(r) => {
-                if let Some(choice) = r.choices.first()
-                    && let Some(content) = &choice.delta.content
-                {
-                    on_chunk(content);
-                    full_content.push_str(content);
+                if let Some(choice) = r.choices.first() {
+                    if let Some(content) = &choice.delta.content {
+                        on_chunk(content);
+                        full_content.push_str(content);
+                    }
+                    if choice.finish_reason.as_deref() == Some("length") {
+                        truncated = true;
+                    }
                 }
             }
             Err(_) => {
@@ -146,12 +345,38 @@ pub fn generate_commit_message(
         });
     }
 
-    Ok(full_content)
+    Ok(Completion {
+        content: full_content,
+        truncated,
+    })
+}
+
+/// One line on what a non-streaming check got back, for `--verbose`: a
+/// working model replies, one that authenticated but errored says so.
+fn describe_reply(body: &str) -> String {
+    let clip = |text: &str| -> String { text.trim().chars().take(REPLY_SNIPPET_CHARS).collect() };
+    let Ok(parsed) = serde_json::from_str::<ChatResponse>(body) else {
+        return format!(
+            "authenticated, but the reply was not a chat completion: {}",
+            clip(body)
+        );
+    };
+    let Some(choice) = parsed.choices.into_iter().next() else {
+        return "authenticated, but the model returned no choices".to_string();
+    };
+    match choice.message.content.as_deref().map(str::trim) {
+        Some(content) if !content.is_empty() => format!("replied \"{}\"", clip(content)),
+        _ => format!(
+            "authenticated, but the reply was empty (finish_reason: {})",
+            choice.finish_reason.as_deref().unwrap_or("none")
+        ),
+    }
 }
 
-pub fn validate_api_key(api_key: &str) -> Result<bool> {
+/// Checks the key with a tiny request; returns [`describe_reply`]'s note.
+pub fn validate_api_key(endpoint: Endpoint<'_>, api_key: &str, model: &str) -> Result<String> {
     let request = ChatRequest {
-        model: Config::default_model().to_string(),
+        model: model.to_string(),
         messages: vec![Message {
             role: "user".to_string(),
             content: "Hi".to_string(),
@@ -159,31 +384,36 @@ pub fn validate_api_key(api_key: &str) -> Result<bool> {
         temperature: None,
         max_completion_tokens: Some(10),
         stream: false,
+        n: None,
     };
 
     let body = serde_json::to_string(&request)?;
 
-    let agent = http_agent();
-    let response = agent
-        .post(API_URL)
-        .header("Authorization", &format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .send(&body);
+    let response = post_chat(endpoint, api_key, &body, &AtomicBool::new(false));
 
     match response {
-        Ok(resp) if resp.status().is_success() => Ok(true),
+        Ok(mut resp) if resp.status().is_success() => {
+            // Read to the end so the connection goes back to the agent's pool.
+            let body = resp.body_mut().read_to_string().unwrap_or_default();
+            Ok(describe_reply(&body))
+        }
         Ok(resp) if resp.status().as_u16() == 401 => {
             Err(YetiError::InvalidApiKey("Invalid API key".to_string()))
         }
-        Ok(resp) => Err(YetiError::ApiError {
-            status: resp.status().as_u16(),
-            message: "API request failed".to_string(),
-        }),
-        Err(e) => Err(handle_ureq_error(e)),
+        Ok(mut resp) => {
+            let body = resp.body_mut().read_to_string().unwrap_or_default();
+            Err(api_error(resp.status().as_u16(), &body))
+        }
+        Err(e) => Err(e),
     }
 }
 
-pub fn check_provider_ready(api_key: &str, model: &str) -> Result<()> {
+pub fn check_provider_ready(
+    endpoint: Endpoint<'_>,
+    api_key: &str,
+    model: &str,
+    cancel: &AtomicBool,
+) -> Result<String> {
     let request = ChatRequest {
         model: model.to_string(),
         messages: vec![Message {
@@ -193,36 +423,163 @@ pub fn check_provider_ready(api_key: &str, model: &str) -> Result<()> {
         temperature: None,
         max_completion_tokens: Some(4),
         stream: false,
+        n: None,
     };
 
     let body = serde_json::to_string(&request)?;
-    let agent = http_agent();
-    let response = agent
-        .post(API_URL)
-        .header("Authorization", &format!("Bearer {}", api_key))
-        .header("Content-Type", "application/json")
-        .send(&body)
-        .map_err(handle_ureq_error)?;
+    let response = post_chat(endpoint, api_key, &body, cancel)?;
 
-    if !response.status().is_success() {
-        return Err(YetiError::ApiError {
-            status: response.status().as_u16(),
-            message: "Provider readiness check failed".to_string(),
-        });
+    let status = response.status().as_u16();
+    let body = response.into_body().read_to_string().unwrap_or_default();
+    if !(200..300).contains(&status) {
+        return Err(api_error(status, &body));
     }
+    Ok(describe_reply(&body))
+}
+
+/// `/models` next to the chat completions URL, e.g. `https://api.cerebras.ai/v1/models`.
+fn models_url(chat_url: &str) -> String {
+    let base = chat_url
+        .strip_suffix("/chat/completions")
+        .unwrap_or(chat_url);
+    format!("{}/models", base)
+}
+
+/// Model IDs the endpoint serves.
+pub fn list_models(endpoint: Endpoint<'_>, api_key: &str) -> Result<Vec<String>> {
+    let response = authorized(endpoint.agent.get(models_url(endpoint.url)), api_key)
+        .call()
+        .map_err(handle_ureq_error)?;
 
-    Ok(())
+    let status = response.status();
+    let body_text = response
+        .into_body()
+        .read_to_string()
+        .map_err(|e| YetiError::NetworkError(e.to_string()))?;
+    match status.as_u16() {
+        200..=299 => {}
+        401 => return Err(YetiError::InvalidApiKey("Invalid API key".to_string())),
+        code => return Err(api_error(code, &body_text)),
+    }
+    let parsed: ModelsResponse = serde_json::from_str(&body_text)?;
+    Ok(parsed.data.into_iter().map(|m| m.id).collect())
 }
 
-fn http_agent() -> ureq::Agent {
+/// One agent per run, shared by validation, preflight and generation so the
+/// connection pool and TLS sessions carry over between calls.
+pub fn http_agent() -> ureq::Agent {
     ureq::Agent::config_builder()
         .timeout_global(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))
         .timeout_per_call(Some(Duration::from_secs(REQUEST_TIMEOUT_SECS)))
+        // Error statuses come back as responses so Retry-After can be read.
+        .http_status_as_error(false)
         .build()
         .new_agent()
 }
 
+/// Adds the bearer token; an empty key (`allow_no_key`) sends no header at all.
+fn authorized<B>(request: ureq::RequestBuilder<B>, api_key: &str) -> ureq::RequestBuilder<B> {
+    if api_key.is_empty() {
+        request
+    } else {
+        request.header("Authorization", &format!("Bearer {}", api_key))
+    }
+}
+
+/// Posts `body`, retrying 429 and 5xx responses with exponential backoff (or
+/// the server's `Retry-After`). Only the request is retried, never a stream
+/// already being read, so no chunk is ever emitted twice. `cancel` is checked
+/// before every attempt and throughout the backoff.
+fn post_chat(
+    endpoint: Endpoint<'_>,
+    api_key: &str,
+    body: &str,
+    cancel: &AtomicBool,
+) -> Result<ureq::http::Response<ureq::Body>> {
+    let mut attempt = 0;
+    loop {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(YetiError::Cancelled);
+        }
+        let mut response = authorized(endpoint.agent.post(endpoint.url), api_key)
+            .header("Content-Type", "application/json")
+            .send(body)
+            .map_err(handle_ureq_error)?;
+        let status = response.status().as_u16();
+        if !(status == 429 || status >= 500) || attempt >= endpoint.max_retries {
+            return Ok(response);
+        }
+        let retry_after = response
+            .headers()
+            .get("retry-after")
+            .and_then(|value| value.to_str().ok())
+            .map(str::to_string);
+        let Some(delay) = retry_delay(attempt, retry_after.as_deref()) else {
+            return Ok(response);
+        };
+        // Drain so the connection goes back to the pool for the retry.
+        let _ = response.body_mut().read_to_string();
+        if !sleep_unless_cancelled(delay, cancel) {
+            return Err(YetiError::Cancelled);
+        }
+        attempt += 1;
+    }
+}
+
+/// Waits out `delay`, returning `false` as soon as `cancel` is set.
+fn sleep_unless_cancelled(delay: Duration, cancel: &AtomicBool) -> bool {
+    let slice = Duration::from_millis(RETRY_SLICE_MS);
+    let mut left = delay;
+    while !left.is_zero() {
+        if cancel.load(Ordering::Relaxed) {
+            return false;
+        }
+        let step = left.min(slice);
+        std::thread::sleep(step);
+        left -= step;
+    }
+    !cancel.load(Ordering::Relaxed)
+}
+
+/// 500ms, 1s, 2s, ... unless the server named a wait in seconds; `None` when
+/// that wait is too long to sit through.
+fn retry_delay(attempt: u32, retry_after: Option<&str>) -> Option<Duration> {
+    match retry_after.and_then(|value| value.trim().parse::<u64>().ok()) {
+        Some(secs) if secs > MAX_RETRY_AFTER_SECS => None,
+        Some(secs) => Some(Duration::from_secs(secs)),
+        None => Some(Duration::from_millis(RETRY_BASE_MS << attempt.min(10))),
+    }
+}
+
+/// A non-2xx reply as an error carrying the provider's own explanation:
+/// `error.message` from an OpenAI-style JSON body, else the body itself.
+fn api_error(status: u16, body: &str) -> YetiError {
+    let message = serde_json::from_str::<StreamErrorResponse>(body)
+        .ok()
+        .map(|reply| reply.error.message.trim().to_string())
+        .filter(|message| !message.is_empty())
+        .unwrap_or_else(|| body.trim().chars().take(ERROR_BODY_CHARS).collect());
+    YetiError::ApiError {
+        status,
+        message: if message.is_empty() {
+            format!("Provider returned HTTP {}", status)
+        } else {
+            message
+        },
+    }
+}
+
 fn handle_ureq_error(e: ureq::Error) -> YetiError {
+    if let ureq::Error::StatusCode(code) = e
+        && code != 401
+        && code != 429
+    {
+        return YetiError::ApiError {
+            status: code,
+            message: format!("Provider returned HTTP {}", code),
+        };
+    }
+
     let err_str = e.to_string();
     if err_str.contains("401") {
         YetiError::InvalidApiKey("Authentication failed".to_string())
@@ -236,7 +593,115 @@ fn handle_ureq_error(e: ureq::Error) -> YetiError {
     }
 }
 
-fn sanitize_message(raw: &str) -> (String, Option<String>) {
+/// The provider refused the key itself (revoked, mistyped), as opposed to the request.
+pub fn is_key_rejected(err: &YetiError) -> bool {
+    matches!(
+        err,
+        YetiError::InvalidApiKey(_) | YetiError::ApiError { status: 401, .. }
+    )
+}
+
+/// Whether a failure is worth retrying on another model (rate limits, overload).
+pub fn is_overloaded(err: &YetiError) -> bool {
+    matches!(err, YetiError::ApiError { status, .. } if *status == 429 || *status >= 500)
+}
+
+/// Tries each model in order, moving on only when the previous one is overloaded.
+/// Returns the model that succeeded alongside its output; stops with
+/// [`YetiError::Cancelled`] once `cancel` is set.
+pub fn generate_with_fallback<T, F>(
+    models: &[String],
+    cancel: &AtomicBool,
+    mut attempt: F,
+) -> Result<(String, T)>
+where
+    F: FnMut(&str) -> Result<T>,
+{
+    let mut last_err = None;
+    for model in models {
+        if cancel.load(Ordering::Relaxed) {
+            return Err(YetiError::Cancelled);
+        }
+        match attempt(model) {
+            Ok(content) => return Ok((model.clone(), content)),
+            Err(e) if is_overloaded(&e) => last_err = Some(e),
+            Err(e) => return Err(e),
+        }
+    }
+    Err(last_err.unwrap_or_else(|| YetiError::ApiError {
+        status: 500,
+        message: "No models configured".to_string(),
+    }))
+}
+
+/// Openers models put before the message, matched case-insensitively at the
+/// start of a line; `preamble_phrases` in the config adds more.
+const PREAMBLE_PHRASES: [&str; 12] = [
+    "here is",
+    "here's",
+    "sure",
+    "certainly",
+    "of course",
+    "okay",
+    "absolutely",
+    "below is",
+    "commit message",
+    "suggested commit",
+    "proposed commit",
+    "the commit message",
+];
+
+/// Lines a conventional title may be preceded by before it is no longer
+/// taken for the real start of the message.
+const MAX_PREAMBLE_LINES: usize = 3;
+
+fn is_preamble(line: &str, extra: &[String]) -> bool {
+    let line = line
+        .trim_start_matches(['*', '_', '>', '"', ' '])
+        .to_lowercase()
+        .replace('\u{2019}', "'");
+    PREAMBLE_PHRASES
+        .iter()
+        .copied()
+        .chain(extra.iter().map(|p| p.trim()))
+        .filter(|phrase| !phrase.is_empty())
+        .any(|phrase| {
+            line.strip_prefix(&phrase.to_lowercase())
+                .is_some_and(|rest| !rest.starts_with(char::is_alphanumeric))
+        })
+}
+
+/// Drops a chatty opener ("Here's the commit message:") so it can't become
+/// the title: a conventional title a few lines in marks the real start,
+/// otherwise leading lines that match a preamble phrase go.
+fn strip_preamble<'a>(mut lines: Vec<&'a str>, extra: &[String]) -> Vec<&'a str> {
+    if let Some(first) = lines.first()
+        && is_preamble(first, extra)
+        && let Some((_, rest)) = first.split_once(": ")
+        && is_conventional_title(rest)
+    {
+        lines[0] = rest;
+        return lines;
+    }
+    if let Some(start) = lines
+        .iter()
+        .take(MAX_PREAMBLE_LINES + 1)
+        .position(|line| is_conventional_title(line))
+    {
+        lines.drain(..start);
+        return lines;
+    }
+    let keep = lines.len().saturating_sub(1);
+    let drop = lines
+        .iter()
+        .take(keep)
+        .take_while(|line| is_preamble(line, extra))
+        .count();
+    lines.drain(..drop);
+    lines
+}
+
+fn sanitize_message(raw: &str, preamble_phrases: &[String]) -> (String, Option<String>) {
     let cleaned: String = raw
         .chars()
         .filter(|c| !c.is_control() || *c == '\n')
@@ -249,13 +714,15 @@ fn sanitize_message(raw: &str) -> (String, Option<String>) {
         .filter(|l| !l.starts_with('#'))
         .filter(|l| !l.starts_with("```"))
         .collect();
+    let lines = strip_preamble(lines, preamble_phrases);
 
     if lines.is_empty() {
         return ("chore: update files".to_string(), None);
     }
 
-    let title = lines[0].to_string();
-    let title = title.chars().take(72).collect();
+    let (gitmoji, rest) = split_gitmoji(lines[0]);
+    let rest: String = rest.chars().take(title_room(gitmoji)).collect();
+    let title = format!("{}{}", gitmoji, rest.trim_end());
 
     let body_lines: Vec<&str> = lines
         .iter()
@@ -274,18 +741,774 @@ fn sanitize_message(raw: &str) -> (String, Option<String>) {
     (title, body)
 }
 
-pub fn parse_commit_message(raw: &str) -> (String, Option<String>) {
-    sanitize_message(raw)
+#[derive(Debug, Clone)]
+pub struct MessageOptions {
+    pub strip_trailing_period: bool,
+    pub strip_line_references: bool,
+    pub wrap_width: Option<usize>,
+    /// Openers to drop before the title, on top of the built-in ones.
+    pub preamble_phrases: Vec<String>,
+}
+
+impl Default for MessageOptions {
+    fn default() -> Self {
+        Self {
+            strip_trailing_period: true,
+            strip_line_references: true,
+            wrap_width: Some(72),
+            preamble_phrases: Vec::new(),
+        }
+    }
+}
+
+/// Greedy word wrap of each body line at `width` columns; words longer than
+/// the width (URLs, paths) stay on a line of their own rather than being split.
+pub fn wrap_body(body: &str, width: usize) -> String {
+    body.lines()
+        .map(|line| {
+            let mut wrapped: Vec<String> = Vec::new();
+            let mut current = String::new();
+            for word in line.split_whitespace() {
+                if !current.is_empty() && current.chars().count() + 1 + word.chars().count() > width
+                {
+                    wrapped.push(std::mem::take(&mut current));
+                }
+                if !current.is_empty() {
+                    current.push(' ');
+                }
+                current.push_str(word);
+            }
+            wrapped.push(current);
+            wrapped.join("\n")
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Drops "line 42"/"lines 10-12" (with a leading "on"/"at"/...) and `@@ ... @@`
+/// hunk headers, which go stale the moment the file changes again.
+fn strip_line_references(body: &str) -> String {
+    const PREPOSITIONS: [&str; 6] = ["on", "at", "in", "around", "near", "to"];
+
+    body.lines()
+        .map(|line| {
+            let mut kept: Vec<String> = Vec::new();
+            let mut words = line.split_whitespace().peekable();
+            while let Some(word) = words.next() {
+                if word.starts_with("@@") {
+                    // Skip through the closing `@@` of the hunk header.
+                    if !(word.len() > 2 && word.ends_with("@@")) {
+                        for next in words.by_ref() {
+                            if next.starts_with("@@") {
+                                break;
+                            }
+                        }
+                    }
+                    continue;
+                }
+
+                let bare = word.to_ascii_lowercase();
+                let is_line_word = bare == "line" || bare == "lines";
+                if is_line_word
+                    && let Some(number) = words.peek()
+                    && number.starts_with(|c: char| c.is_ascii_digit())
+                {
+                    let number = words.next().unwrap_or_default();
+                    if kept
+                        .last()
+                        .is_some_and(|w| PREPOSITIONS.contains(&w.to_ascii_lowercase().as_str()))
+                    {
+                        kept.pop();
+                    }
+                    // Keep sentence punctuation that trailed the number.
+                    let punctuation = number.trim_start_matches(|c: char| {
+                        c.is_ascii_digit() || c == '-' || c == '\u{2013}'
+                    });
+                    if let Some(last) = kept.last_mut() {
+                        last.push_str(punctuation);
+                    }
+                    continue;
+                }
+                kept.push(word.to_string());
+            }
+            kept.join(" ")
+        })
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn strip_trailing_period(title: &str) -> &str {
+    // Leave ellipses alone; only a lone final period breaks conventional style.
+    match title.strip_suffix('.') {
+        Some(rest) if !rest.ends_with('.') => rest.trim_end(),
+        _ => title,
+    }
+}
+
+/// Ticket id such as `PROJ-123` from a branch like `feature/proj-123-login`.
+pub fn ticket_from_branch(branch: &str) -> Option<String> {
+    branch.split(['/', '_']).find_map(|segment| {
+        let mut pieces = segment.split('-');
+        let key = pieces.next()?;
+        let number = pieces.next()?;
+        let is_key = key.len() >= 2
+            && key.starts_with(|c: char| c.is_ascii_alphabetic())
+            && key.chars().all(|c| c.is_ascii_alphanumeric());
+        let is_number = !number.is_empty() && number.chars().all(|c| c.is_ascii_digit());
+        (is_key && is_number).then(|| format!("{}-{}", key.to_ascii_uppercase(), number))
+    })
+}
+
+/// How the issue for a `Refs:` footer is found in the branch name.
+#[derive(Debug, Clone)]
+pub enum IssuePattern {
+    /// A ticket key like `PROJ-123`, or a `#123` issue number.
+    Default,
+    /// `issue_pattern` from the config: the first capture group, or the
+    /// whole match when the pattern has none.
+    Custom(Regex),
+    /// `issue_pattern = ""` turns the footer off.
+    Off,
+}
+
+impl IssuePattern {
+    pub fn new(pattern: Option<&str>) -> std::result::Result<Self, regex::Error> {
+        match pattern {
+            None => Ok(Self::Default),
+            Some(p) if p.trim().is_empty() => Ok(Self::Off),
+            Some(p) => Regex::new(p).map(Self::Custom),
+        }
+    }
+
+    pub fn find(&self, branch: &str) -> Option<String> {
+        match self {
+            Self::Default => ticket_from_branch(branch).or_else(|| {
+                let digits = branch.split_once('#')?.1;
+                let end = digits
+                    .find(|c: char| !c.is_ascii_digit())
+                    .unwrap_or(digits.len());
+                (end > 0).then(|| format!("#{}", &digits[..end]))
+            }),
+            Self::Custom(regex) => {
+                let caps = regex.captures(branch)?;
+                caps.get(1)
+                    .or_else(|| caps.get(0))
+                    .map(|m| m.as_str().to_string())
+            }
+            Self::Off => None,
+        }
+    }
+}
+
+/// Appends a `Refs: <issue>` footer unless the body already mentions a
+/// `Refs:` line for it (a regenerate or an edit may have kept one).
+pub fn add_refs_footer(body: Option<String>, issue: &str) -> Option<String> {
+    let footer = format!("Refs: {}", issue);
+    match body {
+        Some(body) if body.lines().any(|line| line.trim() == footer) => Some(body),
+        Some(body) => Some(format!("{}\n\n{}", body.trim_end(), footer)),
+        None => Some(footer),
+    }
+}
+
+/// Fills `{ticket}`/`{branch}` in a title prefix template. `None` when the
+/// template wants a ticket the branch doesn't carry.
+pub fn render_title_prefix(template: &str, branch: &str) -> Option<String> {
+    let mut prefix = template.replace("{branch}", branch);
+    if prefix.contains("{ticket}") {
+        prefix = prefix.replace("{ticket}", &ticket_from_branch(branch)?);
+    }
+    Some(prefix)
+}
+
+/// Characters left for the rest of the title after a leading gitmoji, which
+/// is kept whole: a unicode emoji takes two columns plus its space.
+fn title_room(gitmoji: &str) -> usize {
+    let width = if gitmoji.is_ascii() { gitmoji.len() } else { 3 };
+    MAX_TITLE_CHARS.saturating_sub(width)
+}
+
+/// Inserts `prefix` after a conventional `type[SCOPE]: ` head (or at the start),
+/// shortening the summary so the whole title stays within 72 characters.
+pub fn apply_title_prefix(title: &str, prefix: &str) -> String {
+    let (gitmoji, title) = split_gitmoji(title);
+    let (head, summary) = match title.split_once(": ") {
+        Some((head, summary)) if is_conventional_title(title) => (format!("{}: ", head), summary),
+        _ => (String::new(), title),
+    };
+    let room = title_room(gitmoji).saturating_sub(head.chars().count() + prefix.chars().count());
+    let summary: String = summary.chars().take(room).collect();
+    format!("{}{}{}{}", gitmoji, head, prefix, summary.trim_end())
+}
+
+/// Rewrites the title's head with the `--type`/`--scope` picked on the
+/// command line, keeping the model's summary and any `!`. Scopes follow the
+/// title style: `type[SCOPE]` normally, `type(scope)` in strict mode.
+pub fn override_title_head(
+    title: &str,
+    kind: Option<&str>,
+    scope: Option<&str>,
+    strict: bool,
+) -> String {
+    if kind.is_none() && scope.is_none() {
+        return title.to_string();
+    }
+    let (gitmoji, title) = split_gitmoji(title);
+    let (head, summary) = match title.split_once(": ") {
+        Some((head, summary)) if is_conventional_title(title) => (head, summary),
+        _ => ("", title),
+    };
+    let (head, breaking) = match head.strip_suffix('!') {
+        Some(head) => (head, "!"),
+        None => (head, ""),
+    };
+    let (old_kind, old_scope) = match head.find(['[', '(']) {
+        Some(idx) => (&head[..idx], Some(&head[idx + 1..head.len() - 1])),
+        None => (head, None),
+    };
+    let kind = kind
+        .or(Some(old_kind).filter(|k| !k.is_empty()))
+        .unwrap_or("chore");
+    let head = match scope.or(old_scope) {
+        Some(scope) if strict => format!("{}({}){}: ", kind, scope.to_lowercase(), breaking),
+        Some(scope) => format!("{}[{}]{}: ", kind, scope.to_uppercase(), breaking),
+        None => format!("{}{}: ", kind, breaking),
+    };
+    let room = title_room(gitmoji).saturating_sub(head.chars().count());
+    let summary: String = summary.chars().take(room).collect();
+    format!("{}{}{}", gitmoji, head, summary.trim_end())
+}
+
+pub fn parse_commit_message(raw: &str, options: &MessageOptions) -> (String, Option<String>) {
+    let (mut title, body) = sanitize_message(raw, &options.preamble_phrases);
+    if options.strip_trailing_period {
+        title = strip_trailing_period(&title).to_string();
+    }
+    let body = match body {
+        Some(body) if options.strip_line_references => {
+            Some(strip_line_references(&body)).filter(|b| !b.is_empty())
+        }
+        body => body,
+    };
+    let body = match (body, options.wrap_width) {
+        (Some(body), Some(width)) => Some(wrap_body(&body, width)),
+        (body, _) => body,
+    };
+    (title, body)
+}
+
+/// Phrases a model uses when it declines rather than answers.
+const REFUSAL_PHRASES: &[&str] = &[
+    "i cannot",
+    "i can't",
+    "i can not",
+    "i'm unable",
+    "i am unable",
+    "i'm not able",
+    "i am not able",
+    "i'm sorry",
+    "i apologize",
+    "as an ai",
+    "unable to generate",
+];
+
+/// A reply that is a refusal or an aside instead of a commit message: no
+/// conventional title, and it reads like an apology.
+pub fn looks_like_refusal(raw: &str) -> bool {
+    let (title, _) = sanitize_message(raw, &[]);
+    if is_conventional_title(&title) {
+        return false;
+    }
+    let text = raw.to_lowercase().replace('\u{2019}', "'");
+    REFUSAL_PHRASES.iter().any(|phrase| text.contains(phrase))
+}
+
+/// Salvages a stalled stream: returns the text up to the last complete line,
+/// but only once the title reads as a conventional commit title.
+pub fn salvage_partial_message(raw: &str) -> Option<&str> {
+    let complete = &raw[..raw.rfind('\n')?];
+    let (title, _) = sanitize_message(complete, &[]);
+    is_conventional_title(&title).then_some(complete)
 }
 
 #[cfg(test)]
 mod tests {
-    use super::parse_commit_message;
+    use super::{
+        API_URL, Completion, Endpoint, IssuePattern, MessageOptions, add_refs_footer, api_error,
+        apply_title_prefix, best_of_request, chat_url, describe_reply, generate_with_fallback,
+        http_agent, looks_like_refusal, override_title_head, parse_commit_message,
+        pick_best_candidate, post_chat, read_stream, render_title_prefix, retry_delay,
+        salvage_partial_message, stream_request, validate_api_key, wrap_body,
+    };
+    use crate::args::Args;
+    use crate::config::Config;
+    use crate::error::YetiError;
+    use crate::prompt::{self, SYSTEM_PROMPT, split_gitmoji};
+    use clap::Parser;
+    use std::cell::Cell;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+    use std::thread;
+    use std::time::Duration;
+
+    /// A keep-alive HTTP server answering `{}` to every request; returns its
+    /// URL and the number of TCP connections it has accepted.
+    fn serve_counting_connections() -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        let connections = Arc::new(AtomicUsize::new(0));
+        let accepted = Arc::clone(&connections);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                accepted.fetch_add(1, Ordering::SeqCst);
+                thread::spawn(move || {
+                    let mut reader = BufReader::new(stream.try_clone().unwrap());
+                    loop {
+                        let mut content_length = 0;
+                        loop {
+                            let mut line = String::new();
+                            if reader.read_line(&mut line).unwrap_or(0) == 0 {
+                                return;
+                            }
+                            if line == "\r\n" {
+                                break;
+                            }
+                            if let Some(value) =
+                                line.to_ascii_lowercase().strip_prefix("content-length:")
+                            {
+                                content_length = value.trim().parse().unwrap_or(0);
+                            }
+                        }
+                        let mut body = vec![0; content_length];
+                        if reader.read_exact(&mut body).is_err() {
+                            return;
+                        }
+                        let reply = "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+                        if stream.write_all(reply.as_bytes()).is_err() {
+                            return;
+                        }
+                    }
+                });
+            }
+        });
+        (url, connections)
+    }
+
+    #[test]
+    fn non_streaming_replies_are_described_for_verbose_logs() {
+        let working = r#"{"choices":[{"message":{"role":"assistant","content":" Hello! How can I help?"},"finish_reason":"stop"}]}"#;
+        assert_eq!(
+            describe_reply(working),
+            "replied \"Hello! How can I help?\""
+        );
+
+        let cut = r#"{"choices":[{"message":{"role":"assistant"},"finish_reason":"length"}]}"#;
+        assert_eq!(
+            describe_reply(cut),
+            "authenticated, but the reply was empty (finish_reason: length)"
+        );
+        assert_eq!(
+            describe_reply(r#"{"choices":[]}"#),
+            "authenticated, but the model returned no choices"
+        );
+        assert_eq!(
+            describe_reply(r#"{"error":{"message":"model overloaded"}}"#),
+            "authenticated, but the reply was not a chat completion: {\"error\":{\"message\":\"model overloaded\"}}"
+        );
+    }
+
+    #[test]
+    fn shared_agent_reuses_one_connection_across_calls() {
+        let (url, connections) = serve_counting_connections();
+        let agent = http_agent();
+        for _ in 0..3 {
+            let endpoint = Endpoint {
+                agent: &agent,
+                url: &url,
+                max_retries: 0,
+            };
+            let mut response =
+                post_chat(endpoint, "csk-test", "{}", &AtomicBool::new(false)).unwrap();
+            response.body_mut().read_to_string().unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 1);
+
+        let (url, connections) = serve_counting_connections();
+        for _ in 0..3 {
+            let agent = http_agent();
+            let endpoint = Endpoint {
+                agent: &agent,
+                url: &url,
+                max_retries: 0,
+            };
+            let mut response =
+                post_chat(endpoint, "csk-test", "{}", &AtomicBool::new(false)).unwrap();
+            response.body_mut().read_to_string().unwrap();
+        }
+        assert_eq!(connections.load(Ordering::SeqCst), 3);
+    }
+
+    /// Answers each request with the next status in `statuses` (then 200),
+    /// all with `Retry-After: retry_after`, and counts the requests served.
+    fn serve_statuses(statuses: Vec<u16>, retry_after: u64) -> (String, Arc<AtomicUsize>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!(
+            "http://{}/v1/chat/completions",
+            listener.local_addr().unwrap()
+        );
+        let requests = Arc::new(AtomicUsize::new(0));
+        let served = Arc::clone(&requests);
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                let mut reader = BufReader::new(stream.try_clone().unwrap());
+                loop {
+                    let mut content_length = 0;
+                    loop {
+                        let mut line = String::new();
+                        if reader.read_line(&mut line).unwrap_or(0) == 0 || line == "\r\n" {
+                            break;
+                        }
+                        if let Some(value) =
+                            line.to_ascii_lowercase().strip_prefix("content-length:")
+                        {
+                            content_length = value.trim().parse().unwrap_or(0);
+                        }
+                    }
+                    let mut body = vec![0; content_length];
+                    if content_length == 0 || reader.read_exact(&mut body).is_err() {
+                        break;
+                    }
+                    let n = served.fetch_add(1, Ordering::SeqCst);
+                    let status = statuses.get(n).copied().unwrap_or(200);
+                    let reply = format!(
+                        "HTTP/1.1 {} X\r\nRetry-After: {}\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{{}}",
+                        status, retry_after
+                    );
+                    if stream.write_all(reply.as_bytes()).is_err() {
+                        break;
+                    }
+                }
+            }
+        });
+        (url, requests)
+    }
+
+    #[test]
+    fn rate_limits_and_server_errors_are_retried_with_backoff() {
+        assert_eq!(retry_delay(0, None), Some(Duration::from_millis(500)));
+        assert_eq!(retry_delay(1, None), Some(Duration::from_secs(1)));
+        assert_eq!(retry_delay(2, None), Some(Duration::from_secs(2)));
+        assert_eq!(retry_delay(0, Some("7")), Some(Duration::from_secs(7)));
+        assert_eq!(retry_delay(0, Some("3600")), None);
+
+        let agent = http_agent();
+        let (url, requests) = serve_statuses(vec![429, 503], 0);
+        let endpoint = Endpoint {
+            agent: &agent,
+            url: &url,
+            max_retries: 3,
+        };
+        assert_eq!(
+            validate_api_key(endpoint, "csk-test", "gpt-oss-120b").unwrap(),
+            "authenticated, but the reply was not a chat completion: {}"
+        );
+        assert_eq!(requests.load(Ordering::SeqCst), 3);
+
+        let (url, requests) = serve_statuses(vec![429, 429], 0);
+        let endpoint = Endpoint {
+            agent: &agent,
+            url: &url,
+            max_retries: 1,
+        };
+        assert!(matches!(
+            validate_api_key(endpoint, "csk-test", "gpt-oss-120b"),
+            Err(YetiError::ApiError { status: 429, .. })
+        ));
+        assert_eq!(requests.load(Ordering::SeqCst), 2);
+    }
+
+    #[test]
+    fn cancel_cuts_the_retry_backoff_and_model_fallback_short() {
+        let agent = http_agent();
+        let (url, requests) = serve_statuses(vec![503, 503, 503], 20);
+        let endpoint = Endpoint {
+            agent: &agent,
+            url: &url,
+            max_retries: 3,
+        };
+        let cancel = Arc::new(AtomicBool::new(false));
+        let setter = Arc::clone(&cancel);
+        thread::spawn(move || {
+            thread::sleep(Duration::from_millis(200));
+            setter.store(true, Ordering::Relaxed);
+        });
+        let started = std::time::Instant::now();
+        let result = post_chat(endpoint, "csk-test", "{}", &cancel);
+        assert!(matches!(result, Err(YetiError::Cancelled)));
+        assert!(started.elapsed() < Duration::from_secs(2));
+        assert_eq!(requests.load(Ordering::SeqCst), 1);
+
+        let models = vec!["primary".to_string(), "secondary".to_string()];
+        let mut tried = 0;
+        let result = generate_with_fallback::<String, _>(&models, &cancel, |_| {
+            tried += 1;
+            Ok(String::new())
+        });
+        assert!(matches!(result, Err(YetiError::Cancelled)));
+        assert_eq!(tried, 0);
+    }
+
+    #[test]
+    fn base_url_redirects_requests_to_its_chat_completions() {
+        assert_eq!(chat_url(None), API_URL);
+        assert_eq!(chat_url(Some("  ")), API_URL);
+        assert_eq!(
+            chat_url(Some("http://localhost:4000/v1/")),
+            "http://localhost:4000/v1/chat/completions"
+        );
+
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/proxy/v1", listener.local_addr().unwrap());
+        let (seen_tx, seen) = std::sync::mpsc::channel();
+        thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                if let Some(value) = line.to_ascii_lowercase().strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let reply =
+                "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: 2\r\n\r\n{}";
+            stream.write_all(reply.as_bytes()).unwrap();
+            seen_tx
+                .send((request_line, String::from_utf8(body).unwrap()))
+                .unwrap();
+        });
+
+        let url = chat_url(Some(&base));
+        let agent = http_agent();
+        let endpoint = Endpoint {
+            agent: &agent,
+            url: &url,
+            max_retries: 0,
+        };
+        assert_eq!(
+            validate_api_key(endpoint, "sk-local", "local-llama").unwrap(),
+            "authenticated, but the reply was not a chat completion: {}"
+        );
+        let (request_line, body) = seen.recv().unwrap();
+        assert_eq!(
+            request_line.trim_end(),
+            "POST /proxy/v1/chat/completions HTTP/1.1"
+        );
+        assert!(body.contains("\"model\":\"local-llama\""), "{body}");
+
+        let local = Config {
+            base_url: Some(base),
+            model: Some("local-llama".to_string()),
+            ..Config::default()
+        };
+        assert_eq!(local.validation_model(), "local-llama");
+        assert_eq!(
+            Config::default().validation_model(),
+            Config::default_model()
+        );
+    }
+
+    #[test]
+    fn model_flag_reaches_the_request_body() {
+        let mut config = Config {
+            model: Some("qwen-3-32b".to_string()),
+            ..Config::default()
+        };
+        Args::try_parse_from(["yeti", "--model", "llama-3.3-70b", "--dry-run"])
+            .unwrap()
+            .apply_to(&mut config);
+
+        assert_eq!(config.model_chain()[0], "llama-3.3-70b");
+        let body = serde_json::to_value(stream_request(
+            SYSTEM_PROMPT,
+            config.model(),
+            "diff",
+            512,
+            config.temperature(),
+        ))
+        .unwrap();
+        assert_eq!(body["model"], "llama-3.3-70b");
+        assert_eq!(body["temperature"], 0.0);
+
+        let mut config = Config::default();
+        Args::try_parse_from(["yeti"])
+            .unwrap()
+            .apply_to(&mut config);
+        assert_eq!(config.model(), Config::default_model());
+    }
+
+    #[test]
+    fn temperature_flag_overrides_config_and_is_range_checked() {
+        let mut config = Config {
+            temperature: Some(0.2),
+            ..Config::default()
+        };
+        let args = Args::try_parse_from(["yeti", "--temperature", "0.5"]).unwrap();
+        args.validate().unwrap();
+        args.apply_to(&mut config);
+
+        let body = serde_json::to_value(stream_request(
+            SYSTEM_PROMPT,
+            config.model(),
+            "diff",
+            512,
+            config.temperature(),
+        ))
+        .unwrap();
+        assert_eq!(body["temperature"], 0.5);
+
+        let configured = Config {
+            temperature: Some(0.2),
+            ..Config::default()
+        };
+        assert_eq!(configured.temperature(), 0.2);
+        assert_eq!(Config::default().temperature(), 0.0);
+
+        for bad in ["2.5", "-0.1", "NaN"] {
+            let err = Args::try_parse_from(["yeti", "--temperature", bad])
+                .unwrap()
+                .validate()
+                .unwrap_err();
+            assert!(
+                matches!(err, YetiError::InvalidArgs(ref message) if message.contains("--temperature")),
+                "{}: {}",
+                bad,
+                err
+            );
+        }
+    }
+
+    #[test]
+    fn best_of_requests_use_the_configured_temperature_above_a_floor() {
+        let sent = |temperature| {
+            let generation = crate::provider::Generation {
+                system_prompt: SYSTEM_PROMPT,
+                model: "llama-3.3-70b",
+                user_prompt: "diff",
+                max_completion_tokens: 512,
+                temperature,
+                candidates: 3,
+            };
+            let body = serde_json::to_value(best_of_request(&generation)).unwrap();
+            assert_eq!(body["n"], 3);
+            body["temperature"].as_f64().unwrap() as f32
+        };
+        assert_eq!(sent(1.2), 1.2);
+        assert_eq!(sent(0.0), super::BEST_OF_MIN_TEMPERATURE);
+    }
+
+    #[test]
+    fn type_and_scope_flags_rewrite_only_the_title_head() {
+        let title = "fix[TUI]: keep cursor on wide chars";
+        assert_eq!(
+            override_title_head(title, Some("feat"), None, false),
+            "feat[TUI]: keep cursor on wide chars"
+        );
+        assert_eq!(
+            override_title_head(title, None, Some("editor"), false),
+            "fix[EDITOR]: keep cursor on wide chars"
+        );
+        assert_eq!(
+            override_title_head(
+                "refactor(api)!: drop v1 routes",
+                Some("feat"),
+                Some("Gateway"),
+                true
+            ),
+            "feat(gateway)!: drop v1 routes"
+        );
+        assert_eq!(
+            override_title_head("Keep cursor on wide chars", None, Some("tui"), false),
+            "chore[TUI]: Keep cursor on wide chars"
+        );
+        assert_eq!(override_title_head(title, None, None, false), title);
+
+        let args = Args::try_parse_from(["yeti", "--type", "feet"]).unwrap();
+        assert!(matches!(
+            args.validate(),
+            Err(YetiError::InvalidArgs(message)) if message.contains("feat, fix")
+        ));
+        let args = Args::try_parse_from(["yeti", "--type", "perf", "--scope", "db"]).unwrap();
+        assert!(args.validate().is_ok());
+    }
+
+    #[test]
+    fn gitmoji_titles_keep_their_emoji_whole() {
+        assert_eq!(prompt::system_prompt(false), SYSTEM_PROMPT);
+        assert!(prompt::system_prompt(true).contains(":sparkles:"));
+
+        let options = MessageOptions::default();
+        let (title, _) = parse_commit_message(":bug: fix[GIT]: keep the stage", &options);
+        assert_eq!(title, ":bug: fix[GIT]: keep the stage");
+        assert_eq!(
+            split_gitmoji(&title),
+            (":bug: ", "fix[GIT]: keep the stage")
+        );
+
+        let long = format!("✨ feat[TUI]: {}", "word ".repeat(30));
+        let (title, _) = parse_commit_message(&long, &options);
+        assert!(title.starts_with("✨ feat[TUI]: word"));
+        assert_eq!(title.chars().count(), 2 + 69);
+        assert_eq!(split_gitmoji("feat: ✨ sparkle"), ("", "feat: ✨ sparkle"));
+
+        assert_eq!(
+            override_title_head("🐛 fix[GIT]: keep the stage", Some("perf"), None, false),
+            "🐛 perf[GIT]: keep the stage"
+        );
+        assert_eq!(
+            apply_title_prefix("🐛 fix[GIT]: keep the stage", "ABC-1 "),
+            "🐛 fix[GIT]: ABC-1 keep the stage"
+        );
+    }
+
+    #[test]
+    fn body_wraps_at_width_and_keeps_long_words_whole() {
+        let body = "Share one agent across validation and generation so pooled connections are reused.\nShort line.";
+        assert_eq!(
+            wrap_body(body, 40),
+            "Share one agent across validation and\ngeneration so pooled connections are\nreused.\nShort line."
+        );
+        assert_eq!(
+            wrap_body("see https://example.com/a/very/long/path here", 10),
+            "see\nhttps://example.com/a/very/long/path\nhere"
+        );
+
+        let options = MessageOptions {
+            wrap_width: None,
+            ..MessageOptions::default()
+        };
+        let (_, unwrapped) = parse_commit_message(&format!("fix: x\n\n{}", body), &options);
+        assert_eq!(unwrapped.as_deref(), Some(body));
+    }
 
     #[test]
     fn parse_commit_message_strips_markdown_and_builds_body() {
         let raw = "```text\n# heading\nfix[CORE]: handle rename metadata\n\nAdd rename source path to prompt context.\n```";
-        let (title, body) = parse_commit_message(raw);
+        let (title, body) = parse_commit_message(raw, &MessageOptions::default());
 
         assert_eq!(title, "fix[CORE]: handle rename metadata");
         assert_eq!(
@@ -294,10 +1517,181 @@ mod tests {
         );
     }
 
+    #[test]
+    fn best_candidate_prefers_conventional_complete_messages() {
+        let candidate = |content: &str, truncated| Completion {
+            content: content.to_string(),
+            truncated,
+        };
+        let candidates = vec![
+            candidate("Updated some files", false),
+            candidate(
+                "feat[TUI]: add a very long title that rambles well beyond fifty chars",
+                false,
+            ),
+            candidate(
+                "feat[TUI]: add pane split\n\nLet users size the panes.",
+                true,
+            ),
+            candidate(
+                "feat[TUI]: add pane split\n\nLet users size the panes.",
+                false,
+            ),
+            candidate("   ", false),
+        ];
+
+        let best = pick_best_candidate(candidates).unwrap();
+        assert!(best.content.starts_with("feat[TUI]: add pane split"));
+        assert!(!best.truncated);
+
+        assert!(pick_best_candidate(vec![candidate("", false)]).is_none());
+    }
+
+    #[test]
+    fn line_numbers_and_hunk_headers_are_stripped_from_the_body() {
+        let raw = "fix[GIT]: keep user index\n\nRestore entries on line 42 after a failed run.\nTouches @@ -10,4 +10,6 @@ the cleanup path near lines 7-9.";
+        let (_, body) = parse_commit_message(raw, &MessageOptions::default());
+
+        assert_eq!(
+            body.as_deref(),
+            Some("Restore entries after a failed run.\nTouches the cleanup path.")
+        );
+
+        let keep = MessageOptions {
+            strip_line_references: false,
+            ..MessageOptions::default()
+        };
+        let (_, body) = parse_commit_message(raw, &keep);
+        assert!(body.unwrap().contains("line 42"));
+    }
+
+    #[test]
+    fn title_prefix_follows_the_type_and_respects_the_length_limit() {
+        let prefix = render_title_prefix("[{ticket}] ", "feature/proj-123-login").unwrap();
+        assert_eq!(prefix, "[PROJ-123] ");
+        assert_eq!(
+            apply_title_prefix("feat[AUTH]: add login form", &prefix),
+            "feat[AUTH]: [PROJ-123] add login form"
+        );
+        assert_eq!(
+            apply_title_prefix("Add login form", &prefix),
+            "[PROJ-123] Add login form"
+        );
+
+        let long = format!("fix[CORE]: {}", "x".repeat(61));
+        let prefixed = apply_title_prefix(&long, &prefix);
+        assert_eq!(prefixed.chars().count(), 72);
+        assert!(prefixed.starts_with("fix[CORE]: [PROJ-123] xxx"));
+
+        assert_eq!(render_title_prefix("[{ticket}] ", "main"), None);
+        assert_eq!(
+            render_title_prefix("{branch}: ", "main").as_deref(),
+            Some("main: ")
+        );
+    }
+
+    #[test]
+    fn model_preambles_never_become_the_title() {
+        let options = MessageOptions::default();
+        for raw in [
+            "Here's the commit message:\n\nfeat[AUTH]: add login form\n\n- validate the email",
+            "Sure! Here is a concise commit message.\nfeat[AUTH]: add login form\n- validate the email",
+            "**Commit message:**\n```\nfeat[AUTH]: add login form\n- validate the email\n```",
+            "Certainly, based on the diff:\nHere is the commit message\nfeat[AUTH]: add login form\n- validate the email",
+        ] {
+            let (title, body) = parse_commit_message(raw, &options);
+            assert_eq!(title, "feat[AUTH]: add login form", "{raw}");
+            assert_eq!(body.as_deref(), Some("- validate the email"), "{raw}");
+        }
+
+        let (title, _) =
+            parse_commit_message("Here is the commit message: fix: keep the stage", &options);
+        assert_eq!(title, "fix: keep the stage");
+
+        // Free-form titles survive; only phrase matches are dropped.
+        let (title, _) = parse_commit_message("Okay:\nSurely handle empty stages", &options);
+        assert_eq!(title, "Surely handle empty stages");
+        let (title, _) = parse_commit_message("Sure, thing", &options);
+        assert_eq!(title, "Sure, thing");
+
+        let custom = MessageOptions {
+            preamble_phrases: vec!["Proposed message".to_string()],
+            ..MessageOptions::default()
+        };
+        let raw = "Proposed message -\nAdd a login form";
+        assert_eq!(parse_commit_message(raw, &custom).0, "Add a login form");
+        assert_eq!(parse_commit_message(raw, &options).0, "Proposed message -");
+    }
+
+    #[test]
+    fn provider_error_bodies_explain_the_failure() {
+        let quota = r#"{"error":{"message":"You exceeded your current quota","type":"insufficient_quota"}}"#;
+        assert!(matches!(
+            api_error(429, quota),
+            YetiError::ApiError { status: 429, message } if message == "You exceeded your current quota"
+        ));
+        assert!(matches!(
+            api_error(404, "  model gpt-9 not found\n"),
+            YetiError::ApiError { message, .. } if message == "model gpt-9 not found"
+        ));
+        assert_eq!(
+            api_error(502, "").to_string(),
+            "API error (502): Provider returned HTTP 502"
+        );
+        let long = "x".repeat(1000);
+        assert!(matches!(
+            api_error(500, &long),
+            YetiError::ApiError { message, .. } if message.len() == 300
+        ));
+    }
+
+    #[test]
+    fn branch_issues_become_a_refs_footer() {
+        let default = IssuePattern::new(None).unwrap();
+        assert_eq!(
+            default.find("feature/PROJ-123-add-login").as_deref(),
+            Some("PROJ-123")
+        );
+        assert_eq!(default.find("fix/#42-crash").as_deref(), Some("#42"));
+        assert_eq!(default.find("main"), None);
+        assert_eq!(IssuePattern::new(Some("")).unwrap().find("PROJ-1-x"), None);
+
+        let custom = IssuePattern::new(Some(r"gh-(\d+)")).unwrap();
+        assert_eq!(custom.find("gh-77-login").as_deref(), Some("77"));
+        assert!(IssuePattern::new(Some("(")).is_err());
+
+        assert_eq!(
+            add_refs_footer(Some("- add the form\n".into()), "PROJ-123").as_deref(),
+            Some("- add the form\n\nRefs: PROJ-123")
+        );
+        assert_eq!(add_refs_footer(None, "#42").as_deref(), Some("Refs: #42"));
+        let kept = "- add the form\n\nRefs: PROJ-123".to_string();
+        assert_eq!(add_refs_footer(Some(kept.clone()), "PROJ-123"), Some(kept));
+    }
+
+    #[test]
+    fn refusals_are_told_apart_from_commit_messages() {
+        assert!(looks_like_refusal(
+            "I cannot generate a commit message without seeing the diff."
+        ));
+        assert!(looks_like_refusal(
+            "I\u{2019}m sorry, but I can\u{2019}t help with that."
+        ));
+        assert!(looks_like_refusal(
+            "As an AI language model, I need more context."
+        ));
+
+        // A conventional title wins even when the body apologizes.
+        assert!(!looks_like_refusal(
+            "fix[API]: stop retrying on 401\n\n- I cannot reproduce the hang anymore"
+        ));
+        assert!(!looks_like_refusal("Update the README install steps"));
+    }
+
     #[test]
     fn parse_commit_message_falls_back_when_content_is_empty() {
         let raw = "\u{0000}\u{0007}\n```";
-        let (title, body) = parse_commit_message(raw);
+        let (title, body) = parse_commit_message(raw, &MessageOptions::default());
 
         assert_eq!(title, "chore: update files");
         assert!(body.is_none());
@@ -310,7 +1704,7 @@ mod tests {
             "{long_title}\n\nshort\nBody line one is long enough.\nBody line two is long enough.\nBody line three is long enough.\nBody line four is long enough."
         );
 
-        let (title, body) = parse_commit_message(&raw);
+        let (title, body) = parse_commit_message(&raw, &MessageOptions::default());
 
         assert_eq!(title.chars().count(), 72);
         assert_eq!(
@@ -320,4 +1714,110 @@ mod tests {
             )
         );
     }
+
+    #[test]
+    fn parse_commit_message_strips_single_trailing_period_from_title_only() {
+        let raw = "fix: do thing.\n\nExplain why the thing matters.";
+        let (title, body) = parse_commit_message(raw, &MessageOptions::default());
+
+        assert_eq!(title, "fix: do thing");
+        assert_eq!(body.as_deref(), Some("Explain why the thing matters."));
+
+        let (ellipsis, _) = parse_commit_message("fix: wait for it...", &MessageOptions::default());
+        assert_eq!(ellipsis, "fix: wait for it...");
+
+        let keep = MessageOptions {
+            strip_trailing_period: false,
+            ..MessageOptions::default()
+        };
+        let (kept, _) = parse_commit_message("fix: do thing.", &keep);
+        assert_eq!(kept, "fix: do thing.");
+    }
+
+    #[test]
+    fn salvage_partial_message_requires_complete_conventional_title() {
+        assert!(salvage_partial_message("feat[CORE]: add partial").is_none());
+        assert!(salvage_partial_message("Sure, here is\n").is_none());
+
+        let partial = salvage_partial_message(
+            "fix[TUI]: keep partial stream\n\nFirst body line is done.\nSecond li",
+        )
+        .expect("complete title should be salvaged");
+        let (title, body) = parse_commit_message(partial, &MessageOptions::default());
+
+        assert_eq!(title, "fix[TUI]: keep partial stream");
+        assert_eq!(body.as_deref(), Some("First body line is done."));
+    }
+
+    #[test]
+    fn fallback_chain_moves_past_overloaded_models_only() {
+        let models = vec![
+            "primary".to_string(),
+            "secondary".to_string(),
+            "tertiary".to_string(),
+        ];
+        let mut tried = Vec::new();
+        let (model, content) = generate_with_fallback(&models, &AtomicBool::new(false), |model| {
+            tried.push(model.to_string());
+            match model {
+                "primary" => Err(YetiError::ApiError {
+                    status: 503,
+                    message: "overloaded".to_string(),
+                }),
+                _ => Ok(format!("fix: generated by {}", model)),
+            }
+        })
+        .expect("fallback should succeed");
+
+        assert_eq!(model, "secondary");
+        assert_eq!(content, "fix: generated by secondary");
+        assert_eq!(tried, vec!["primary", "secondary"]);
+
+        let auth_failure =
+            generate_with_fallback::<String, _>(&models, &AtomicBool::new(false), |_| {
+                Err(YetiError::InvalidApiKey(
+                    "Authentication failed".to_string(),
+                ))
+            });
+        assert!(matches!(auth_failure, Err(YetiError::InvalidApiKey(_))));
+    }
+
+    #[test]
+    fn cancel_flag_stops_chunk_consumption() {
+        let sse = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"feat: add\"}}]}\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" thing\"}}]}\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" more\"}}]}\n",
+        );
+        let cancel = AtomicBool::new(false);
+        let chunks = Cell::new(0);
+
+        let completion = read_stream(sse.as_bytes(), &cancel, |_| {
+            chunks.set(chunks.get() + 1);
+            cancel.store(true, Ordering::Relaxed);
+        })
+        .expect("cancelled stream still returns");
+
+        assert_eq!(chunks.get(), 1);
+        assert_eq!(completion.content, "feat: add");
+    }
+
+    #[test]
+    fn stream_reader_surfaces_length_finish_reason() {
+        let sse = concat!(
+            "data: {\"choices\":[{\"delta\":{\"content\":\"feat: add\"}}]}\n\n",
+            "data: {\"choices\":[{\"delta\":{\"content\":\" thing\"},\"finish_reason\":\"length\"}]}\n\n",
+            "data: [DONE]\n"
+        );
+        let completion =
+            read_stream(sse.as_bytes(), &AtomicBool::new(false), |_| {}).expect("stream parses");
+
+        assert_eq!(completion.content, "feat: add thing");
+        assert!(completion.truncated);
+
+        let finished = "data: {\"choices\":[{\"delta\":{\"content\":\"fix: x\"},\"finish_reason\":\"stop\"}]}\n";
+        let completion = read_stream(finished.as_bytes(), &AtomicBool::new(false), |_| {})
+            .expect("stream parses");
+        assert!(!completion.truncated);
+    }
 }
@@ -0,0 +1,143 @@
+use crate::prompt::{FileInfo, FileStatus};
+
+/// Keep-a-Changelog section a commit belongs under.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Section {
+    Added,
+    Changed,
+    Removed,
+    Fixed,
+}
+
+impl Section {
+    fn heading(self) -> &'static str {
+        match self {
+            Section::Added => "### Added",
+            Section::Changed => "### Changed",
+            Section::Removed => "### Removed",
+            Section::Fixed => "### Fixed",
+        }
+    }
+}
+
+/// Picks the section from the title's type; a change that only deletes
+/// files is a removal whatever the model called it.
+pub fn section_for(kind: Option<&str>, files: &[FileInfo]) -> Section {
+    if !files.is_empty() && files.iter().all(|f| f.status == FileStatus::Deleted) {
+        return Section::Removed;
+    }
+    match kind {
+        Some("feat") => Section::Added,
+        Some("fix") => Section::Fixed,
+        Some("revert") => Section::Removed,
+        _ => Section::Changed,
+    }
+}
+
+/// Splits `type[SCOPE]: summary` (or the standard `type(scope)!:` form) into
+/// its type, scope and summary, past any leading gitmoji; titles without a
+/// type come back whole.
+fn split_title(title: &str) -> (Option<&str>, Option<&str>, &str) {
+    let (_, title) = crate::prompt::split_gitmoji(title);
+    let Some((prefix, summary)) = title.split_once(": ") else {
+        return (None, None, title);
+    };
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let scoped = prefix
+        .split_once('[')
+        .map(|(kind, scope)| (kind, scope.strip_suffix(']')))
+        .or_else(|| {
+            prefix
+                .split_once('(')
+                .map(|(kind, scope)| (kind, scope.strip_suffix(')')))
+        });
+    match scoped {
+        Some((kind, scope)) => (Some(kind), scope, summary),
+        None if prefix.chars().all(|c| c.is_ascii_alphabetic()) => (Some(prefix), None, summary),
+        None => (None, None, title),
+    }
+}
+
+/// A Keep-a-Changelog entry for `message`: the section heading, the title
+/// as a bullet, and any body bullets nested under it.
+pub fn entry(message: &str, files: &[FileInfo]) -> String {
+    let mut lines = message.lines();
+    let title = lines.next().unwrap_or_default().trim();
+    let (kind, scope, summary) = split_title(title);
+    let section = section_for(kind, files);
+
+    let mut summary = summary.trim().to_string();
+    if let Some(first) = summary.get(..1) {
+        summary.replace_range(..1, &first.to_uppercase());
+    }
+    let mut out = format!("{}\n\n", section.heading());
+    match scope {
+        Some(scope) if !scope.is_empty() => {
+            out.push_str(&format!("- **{}:** {}\n", scope, summary))
+        }
+        _ => out.push_str(&format!("- {}\n", summary)),
+    }
+    for line in lines {
+        let line = line.trim();
+        if let Some(point) = line.strip_prefix("- ").or_else(|| line.strip_prefix("* ")) {
+            out.push_str(&format!("  - {}\n", point));
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Section, entry, section_for};
+    use crate::prompt::{FileInfo, FileStatus};
+
+    fn file(status: FileStatus) -> FileInfo {
+        FileInfo::new("src/history.rs", status)
+    }
+
+    #[test]
+    fn feat_message_becomes_an_added_entry() {
+        let message = "feat(history): log commits per repository\n\n- write .git/yeti-history.jsonl\n- keep the global log as default";
+
+        assert_eq!(
+            entry(message, &[file(FileStatus::Added)]),
+            "### Added\n\n- **history:** Log commits per repository\n  - write .git/yeti-history.jsonl\n  - keep the global log as default\n"
+        );
+    }
+
+    #[test]
+    fn bracketed_scope_titles_are_split_like_parenthesized_ones() {
+        assert_eq!(
+            entry(
+                "fix[TUI]: keep cursor on wide chars",
+                &[file(FileStatus::Modified)]
+            ),
+            "### Fixed\n\n- **TUI:** Keep cursor on wide chars\n"
+        );
+    }
+
+    #[test]
+    fn gitmoji_titles_are_filed_by_their_type() {
+        let added = [file(FileStatus::Added)];
+        assert_eq!(
+            entry("✨ feat[CLI]: add --changelog", &added),
+            "### Added\n\n- **CLI:** Add --changelog\n"
+        );
+        assert_eq!(
+            entry(":bug: fix: keep the stage", &added),
+            "### Fixed\n\n- Keep the stage\n"
+        );
+    }
+
+    #[test]
+    fn section_follows_type_unless_only_deletions() {
+        let modified = [file(FileStatus::Modified)];
+        assert_eq!(section_for(Some("fix"), &modified), Section::Fixed);
+        assert_eq!(section_for(Some("refactor"), &modified), Section::Changed);
+        assert_eq!(section_for(None, &modified), Section::Changed);
+        assert_eq!(
+            section_for(Some("chore"), &[file(FileStatus::Deleted)]),
+            Section::Removed
+        );
+    }
+}
@@ -0,0 +1,170 @@
+use crate::color::Palette;
+use crate::error::{Result, YetiError};
+use crate::git::GitRepo;
+use crate::prompt::{FileStatus, PATCH_BUDGET, resolve_scope_hint, type_hint};
+use crate::tui::{format_count, render_file_table};
+
+/// Runs `yeti check`; returns whether anything is staged.
+pub fn run() -> Result<bool> {
+    let repo = GitRepo::discover()?;
+    match report(&repo, &Palette::detect())? {
+        Some(text) => {
+            print!("{}", text);
+            Ok(true)
+        }
+        None => {
+            println!("nothing staged on {}", repo.branch());
+            Ok(false)
+        }
+    }
+}
+
+/// Runs `yeti --list-staged`: the summary table alone; returns whether anything is staged.
+pub fn list_staged() -> Result<bool> {
+    let repo = GitRepo::discover()?;
+    match staged_table(&repo, &Palette::detect())? {
+        Some(table) => {
+            print!("{}", table);
+            Ok(true)
+        }
+        None => {
+            println!("nothing staged on {}", repo.branch());
+            Ok(false)
+        }
+    }
+}
+
+fn staged_table(repo: &GitRepo, p: &Palette) -> Result<Option<String>> {
+    match repo.get_staged_summary(PATCH_BUDGET) {
+        Ok(summary) => Ok(Some(render_file_table(&summary.files, p))),
+        Err(YetiError::NoChangesToCommit) => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+fn report(repo: &GitRepo, p: &Palette) -> Result<Option<String>> {
+    let summary = match repo.get_staged_summary(PATCH_BUDGET) {
+        Ok(summary) => summary,
+        Err(YetiError::NoChangesToCommit) => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    let total_add: usize = summary.files.iter().map(|f| f.additions).sum();
+    let total_del: usize = summary.files.iter().map(|f| f.deletions).sum();
+    let mut out = String::new();
+
+    out.push_str(&format!(
+        "{}{}staged{} on {}{}{}: {} files {}+{}{} {}-{}{}\n",
+        p.bold,
+        p.orange,
+        p.reset,
+        p.blue,
+        summary.branch,
+        p.reset,
+        format_count(summary.files.len()),
+        p.green,
+        format_count(total_add),
+        p.reset,
+        p.dim,
+        format_count(total_del),
+        p.reset
+    ));
+    for file in &summary.files {
+        let status = match file.status {
+            FileStatus::Added => "A",
+            FileStatus::Deleted => "D",
+            FileStatus::Renamed => "R",
+            FileStatus::Modified => "M",
+        };
+        out.push_str(&format!("  {} {}\n", status, file.annotated_path()));
+    }
+
+    out.push_str(&format!(
+        "{}hints{}: type {} · scope {}\n",
+        p.dim,
+        p.reset,
+        type_hint(&summary.files).unwrap_or("(unclear)"),
+        resolve_scope_hint(
+            &summary.files,
+            summary.repo_name.as_deref(),
+            &summary.workspace_crates
+        )
+        .as_deref()
+        .unwrap_or("(mixed)")
+    ));
+
+    Ok(Some(out))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{report, staged_table};
+    use crate::color::Palette;
+    use crate::error::Result;
+    use crate::git::test_support::{
+        create_temp_repo_dir, git_repo, init_repo_with_initial_commit, write_file,
+    };
+    use std::fs;
+    use std::path::Path;
+
+    #[test]
+    fn check_reports_nothing_when_index_matches_head() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("check-clean");
+        let repo = git_repo(init_repo_with_initial_commit(&temp_dir)?);
+
+        assert!(report(&repo, &Palette::plain())?.is_none());
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn check_lists_staged_files_and_hints() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("check-staged");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        let repo = git_repo(repo);
+        let text = report(&repo, &Palette::plain())?.expect("staged changes reported");
+
+        assert!(text.contains(": 1 files +1 -0"));
+        assert!(text.contains("  M src/file.txt"));
+        assert!(text.contains("scope CORE"));
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn list_staged_prints_the_summary_table() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("list-staged");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        write_file(&temp_dir.join("notes.md"), "hello\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.add_path(Path::new("notes.md"))?;
+            index.write()?;
+        }
+
+        let repo = git_repo(repo);
+        let table = staged_table(&repo, &Palette::plain())?.expect("staged files listed");
+
+        assert!(table.contains("src/file.txt"));
+        assert!(table.contains("notes.md"));
+        assert!(table.contains("2 files"));
+        assert!(!table.contains('\x1b'));
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+}
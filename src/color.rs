@@ -0,0 +1,90 @@
+use std::ffi::OsString;
+use std::io::IsTerminal;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Set once from `--no-color`, before anything is printed.
+static COLOR_FLAG_OFF: AtomicBool = AtomicBool::new(false);
+
+/// ANSI escape codes for plain stdout output; every field is empty when color is off.
+#[derive(Debug, Clone, Copy)]
+pub struct Palette {
+    pub orange: &'static str,
+    pub yellow: &'static str,
+    pub green: &'static str,
+    pub blue: &'static str,
+    pub dim: &'static str,
+    pub bold: &'static str,
+    pub reset: &'static str,
+}
+
+impl Palette {
+    pub fn ansi() -> Self {
+        Self {
+            orange: "\x1b[38;5;208m",
+            yellow: "\x1b[38;5;214m",
+            green: "\x1b[38;5;142m",
+            blue: "\x1b[38;5;109m",
+            dim: "\x1b[38;5;246m",
+            bold: "\x1b[1m",
+            reset: "\x1b[0m",
+        }
+    }
+
+    pub fn plain() -> Self {
+        Self {
+            orange: "",
+            yellow: "",
+            green: "",
+            blue: "",
+            dim: "",
+            bold: "",
+            reset: "",
+        }
+    }
+
+    pub fn detect() -> Self {
+        Self::for_color(color_enabled())
+    }
+
+    pub fn for_color(enabled: bool) -> Self {
+        if enabled { Self::ansi() } else { Self::plain() }
+    }
+
+    pub fn is_plain(&self) -> bool {
+        self.reset.is_empty()
+    }
+}
+
+/// `--no-color`: turns color off for help, tables and the TUI alike.
+pub fn disable() {
+    COLOR_FLAG_OFF.store(true, Ordering::Relaxed);
+}
+
+pub fn color_enabled() -> bool {
+    color_enabled_for(
+        COLOR_FLAG_OFF.load(Ordering::Relaxed),
+        std::env::var_os("NO_COLOR"),
+        std::io::stdout().is_terminal(),
+    )
+}
+
+pub fn color_enabled_for(flag_off: bool, no_color: Option<OsString>, stdout_is_tty: bool) -> bool {
+    // https://no-color.org: any non-empty value disables color.
+    let no_color = no_color.is_some_and(|v| !v.is_empty());
+    stdout_is_tty && !no_color && !flag_off
+}
+
+#[cfg(test)]
+mod tests {
+    use super::color_enabled_for;
+    use std::ffi::OsString;
+
+    #[test]
+    fn no_color_and_non_tty_disable_color() {
+        assert!(color_enabled_for(false, None, true));
+        assert!(color_enabled_for(false, Some(OsString::new()), true));
+        assert!(!color_enabled_for(false, Some(OsString::from("1")), true));
+        assert!(!color_enabled_for(false, None, false));
+        assert!(!color_enabled_for(true, None, true));
+    }
+}
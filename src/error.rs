@@ -3,26 +3,79 @@ use std::fmt;
 #[derive(Debug)]
 pub enum YetiError {
     NotAGitRepo,
+    BareRepo,
+    GitNotFound,
     NoChangesToCommit,
+    /// Nothing staged, though this many work tree changes could have been.
+    NothingStaged(usize),
+    NothingToAmend,
+    NoCommitToAmend,
+    LineEndingsOnly(Vec<String>),
     InvalidApiKey(String),
-    ApiError { status: u16, message: String },
+    ApiError {
+        status: u16,
+        message: String,
+    },
     NetworkError(String),
     CommitFailed(String),
+    TagFailed(String),
+    StageHookFailed {
+        command: String,
+        output: String,
+    },
     IoError(String),
+    InvalidArgs(String),
+    /// A run without a terminal hit something only a person could answer.
+    Headless(String),
+    /// The user stopped the request before it was sent or while it waited to retry.
+    Cancelled,
 }
 
 impl fmt::Display for YetiError {
     fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
         match self {
             YetiError::NotAGitRepo => write!(f, "Not inside a git repository"),
+            YetiError::BareRepo => write!(
+                f,
+                "This is a bare repository; yeti needs a working tree to stage and commit from"
+            ),
+            YetiError::GitNotFound => write!(
+                f,
+                "git executable not found on PATH (yeti needs the git CLI to commit)"
+            ),
             YetiError::NoChangesToCommit => write!(f, "No changes to commit"),
+            YetiError::NothingStaged(count) => write!(
+                f,
+                "{} changed file{} in the work tree, but nothing is staged; run without --staged-only or stage files first",
+                count,
+                if *count == 1 { "" } else { "s" }
+            ),
+            YetiError::NothingToAmend => {
+                write!(f, "Nothing to amend: no new changes staged since HEAD")
+            }
+            YetiError::NoCommitToAmend => write!(
+                f,
+                "--amend needs an existing commit, but this repository has none yet; drop --amend for the first commit"
+            ),
+            YetiError::LineEndingsOnly(paths) => write!(
+                f,
+                "Only line endings changed (CRLF/LF normalization, e.g. core.autocrlf) in: {}. Pass --force to commit the normalization, or restore the original endings",
+                paths.join(", ")
+            ),
             YetiError::InvalidApiKey(msg) => write!(f, "Invalid API key: {}", msg),
             YetiError::ApiError { status, message } => {
                 write!(f, "API error ({}): {}", status, message)
             }
             YetiError::NetworkError(msg) => write!(f, "Network error: {}", msg),
             YetiError::CommitFailed(msg) => write!(f, "Git commit failed: {}", msg),
+            YetiError::TagFailed(msg) => write!(f, "Git tag failed: {}", msg),
+            YetiError::StageHookFailed { command, output } => {
+                write!(f, "Stage hook `{}` failed: {}", command, output)
+            }
             YetiError::IoError(msg) => write!(f, "IO error: {}", msg),
+            YetiError::InvalidArgs(msg) => write!(f, "Invalid argument: {}", msg),
+            YetiError::Headless(msg) => write!(f, "{}", msg),
+            YetiError::Cancelled => write!(f, "Cancelled"),
         }
     }
 }
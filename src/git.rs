@@ -1,8 +1,16 @@
 use crate::error::{Result, YetiError};
-use crate::prompt::{FileInfo, FileStatus};
-use git2::{DiffFindOptions, DiffOptions, Repository};
+use crate::prompt::{ContentAttr, FileInfo, FileStatus, RecentCommit, WorkspaceCrate};
+use crate::yetiignore::YetiIgnore;
+use git2::{
+    AttrCheckFlags, AttrValue, DiffFindOptions, DiffFormat, DiffOptions, IndexEntry, Repository,
+    Status, StatusOptions,
+};
 use std::cell::RefCell;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+/// How much of each `--context-commits` patch is read before stopping.
+pub const RECENT_COMMIT_DIFF_BYTES: usize = 3000;
 
 pub struct GitRepo {
     repo: Repository,
@@ -12,11 +20,108 @@ pub struct GitRepo {
 pub struct StagedSummary {
     pub branch: String,
     pub files: Vec<FileInfo>,
+    pub repo_name: Option<String>,
+    pub workspace_crates: Vec<WorkspaceCrate>,
+    /// Commits before this one for `--context-commits`, newest first.
+    pub recent_commits: Vec<RecentCommit>,
+}
+
+/// Author identity from git config, as `git commit` would pick it up.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Identity {
+    pub name: Option<String>,
+    pub email: Option<String>,
+}
+
+impl Identity {
+    /// Config keys `git commit` will complain about.
+    pub fn missing_keys(&self) -> Vec<&'static str> {
+        let mut missing = Vec::new();
+        if self.name.is_none() {
+            missing.push("user.name");
+        }
+        if self.email.is_none() {
+            missing.push("user.email");
+        }
+        missing
+    }
+}
+
+/// One changed path, offered in the `--select` file picker.
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorktreeChange {
+    pub path: String,
+    pub status: FileStatus,
+    /// Some of the change is already in the index.
+    pub staged: bool,
+}
+
+/// What yeti stages before generating a message.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum StageMode {
+    /// Everything, untracked files included (`git add -A`).
+    #[default]
+    All,
+    /// Edits and deletions of tracked files only (`git add -u`).
+    Tracked,
+    /// Like `All`, but an untracked directory is left out whole instead of
+    /// being walked (`recurse_untracked = false`).
+    AllTopLevel,
+    /// Nothing; commit the index as the user left it.
+    StagedOnly,
+}
+
+/// Index entries as they stood before yeti staged anything, limited to the
+/// paths `stage_all` actually changed. Restoring it leaves whatever the user
+/// had staged beforehand untouched.
+#[derive(Debug, Default)]
+pub struct StageSnapshot {
+    entries: Vec<(Vec<u8>, Option<IndexEntry>)>,
+    /// What the user had staged for paths left out of a `--select` pick.
+    held_back: Vec<(Vec<u8>, Option<IndexEntry>)>,
+}
+
+impl StageSnapshot {
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Folds in a later snapshot, keeping the earliest recorded state per path.
+    pub fn merge(&mut self, later: StageSnapshot) {
+        for (path, entry) in later.entries {
+            if !self.entries.iter().any(|(p, _)| *p == path) {
+                self.entries.push((path, entry));
+            }
+        }
+        for (path, entry) in later.held_back {
+            if !self.held_back.iter().any(|(p, _)| *p == path) {
+                self.held_back.push((path, entry));
+            }
+        }
+    }
+
+    /// The staged entries a `--select` pick left out, to `restore_stage` once
+    /// the commit is made so they are still staged afterwards.
+    pub fn take_held_back(&mut self) -> StageSnapshot {
+        StageSnapshot {
+            entries: std::mem::take(&mut self.held_back),
+            held_back: Vec::new(),
+        }
+    }
 }
 
 impl GitRepo {
     pub fn discover() -> Result<Self> {
-        let repo = Repository::discover(".").map_err(|_| YetiError::NotAGitRepo)?;
+        Self::discover_from(Path::new("."))
+    }
+
+    /// A bare repository has no work tree to stage from, so it is refused
+    /// here rather than failing later inside `git commit`.
+    fn discover_from(path: &Path) -> Result<Self> {
+        let repo = Repository::discover(path).map_err(|_| YetiError::NotAGitRepo)?;
+        if repo.is_bare() {
+            return Err(YetiError::BareRepo);
+        }
         Ok(Self { repo })
     }
 
@@ -28,21 +133,125 @@ impl GitRepo {
             .unwrap_or_else(|| "HEAD".to_string())
     }
 
-    pub fn get_staged_summary(&self) -> Result<StagedSummary> {
+    /// Flags staged files that `.gitignore` would normally exclude.
+    pub fn mark_ignored(&self, files: &mut [FileInfo]) -> Result<()> {
+        for file in files.iter_mut() {
+            file.ignored = self.repo.status_should_ignore(Path::new(&file.path))?;
+        }
+        Ok(())
+    }
+
+    /// Name of the working tree's top-level directory.
+    pub fn repo_name(&self) -> Option<String> {
+        self.repo
+            .workdir()
+            .and_then(|dir| dir.file_name())
+            .map(|name| name.to_string_lossy().to_string())
+    }
+
+    /// Patterns from `.yetiignore` at the work tree root.
+    fn yetiignore(&self) -> YetiIgnore {
+        self.repo
+            .workdir()
+            .map(YetiIgnore::load)
+            .unwrap_or_default()
+    }
+
+    /// The repository's `.git` directory.
+    pub fn git_dir(&self) -> &Path {
+        self.repo.path()
+    }
+
+    /// Runs `pre_stage_command` from the work tree root so a formatter's
+    /// edits are there to be staged; a nonzero exit aborts with its output.
+    pub fn run_stage_hook(&self, command: &str) -> Result<()> {
+        let dir = self.repo.workdir().unwrap_or_else(|| self.repo.path());
+        let mut cmd = if cfg!(windows) {
+            let mut cmd = std::process::Command::new("cmd");
+            cmd.args(["/C", command]);
+            cmd
+        } else {
+            let mut cmd = std::process::Command::new("sh");
+            cmd.args(["-c", command]);
+            cmd
+        };
+        let output = cmd
+            .current_dir(dir)
+            .output()
+            .map_err(|e| YetiError::StageHookFailed {
+                command: command.to_string(),
+                output: e.to_string(),
+            })?;
+        if output.status.success() {
+            return Ok(());
+        }
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let text = if !stderr.trim().is_empty() {
+            stderr.trim().to_string()
+        } else if !stdout.trim().is_empty() {
+            stdout.trim().to_string()
+        } else {
+            format!("exited with {}", output.status)
+        };
+        Err(YetiError::StageHookFailed {
+            command: command.to_string(),
+            output: text,
+        })
+    }
+
+    /// Staged changes against HEAD, keeping up to `diff_bytes` of each
+    /// file's patch (normally [`crate::prompt::PATCH_BUDGET`]).
+    pub fn get_staged_summary(&self, diff_bytes: usize) -> Result<StagedSummary> {
+        self.summary_since("HEAD", diff_bytes)
+    }
+
+    /// False on an unborn branch, where there is nothing to amend.
+    pub fn has_commits(&self) -> bool {
+        self.repo
+            .head()
+            .is_ok_and(|head| head.peel_to_commit().is_ok())
+    }
+
+    /// What the amended commit will hold: HEAD's own changes plus anything
+    /// newly staged, so the message covers both.
+    pub fn amend_summary(&self, diff_bytes: usize) -> Result<StagedSummary> {
+        if !self.has_commits() {
+            return Err(YetiError::NoCommitToAmend);
+        }
+        if self.get_staged_files("HEAD", 0)?.is_empty() {
+            return Err(YetiError::NothingToAmend);
+        }
+        self.summary_since("HEAD^", diff_bytes)
+    }
+
+    /// Staged changes relative to `base`; an unresolvable base (no parent,
+    /// unborn branch) diffs against the empty tree.
+    fn summary_since(&self, base: &str, diff_bytes: usize) -> Result<StagedSummary> {
         let branch = self.branch();
-        let files = self.get_staged_files()?;
+        let files = self.get_staged_files(base, diff_bytes)?;
 
         if files.is_empty() {
             return Err(YetiError::NoChangesToCommit);
         }
 
-        Ok(StagedSummary { branch, files })
+        Ok(StagedSummary {
+            branch,
+            files,
+            repo_name: self.repo_name(),
+            workspace_crates: self
+                .repo
+                .workdir()
+                .map(read_workspace_crates)
+                .unwrap_or_default(),
+            recent_commits: Vec::new(),
+        })
     }
 
-    fn get_staged_files(&self) -> Result<Vec<FileInfo>> {
+    fn get_staged_files(&self, base: &str, diff_bytes: usize) -> Result<Vec<FileInfo>> {
         let head_tree = self
             .repo
-            .revparse_single("HEAD")
+            .revparse_single(base)
             .ok()
             .and_then(|o| o.peel_to_tree().ok());
 
@@ -62,6 +271,7 @@ impl GitRepo {
 
         let files: RefCell<Vec<FileInfo>> = RefCell::new(Vec::new());
         let file_index: RefCell<HashMap<String, usize>> = RefCell::new(HashMap::new());
+        let binary: RefCell<HashSet<String>> = RefCell::new(HashSet::new());
 
         diff.foreach(
             &mut |delta, _| {
@@ -91,12 +301,31 @@ impl GitRepo {
                         diff: String::new(),
                         status,
                         old_path,
+                        ignored: false,
+                        attr: None,
+                        symlink_target: self.symlink_target(&delta),
+                        mode_changed: delta.status() == git2::Delta::Modified
+                            && delta.old_file().mode() != delta.new_file().mode(),
+                        hunks: 0,
+                        eol_only: self.eol_only(&delta),
                     });
                 }
                 true
             },
-            None,
-            None,
+            Some(&mut |delta, _| {
+                if let Some(path) = delta_path(&delta) {
+                    binary.borrow_mut().insert(path);
+                }
+                true
+            }),
+            Some(&mut |delta, _hunk| {
+                if let Some(path) = delta_path(&delta)
+                    && let Some(&index) = file_index.borrow().get(&path)
+                {
+                    files.borrow_mut()[index].hunks += 1;
+                }
+                true
+            }),
             Some(&mut |delta, _hunk, line| {
                 let Some(path) = delta_path(&delta) else {
                     return true;
@@ -116,7 +345,7 @@ impl GitRepo {
                     _ => {}
                 }
 
-                if files_mut[index].diff.len() < 3000
+                if files_mut[index].diff.len() < diff_bytes
                     && let Ok(text) = std::str::from_utf8(line.content())
                 {
                     let prefix = line.origin();
@@ -128,17 +357,500 @@ impl GitRepo {
             }),
         )?;
 
-        Ok(files.into_inner())
+        let mut files = files.into_inner();
+        let binary = binary.into_inner();
+        let ignore = self.yetiignore();
+        files.retain(|file| !ignore.is_excluded(&file.path));
+        for file in &mut files {
+            file.attr = self
+                .content_attr(&file.path)
+                .or_else(|| binary.contains(&file.path).then_some(ContentAttr::Binary));
+            // An LFS pointer, binary blob or link-target diff would only mislead the model.
+            if file.attr.is_some() || file.symlink_target.is_some() {
+                file.diff.clear();
+            }
+        }
+        Ok(files)
+    }
+
+    /// The blobs differ only in CRLF vs LF, as when `core.autocrlf`
+    /// normalizes a file that was committed with Windows line endings.
+    fn eol_only(&self, delta: &git2::DiffDelta<'_>) -> bool {
+        if delta.status() != git2::Delta::Modified {
+            return false;
+        }
+        let (Ok(old), Ok(new)) = (
+            self.repo.find_blob(delta.old_file().id()),
+            self.repo.find_blob(delta.new_file().id()),
+        ) else {
+            return false;
+        };
+        let strip_cr =
+            |bytes: &[u8]| -> Vec<u8> { bytes.iter().copied().filter(|&b| b != b'\r').collect() };
+        old.content() != new.content() && strip_cr(old.content()) == strip_cr(new.content())
+    }
+
+    /// The link target stored in the blob when the entry is a symlink.
+    fn symlink_target(&self, delta: &git2::DiffDelta<'_>) -> Option<String> {
+        let file = match delta.status() {
+            git2::Delta::Deleted => delta.old_file(),
+            _ => delta.new_file(),
+        };
+        if file.mode() != git2::FileMode::Link {
+            return None;
+        }
+        let target = self
+            .repo
+            .find_blob(file.id())
+            .map(|blob| String::from_utf8_lossy(blob.content()).into_owned())
+            .unwrap_or_default();
+        Some(target)
+    }
+
+    fn content_attr(&self, path: &str) -> Option<ContentAttr> {
+        let attr = |name| {
+            self.repo
+                .get_attr(Path::new(path), name, AttrCheckFlags::FILE_THEN_INDEX)
+                .ok()
+                .flatten()
+        };
+        if attr("filter") == Some("lfs") {
+            return Some(ContentAttr::Lfs);
+        }
+        let binary = matches!(AttrValue::from_string(attr("binary")), AttrValue::True)
+            || matches!(AttrValue::from_string(attr("diff")), AttrValue::False)
+            || attr("merge") == Some("binary");
+        binary.then_some(ContentAttr::Binary)
+    }
+
+    /// Paths with staged changes that also carry further unstaged edits.
+    pub fn partially_staged_files(&self) -> Result<Vec<String>> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(false);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+
+        let staged = Status::INDEX_NEW | Status::INDEX_MODIFIED | Status::INDEX_RENAMED;
+        let unstaged = Status::WT_MODIFIED | Status::WT_DELETED;
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.status().intersects(staged))
+            .filter(|entry| entry.status().intersects(unstaged))
+            .filter_map(|entry| entry.path().map(|p| p.to_string()))
+            .collect())
+    }
+
+    /// `user.name`/`user.email` from the repository's layered git config.
+    pub fn identity(&self) -> Identity {
+        let config = self.repo.config().ok();
+        let get = |key| {
+            config
+                .as_ref()
+                .and_then(|c| c.get_string(key).ok())
+                .filter(|v| !v.trim().is_empty())
+        };
+        Identity {
+            name: get("user.name"),
+            email: get("user.email"),
+        }
+    }
+
+    /// Size of each staged blob as recorded in the index; deleted files are skipped.
+    pub fn staged_sizes(&self, files: &[FileInfo]) -> Result<Vec<(String, u64)>> {
+        let index = self.repo.index()?;
+        Ok(files
+            .iter()
+            .filter_map(|file| {
+                let entry = index.get_path(Path::new(&file.path), 0)?;
+                Some((file.path.clone(), u64::from(entry.file_size)))
+            })
+            .collect())
+    }
+
+    /// Subject line of the commit HEAD points at, if any.
+    pub fn head_subject(&self) -> Option<String> {
+        let commit = self.repo.head().ok()?.peel_to_commit().ok()?;
+        commit.summary().map(|s| s.to_string())
+    }
+
+    /// Up to `count` first-parent commits starting at `from`, newest first,
+    /// each with its patch cut off after [`RECENT_COMMIT_DIFF_BYTES`].
+    pub fn recent_commits(&self, from: &str, count: usize) -> Result<Vec<RecentCommit>> {
+        let mut next = self
+            .repo
+            .revparse_single(from)
+            .ok()
+            .and_then(|object| object.peel_to_commit().ok());
+        let mut commits = Vec::new();
+        while let Some(commit) = next.take()
+            && commits.len() < count
+        {
+            let parent = commit.parent(0).ok();
+            let parent_tree = parent.as_ref().map(|p| p.tree()).transpose()?;
+            let diff =
+                self.repo
+                    .diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let mut text = String::new();
+            let printed = diff.print(DiffFormat::Patch, |_, _, line| {
+                if let Ok(content) = std::str::from_utf8(line.content()) {
+                    if matches!(line.origin(), '+' | '-' | ' ') {
+                        text.push(line.origin());
+                    }
+                    text.push_str(content);
+                }
+                text.len() < RECENT_COMMIT_DIFF_BYTES
+            });
+            // Stopping at the cap surfaces as a user abort, not a failure.
+            if let Err(e) = printed
+                && e.code() != git2::ErrorCode::User
+            {
+                return Err(e.into());
+            }
+            commits.push(RecentCommit {
+                subject: commit.summary().unwrap_or_default().to_string(),
+                diff: text,
+            });
+            next = parent;
+        }
+        Ok(commits)
+    }
+
+    /// Those of `paths` whose work tree copy differs from the index.
+    fn worktree_modified(&self, paths: &[String]) -> Vec<String> {
+        paths
+            .iter()
+            .filter(|path| {
+                self.repo
+                    .status_file(Path::new(path.as_str()))
+                    .is_ok_and(|status| status.contains(Status::WT_MODIFIED))
+            })
+            .cloned()
+            .collect()
+    }
+
+    fn restage(&self, paths: &[String]) -> Result<()> {
+        let mut index = self.repo.index()?;
+        for path in paths {
+            index.add_path(Path::new(path))?;
+        }
+        index.write()?;
+        Ok(())
+    }
+
+    /// The tree the index would commit; equal ids mean identical staged content.
+    pub fn staged_tree_id(&self) -> Result<git2::Oid> {
+        Ok(self.repo.index()?.write_tree()?)
+    }
+
+    /// HEAD's hash abbreviated the way `git log --oneline` would.
+    pub fn head_short_id(&self) -> Option<String> {
+        let commit = self.repo.head().ok()?.peel_to_commit().ok()?;
+        let short = commit.as_object().short_id().ok()?;
+        short.as_str().map(str::to_string)
+    }
+
+    pub fn head_commit_time(&self) -> Option<git2::Time> {
+        self.repo
+            .head()
+            .ok()
+            .and_then(|h| h.peel_to_commit().ok())
+            .map(|c| c.time())
+    }
+
+    /// Stages changes per `mode` and returns what the index held beforehand
+    /// for the paths this touched.
+    /// Paths matched by `.yetiignore` are left as they are.
+    pub fn stage(&self, mode: StageMode) -> Result<StageSnapshot> {
+        let ignore = self.yetiignore();
+        let mut skip_excluded =
+            |path: &Path, _: &[u8]| i32::from(ignore.is_excluded(&path.to_string_lossy()));
+        match mode {
+            StageMode::All => self.stage_with(|index| {
+                index.add_all(
+                    ["*"].iter(),
+                    git2::IndexAddOption::DEFAULT,
+                    Some(&mut skip_excluded),
+                )
+            }),
+            StageMode::AllTopLevel => {
+                let untracked: Vec<String> = self
+                    .worktree_changes(false)?
+                    .into_iter()
+                    .filter(|change| {
+                        change.status == FileStatus::Added && !change.path.ends_with('/')
+                    })
+                    .map(|change| change.path)
+                    .collect();
+                self.stage_with(|index| {
+                    index.update_all(["*"].iter(), Some(&mut skip_excluded))?;
+                    for path in &untracked {
+                        index.add_path(Path::new(path))?;
+                    }
+                    Ok(())
+                })
+            }
+            StageMode::Tracked => {
+                self.stage_with(|index| index.update_all(["*"].iter(), Some(&mut skip_excluded)))
+            }
+            StageMode::StagedOnly => Ok(StageSnapshot::default()),
+        }
+    }
+
+    /// Stages exactly `paths` (as picked in the file selector); a path gone
+    /// from the work tree is staged as a deletion, and anything staged
+    /// beforehand but left unpicked goes back to its HEAD version.
+    pub fn stage_paths(&self, paths: &[String]) -> Result<StageSnapshot> {
+        let workdir = self
+            .repo
+            .workdir()
+            .ok_or(YetiError::BareRepo)?
+            .to_path_buf();
+        let ignore = self.yetiignore();
+        let mut skip_excluded =
+            |path: &Path, _: &[u8]| i32::from(ignore.is_excluded(&path.to_string_lossy()));
+        let head_tree = self
+            .repo
+            .head()
+            .ok()
+            .and_then(|head| head.peel_to_tree().ok());
+        let listed = self.changes(false, true)?;
+        let unpicked: Vec<&str> = listed
+            .iter()
+            .filter(|change| change.staged && !paths.contains(&change.path))
+            .map(|change| change.path.as_str())
+            .collect();
+        // Picking a deletion stages it, even with the file back on disk
+        // after `git rm --cached`.
+        let deletions: HashSet<&str> = listed
+            .iter()
+            .filter(|change| change.status == FileStatus::Deleted)
+            .map(|change| change.path.as_str())
+            .collect();
+        let held_back = {
+            let index = self.repo.index()?;
+            unpicked
+                .iter()
+                .map(|path| (path.as_bytes().to_vec(), index.get_path(Path::new(path), 0)))
+                .collect()
+        };
+        let mut snapshot = self.stage_with(|index| {
+            for &path in &unpicked {
+                let in_head = head_tree
+                    .as_ref()
+                    .and_then(|tree| tree.get_path(Path::new(path)).ok());
+                match in_head {
+                    Some(entry) => index.add(&IndexEntry {
+                        ctime: git2::IndexTime::new(0, 0),
+                        mtime: git2::IndexTime::new(0, 0),
+                        dev: 0,
+                        ino: 0,
+                        mode: entry.filemode() as u32,
+                        uid: 0,
+                        gid: 0,
+                        file_size: 0,
+                        id: entry.id(),
+                        flags: path.len().min(0xfff) as u16,
+                        flags_extended: 0,
+                        path: path.as_bytes().to_vec(),
+                    })?,
+                    None => index.remove_path(Path::new(path))?,
+                }
+            }
+            for path in paths {
+                if path.ends_with('/') {
+                    // An untracked directory listed without recursion.
+                    index.add_all(
+                        [path.as_str()].iter(),
+                        git2::IndexAddOption::DEFAULT,
+                        Some(&mut skip_excluded),
+                    )?;
+                } else if !deletions.contains(path.as_str())
+                    && workdir.join(path).symlink_metadata().is_ok()
+                {
+                    index.add_path(Path::new(path))?;
+                } else {
+                    index.remove_path(Path::new(path))?;
+                }
+            }
+            Ok(())
+        })?;
+        snapshot.held_back = held_back;
+        Ok(snapshot)
+    }
+
+    /// Unstaged work tree changes, untracked files included.
+    /// Without `recurse_untracked` an untracked directory is one `dir/` entry.
+    pub fn worktree_changes(&self, recurse_untracked: bool) -> Result<Vec<WorktreeChange>> {
+        self.changes(recurse_untracked, false)
+    }
+
+    /// What `--select` offers: work tree changes plus whatever is already
+    /// staged, since the selection decides the whole commit.
+    pub fn selectable_changes(&self, recurse_untracked: bool) -> Result<Vec<WorktreeChange>> {
+        self.changes(recurse_untracked, true)
+    }
+
+    /// Changed paths, one entry each; with `include_staged` a path with
+    /// nothing left unstaged is listed by its index status.
+    fn changes(
+        &self,
+        recurse_untracked: bool,
+        include_staged: bool,
+    ) -> Result<Vec<WorktreeChange>> {
+        let mut opts = StatusOptions::new();
+        opts.include_untracked(true)
+            .recurse_untracked_dirs(recurse_untracked);
+        let statuses = self.repo.statuses(Some(&mut opts))?;
+        let ignore = self.yetiignore();
+
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.path().is_some_and(|path| !ignore.is_excluded(path)))
+            .filter_map(|entry| {
+                let status = entry.status();
+                let staged = status.intersects(
+                    Status::INDEX_NEW
+                        | Status::INDEX_MODIFIED
+                        | Status::INDEX_DELETED
+                        | Status::INDEX_RENAMED
+                        | Status::INDEX_TYPECHANGE,
+                );
+                let status = if include_staged && status.contains(Status::INDEX_DELETED) {
+                    // Removed with `git rm --cached`: the file is untracked,
+                    // but the change to commit is the deletion.
+                    FileStatus::Deleted
+                } else if status.contains(Status::WT_NEW) {
+                    FileStatus::Added
+                } else if status.contains(Status::WT_DELETED) {
+                    FileStatus::Deleted
+                } else if status.intersects(Status::WT_MODIFIED | Status::WT_TYPECHANGE) {
+                    FileStatus::Modified
+                } else if !include_staged || !staged {
+                    return None;
+                } else if status.contains(Status::INDEX_NEW) {
+                    FileStatus::Added
+                } else if status.contains(Status::INDEX_DELETED) {
+                    FileStatus::Deleted
+                } else {
+                    FileStatus::Modified
+                };
+                Some(WorktreeChange {
+                    path: entry.path()?.to_string(),
+                    status,
+                    staged,
+                })
+            })
+            .collect())
+    }
+
+    fn stage_with(
+        &self,
+        apply: impl FnOnce(&mut git2::Index) -> std::result::Result<(), git2::Error>,
+    ) -> Result<StageSnapshot> {
+        let mut index = self.repo.index()?;
+        let mut before: HashMap<Vec<u8>, IndexEntry> =
+            index.iter().map(|e| (e.path.clone(), e)).collect();
+
+        apply(&mut index)?;
+        index.write()?;
+
+        let mut entries = Vec::new();
+        for after in index.iter() {
+            match before.remove(&after.path) {
+                Some(prev) if prev.id == after.id && prev.mode == after.mode => {}
+                prev => entries.push((after.path, prev)),
+            }
+        }
+        // Whatever is left was removed from the index (deleted in the worktree).
+        entries.extend(before.into_iter().map(|(path, prev)| (path, Some(prev))));
+
+        Ok(StageSnapshot {
+            entries,
+            held_back: Vec::new(),
+        })
     }
 
-    pub fn stage_all(&self) -> Result<()> {
+    /// Puts back the index entries recorded by `stage`.
+    pub fn restore_stage(&self, snapshot: StageSnapshot) -> Result<()> {
         let mut index = self.repo.index()?;
-        index.add_all(["*"].iter(), git2::IndexAddOption::DEFAULT, None)?;
+        for (path, entry) in snapshot.entries {
+            match entry {
+                Some(entry) => index.add(&entry)?,
+                None => {
+                    let path = String::from_utf8_lossy(&path).into_owned();
+                    index.remove_path(Path::new(&path))?;
+                }
+            }
+        }
         index.write()?;
         Ok(())
     }
 }
 
+/// Members of the Cargo workspace rooted at `root`, with `dir/*` globs
+/// expanded; empty when `root` isn't a workspace.
+pub fn read_workspace_crates(root: &Path) -> Vec<WorkspaceCrate> {
+    let manifest = |dir: &Path| -> Option<toml::Table> {
+        std::fs::read_to_string(dir.join("Cargo.toml"))
+            .ok()?
+            .parse()
+            .ok()
+    };
+    let Some(members) = manifest(root).and_then(|table| {
+        let members = table.get("workspace")?.get("members")?.as_array()?.clone();
+        Some(members)
+    }) else {
+        return Vec::new();
+    };
+
+    let mut dirs = Vec::new();
+    for member in members.iter().filter_map(|m| m.as_str()) {
+        let member = member.trim_end_matches('/');
+        match member.strip_suffix("/*") {
+            Some(parent) => {
+                let Ok(entries) = std::fs::read_dir(root.join(parent)) else {
+                    continue;
+                };
+                for entry in entries.flatten() {
+                    if entry.path().is_dir() {
+                        dirs.push(format!(
+                            "{}/{}",
+                            parent,
+                            entry.file_name().to_string_lossy()
+                        ));
+                    }
+                }
+            }
+            None => dirs.push(member.to_string()),
+        }
+    }
+    dirs.sort();
+
+    dirs.into_iter()
+        .filter_map(|dir| {
+            let table = manifest(&root.join(&dir))?;
+            let name = table.get("package")?.get("name")?.as_str()?.to_string();
+            Some(WorkspaceCrate { dir, name })
+        })
+        .collect()
+}
+
+/// Back-to-back identical subjects usually mean a re-run or an amend gone wrong.
+pub fn is_duplicate_subject(title: &str, previous: Option<&str>) -> bool {
+    previous.is_some_and(|prev| prev.trim() == title.trim())
+}
+
+/// Refuses a commit whose every file only changed line endings, since the
+/// model would see whole files rewritten and describe a change that isn't there.
+pub fn check_line_endings(files: &[FileInfo]) -> Result<()> {
+    if files.is_empty() || !files.iter().all(|f| f.eol_only) {
+        return Ok(());
+    }
+    Err(YetiError::LineEndingsOnly(
+        files.iter().map(|f| f.path.clone()).collect(),
+    ))
+}
+
 fn delta_path(delta: &git2::DiffDelta<'_>) -> Option<String> {
     delta
         .new_file()
@@ -147,16 +859,161 @@ fn delta_path(delta: &git2::DiffDelta<'_>) -> Option<String> {
         .map(|p| p.to_string_lossy().to_string())
 }
 
-pub fn commit_with_git_cli(title: &str, body: Option<&str>) -> Result<()> {
+pub fn ensure_git_cli() -> Result<()> {
+    check_git_cli(None)
+}
+
+fn check_git_cli(path_override: Option<&std::ffi::OsStr>) -> Result<()> {
     let mut cmd = std::process::Command::new("git");
-    cmd.arg("commit").arg("-m").arg(title).arg("--no-verify");
+    cmd.arg("--version");
+    if let Some(path) = path_override {
+        cmd.env("PATH", path);
+    }
+
+    match cmd.output() {
+        Ok(output) if output.status.success() => Ok(()),
+        _ => Err(YetiError::GitNotFound),
+    }
+}
+
+/// Wall-clock time carrying the local UTC offset, as git would record it.
+pub fn local_time_now() -> git2::Time {
+    git2::Signature::now("yeti", "yeti@localhost")
+        .map(|sig| sig.when())
+        .unwrap_or_else(|_| {
+            let secs = std::time::SystemTime::now()
+                .duration_since(std::time::UNIX_EPOCH)
+                .map(|d| d.as_secs() as i64)
+                .unwrap_or(0);
+            git2::Time::new(secs, 0)
+        })
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct CommitOptions {
+    pub no_verify: bool,
+    /// `Some("")` signs with the default key, `Some(id)` with a specific one.
+    pub sign: Option<String>,
+    /// Replace HEAD instead of adding a commit.
+    pub amend: bool,
+}
+
+pub fn validate_signing_key(key_id: &str) -> Result<()> {
+    if key_id.starts_with('-') || key_id.chars().any(|c| c.is_whitespace() || c.is_control()) {
+        return Err(YetiError::InvalidArgs(format!(
+            "'{}' is not a usable signing key id",
+            key_id
+        )));
+    }
+    Ok(())
+}
+
+fn signing_arg(sign: Option<&str>) -> Option<String> {
+    sign.map(|key_id| format!("-S{}", key_id))
+}
+
+/// Title and body go in separate `-m` args; git puts exactly one blank line
+/// between them, so neither may carry its own leading or trailing blank lines.
+fn commit_args(title: &str, body: Option<&str>, options: &CommitOptions) -> Vec<String> {
+    let mut args = vec![
+        "commit".to_string(),
+        "-m".to_string(),
+        title.trim().to_string(),
+    ];
+    if options.no_verify {
+        args.push("--no-verify".to_string());
+    }
+    if let Some(sign) = signing_arg(options.sign.as_deref()) {
+        args.push(sign);
+    }
+    if options.amend {
+        args.push("--amend".to_string());
+    }
 
-    if let Some(b) = body
-        && !b.is_empty()
+    if let Some(b) = body.map(|b| b.trim_matches(|c| c == '\n' || c == '\r'))
+        && !b.trim().is_empty()
     {
-        cmd.arg("-m").arg(b);
+        args.push("-m".to_string());
+        args.push(b.trim_end().to_string());
+    }
+
+    args
+}
+
+/// Commits with hooks running (unless `no_verify`). A hook that rewrites
+/// any of `paths`, like a formatter, gets its edits re-staged: a failed
+/// attempt is retried once, a passing one is amended. Returns the paths
+/// the hooks changed, so the caller can say the content moved.
+pub fn commit_respecting_hooks(
+    title: &str,
+    body: Option<&str>,
+    options: &CommitOptions,
+    paths: &[String],
+) -> Result<Vec<String>> {
+    commit_respecting_hooks_in(None, title, body, options, paths)
+}
+
+fn commit_respecting_hooks_in(
+    dir: Option<&Path>,
+    title: &str,
+    body: Option<&str>,
+    options: &CommitOptions,
+    paths: &[String],
+) -> Result<Vec<String>> {
+    if options.no_verify {
+        return commit_in(dir, title, body, options).map(|_| Vec::new());
+    }
+    let repo = GitRepo::discover_from(dir.unwrap_or(Path::new(".")))?;
+    // Edits already in the work tree are the user's, not the hook's.
+    let dirty_before = repo.worktree_modified(paths);
+
+    let first = commit_in(dir, title, body, options);
+    let rewritten: Vec<String> = repo
+        .worktree_modified(paths)
+        .into_iter()
+        .filter(|path| !dirty_before.contains(path))
+        .collect();
+    if rewritten.is_empty() {
+        return first.map(|_| Vec::new());
+    }
+
+    repo.restage(&rewritten)?;
+    match first {
+        Err(_) => commit_in(dir, title, body, options)?,
+        // Keep the message as committed, trailers from commit-msg hooks included.
+        Ok(()) => run_git_commit(dir, amend_no_edit_args(options))?,
+    }
+    Ok(rewritten)
+}
+
+fn amend_no_edit_args(options: &CommitOptions) -> Vec<String> {
+    let mut args = vec![
+        "commit".to_string(),
+        "--amend".to_string(),
+        "--no-edit".to_string(),
+        "--no-verify".to_string(),
+    ];
+    if let Some(sign) = signing_arg(options.sign.as_deref()) {
+        args.push(sign);
     }
+    args
+}
+
+fn commit_in(
+    dir: Option<&Path>,
+    title: &str,
+    body: Option<&str>,
+    options: &CommitOptions,
+) -> Result<()> {
+    run_git_commit(dir, commit_args(title, body, options))
+}
 
+fn run_git_commit(dir: Option<&Path>, args: Vec<String>) -> Result<()> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(args);
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
     let output = cmd
         .output()
         .map_err(|e| YetiError::CommitFailed(format!("Failed to run git commit: {}", e)))?;
@@ -177,38 +1034,139 @@ pub fn commit_with_git_cli(title: &str, body: Option<&str>) -> Result<()> {
     Ok(())
 }
 
-pub fn unstage_all_with_git_cli() -> Result<()> {
-    let output = std::process::Command::new("git")
-        .arg("reset")
-        .arg("--mixed")
-        .arg("--quiet")
+/// Rejects names git would refuse as `refs/tags/<name>`.
+pub fn validate_tag_name(name: &str) -> Result<()> {
+    if git2::Reference::is_valid_name(&format!("refs/tags/{}", name)) {
+        Ok(())
+    } else {
+        Err(YetiError::InvalidArgs(format!(
+            "--tag {:?} is not a valid tag name",
+            name
+        )))
+    }
+}
+
+fn tag_args(name: &str, message: &str) -> Vec<String> {
+    vec![
+        "tag".to_string(),
+        "-a".to_string(),
+        name.to_string(),
+        "-m".to_string(),
+        message.to_string(),
+    ]
+}
+
+/// Creates an annotated tag on HEAD, so it lands on the commit just made.
+pub fn tag_with_git_cli(name: &str, message: &str) -> Result<()> {
+    tag_in(None, name, message)
+}
+
+fn tag_in(dir: Option<&Path>, name: &str, message: &str) -> Result<()> {
+    let mut cmd = std::process::Command::new("git");
+    cmd.args(tag_args(name, message));
+    if let Some(dir) = dir {
+        cmd.current_dir(dir);
+    }
+    let output = cmd
         .output()
-        .map_err(|e| YetiError::CommitFailed(format!("Failed to run git reset: {}", e)))?;
+        .map_err(|e| YetiError::TagFailed(format!("Failed to run git tag: {}", e)))?;
 
     if !output.status.success() {
         let stderr = String::from_utf8_lossy(&output.stderr);
-        let stdout = String::from_utf8_lossy(&output.stdout);
-        let msg = if !stderr.is_empty() {
-            stderr.to_string()
-        } else if !stdout.is_empty() {
-            stdout.to_string()
-        } else {
-            "Git reset failed".to_string()
-        };
-        return Err(YetiError::CommitFailed(msg));
+        return Err(YetiError::TagFailed(stderr.trim().to_string()));
     }
-
     Ok(())
 }
 
 #[cfg(test)]
 mod tests {
-    use super::{GitRepo, Result};
-    use crate::prompt::FileStatus;
-    use git2::{Repository, Signature};
+    use super::test_support::{create_temp_repo_dir, init_repo_with_initial_commit, write_file};
+    use super::{
+        CommitOptions, GitRepo, Identity, RECENT_COMMIT_DIFF_BYTES, Result, StageMode,
+        WorktreeChange, check_git_cli, check_line_endings, commit_args, commit_in,
+        commit_respecting_hooks_in, is_duplicate_subject, read_workspace_crates, signing_arg,
+        tag_args, tag_in, validate_signing_key, validate_tag_name,
+    };
+    use crate::error::YetiError;
+    use crate::prompt::{
+        ContentAttr, FileInfo, FileStatus, PATCH_BUDGET, PromptOptions, WorkspaceCrate,
+        resolve_scope_hint,
+    };
     use std::fs;
-    use std::path::{Path, PathBuf};
-    use std::time::{SystemTime, UNIX_EPOCH};
+    use std::path::Path;
+
+    #[test]
+    fn workspace_members_map_files_to_crate_scopes() -> Result<()> {
+        let root = create_temp_repo_dir("workspace");
+        write_file(
+            &root.join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\", \"tools/cli\"]\n",
+        )?;
+        write_file(
+            &root.join("crates/core/Cargo.toml"),
+            "[package]\nname = \"yeti-core\"\n",
+        )?;
+        write_file(
+            &root.join("crates/net/Cargo.toml"),
+            "[package]\nname = \"yeti-net\"\n",
+        )?;
+        write_file(&root.join("crates/notes/README.md"), "not a crate\n")?;
+        write_file(
+            &root.join("tools/cli/Cargo.toml"),
+            "[package]\nname = \"yeti_cli\"\n",
+        )?;
+
+        let crates = read_workspace_crates(&root);
+        assert_eq!(
+            crates,
+            vec![
+                WorkspaceCrate {
+                    dir: "crates/core".to_string(),
+                    name: "yeti-core".to_string(),
+                },
+                WorkspaceCrate {
+                    dir: "crates/net".to_string(),
+                    name: "yeti-net".to_string(),
+                },
+                WorkspaceCrate {
+                    dir: "tools/cli".to_string(),
+                    name: "yeti_cli".to_string(),
+                },
+            ]
+        );
+
+        let files = |paths: &[&str]| -> Vec<FileInfo> {
+            paths
+                .iter()
+                .map(|path| FileInfo {
+                    additions: 1,
+                    ..FileInfo::new(path, FileStatus::Modified)
+                })
+                .collect()
+        };
+        let scope = |paths: &[&str]| resolve_scope_hint(&files(paths), Some("yeti"), &crates);
+
+        assert_eq!(
+            scope(&["crates/core/src/lib.rs", "crates/core/Cargo.toml"]).as_deref(),
+            Some("YETI-CORE")
+        );
+        assert_eq!(
+            scope(&["tools/cli/src/main.rs"]).as_deref(),
+            Some("YETI-CLI")
+        );
+        assert_eq!(
+            scope(&["crates/core/src/lib.rs", "crates/net/src/lib.rs"]).as_deref(),
+            Some("YETI")
+        );
+        assert_eq!(
+            scope(&["crates/core-extra/src/lib.rs"]).as_deref(),
+            Some("CORE-EXTRA")
+        );
+        assert!(read_workspace_crates(&root.join("crates/core")).is_empty());
+
+        let _ = fs::remove_dir_all(&root);
+        Ok(())
+    }
 
     #[test]
     fn staged_summary_detects_rename_and_tracks_old_path() -> Result<()> {
@@ -230,7 +1188,7 @@ mod tests {
         write_file(&new_path, "one\ntwo\nunstaged-extra\n")?;
 
         let git_repo = GitRepo { repo };
-        let summary = git_repo.get_staged_summary()?;
+        let summary = git_repo.get_staged_summary(PATCH_BUDGET)?;
         let renamed = summary
             .files
             .iter()
@@ -249,22 +1207,173 @@ mod tests {
     }
 
     #[test]
-    fn staged_summary_uses_index_not_working_tree_for_patch() -> Result<()> {
-        let temp_dir = create_temp_repo_dir("staged-only");
-        let repo = init_repo_with_initial_commit(&temp_dir)?;
-        let file_path = temp_dir.join("src/file.txt");
+    fn staged_diffs_are_read_up_to_the_given_budget() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("diff-budget");
+        let git = GitRepo {
+            repo: init_repo_with_initial_commit(&temp_dir)?,
+        };
+        let big: String = (0..2000).map(|i| format!("line {}\n", i)).collect();
+        write_file(&temp_dir.join("src/file.txt"), &big)?;
+        git.stage(StageMode::All)?;
 
-        write_file(&file_path, "one\ntwo\nstaged-only\n")?;
-        {
-            let mut index = repo.index()?;
-            index.add_path(Path::new("src/file.txt"))?;
-            index.write()?;
-        }
+        let full = git.get_staged_summary(PATCH_BUDGET)?;
+        assert!(full.files[0].diff.len() > 10_000);
+        assert!(full.files[0].diff.len() < PATCH_BUDGET + 100);
+        let capped = git.get_staged_summary(1000)?;
+        assert!(capped.files[0].diff.len() < 1100);
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn autocrlf_normalization_is_reported_as_line_endings_only() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("autocrlf");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let file_path = temp_dir.join("src/file.txt");
+        write_file(&file_path, "one\r\ntwo\r\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let sig = git2::Signature::now("yeti-tests", "yeti-tests@example.com")?;
+        let head = repo.head()?.peel_to_commit()?;
+        repo.commit(Some("HEAD"), &sig, &sig, "crlf", &tree, &[&head])?;
+        drop((tree, head));
+
+        // Re-adding with autocrlf on stores LF, so the blob differs only in
+        // endings; dropping the entry first is what `git add --renormalize` does.
+        repo.config()?.set_bool("core.autocrlf", true)?;
+        {
+            let mut index = repo.index()?;
+            index.remove_path(Path::new("src/file.txt"))?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        let git_repo = GitRepo { repo };
+        let summary = git_repo.get_staged_summary(PATCH_BUDGET)?;
+        assert_eq!(summary.files.len(), 1);
+        assert!(summary.files[0].eol_only);
+        match check_line_endings(&summary.files) {
+            Err(YetiError::LineEndingsOnly(paths)) => assert_eq!(paths, ["src/file.txt"]),
+            other => panic!("expected a line-ending error, got {:?}", other),
+        }
+
+        write_file(&temp_dir.join("src/other.txt"), "real change\n")?;
+        git_repo.stage(StageMode::All)?;
+        let summary = git_repo.get_staged_summary(PATCH_BUDGET)?;
+        assert!(check_line_endings(&summary.files).is_ok());
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn recent_commits_come_newest_first_with_their_patches() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("recent-commits");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let sig = git2::Signature::now("yeti-tests", "yeti-tests@example.com")?;
+        let head = repo.head()?.peel_to_commit()?;
+        repo.commit(Some("HEAD"), &sig, &sig, "add line three", &tree, &[&head])?;
+        drop((tree, head));
+        let git = GitRepo { repo };
+
+        let recent = git.recent_commits("HEAD", 5)?;
+        let subjects: Vec<&str> = recent.iter().map(|c| c.subject.as_str()).collect();
+        assert_eq!(subjects, ["add line three", "initial commit"]);
+        assert!(recent[0].diff.contains("+three\n"), "{}", recent[0].diff);
+        assert!(recent[1].diff.contains("+one\n"));
+
+        assert_eq!(git.recent_commits("HEAD^", 5)?.len(), 1);
+        assert_eq!(git.recent_commits("HEAD", 1)?.len(), 1);
+
+        // A huge commit is read only up to the cap.
+        let big: String = (0..5000).map(|i| format!("line {}\n", i)).collect();
+        write_file(&temp_dir.join("src/big.txt"), &big)?;
+        git.stage(StageMode::All)?;
+        let tree = git.repo.find_tree(git.repo.index()?.write_tree()?)?;
+        let head = git.repo.head()?.peel_to_commit()?;
+        git.repo
+            .commit(Some("HEAD"), &sig, &sig, "add a big file", &tree, &[&head])?;
+        drop((tree, head));
+        let recent = git.recent_commits("HEAD", 1)?;
+        assert!(recent[0].diff.len() < RECENT_COMMIT_DIFF_BYTES + 100);
+        assert!(recent[0].diff.contains("+line 0\n"));
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn hunks_per_file_are_counted_and_listed_in_the_prompt() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("hunks");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let lines: Vec<String> = (1..=30).map(|n| format!("line {}", n)).collect();
+        write_file(&temp_dir.join("src/file.txt"), &lines.join("\n"))?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+        let tree = repo.find_tree(repo.index()?.write_tree()?)?;
+        let sig = git2::Signature::now("yeti-tests", "yeti-tests@example.com")?;
+        let head = repo.head()?.peel_to_commit()?;
+        repo.commit(Some("HEAD"), &sig, &sig, "thirty lines", &tree, &[&head])?;
+        drop((tree, head));
+
+        let mut edited = lines.clone();
+        edited[1] = "line two".to_string();
+        edited[27] = "line twenty-eight".to_string();
+        write_file(&temp_dir.join("src/file.txt"), &edited.join("\n"))?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        let git_repo = GitRepo { repo };
+        let summary = git_repo.get_staged_summary(PATCH_BUDGET)?;
+        assert_eq!(summary.files[0].hunks, 2);
+
+        let prompt =
+            crate::prompt::build_user_prompt("main", &summary.files, &PromptOptions::default());
+        assert!(prompt.contains("(modified: +2/-2) (2 hunks)"), "{prompt}");
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn staged_summary_uses_index_not_working_tree_for_patch() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("staged-only");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let file_path = temp_dir.join("src/file.txt");
+
+        write_file(&file_path, "one\ntwo\nstaged-only\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
 
         write_file(&file_path, "one\ntwo\nstaged-only\nunstaged-only\n")?;
 
         let git_repo = GitRepo { repo };
-        let summary = git_repo.get_staged_summary()?;
+        let summary = git_repo.get_staged_summary(PATCH_BUDGET)?;
         let changed = summary
             .files
             .iter()
@@ -280,7 +1389,754 @@ mod tests {
         Ok(())
     }
 
-    fn create_temp_repo_dir(suffix: &str) -> PathBuf {
+    #[test]
+    fn mark_ignored_flags_force_added_ignored_files() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("force-added");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join(".gitignore"), "*.log\n")?;
+        write_file(&temp_dir.join("debug.log"), "trace\n")?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("debug.log"))?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        let git_repo = GitRepo { repo };
+        let mut files = git_repo.get_staged_summary(PATCH_BUDGET)?.files;
+        git_repo.mark_ignored(&mut files)?;
+
+        let log = files.iter().find(|f| f.path == "debug.log").unwrap();
+        let tracked = files.iter().find(|f| f.path == "src/file.txt").unwrap();
+        assert!(log.ignored);
+        assert_eq!(log.status_tag(), "A!");
+        assert!(!tracked.ignored);
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn stage_modes_control_which_changes_are_staged() -> Result<()> {
+        let staged_paths = |mode| -> Result<Vec<String>> {
+            let temp_dir = create_temp_repo_dir("stage-mode");
+            let repo = init_repo_with_initial_commit(&temp_dir)?;
+            write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+            write_file(&temp_dir.join("scratch.txt"), "notes\n")?;
+            write_file(&temp_dir.join("src/ready.txt"), "ready\n")?;
+            {
+                let mut index = repo.index()?;
+                index.add_path(Path::new("src/ready.txt"))?;
+                index.write()?;
+            }
+
+            let git_repo = GitRepo { repo };
+            git_repo.stage(mode)?;
+            let mut paths: Vec<String> = git_repo
+                .get_staged_summary(PATCH_BUDGET)?
+                .files
+                .into_iter()
+                .map(|f| f.path)
+                .collect();
+            paths.sort();
+
+            drop(git_repo);
+            let _ = fs::remove_dir_all(&temp_dir);
+            Ok(paths)
+        };
+
+        assert_eq!(
+            staged_paths(StageMode::All)?,
+            ["scratch.txt", "src/file.txt", "src/ready.txt"]
+        );
+        assert_eq!(
+            staged_paths(StageMode::Tracked)?,
+            ["src/file.txt", "src/ready.txt"]
+        );
+        assert_eq!(staged_paths(StageMode::StagedOnly)?, ["src/ready.txt"]);
+        Ok(())
+    }
+
+    #[test]
+    fn restore_stage_keeps_changes_staged_before_yeti_ran() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("restore-stage");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        // The user staged one version of the file, then kept editing it.
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nstaged\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+        write_file(
+            &temp_dir.join("src/file.txt"),
+            "one\ntwo\nstaged\nunstaged\n",
+        )?;
+        write_file(&temp_dir.join("src/new.txt"), "fresh\n")?;
+
+        let git_repo = GitRepo { repo };
+        let snapshot = git_repo.stage(StageMode::All)?;
+        assert!(!snapshot.is_empty());
+        git_repo.restore_stage(snapshot)?;
+
+        let files = git_repo.get_staged_summary(PATCH_BUDGET)?.files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].path, "src/file.txt");
+        assert!(files[0].diff.contains("+staged"));
+        assert!(!files[0].diff.contains("+unstaged"));
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn subject_matching_head_is_flagged_as_duplicate() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("duplicate-subject");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let git_repo = GitRepo { repo };
+        let head = git_repo.head_subject();
+
+        assert_eq!(head.as_deref(), Some("initial commit"));
+        assert!(is_duplicate_subject("initial commit", head.as_deref()));
+        assert!(!is_duplicate_subject(
+            "feat[CORE]: add thing",
+            head.as_deref()
+        ));
+        assert!(!is_duplicate_subject("initial commit", None));
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn lfs_and_binary_attributes_annotate_files_and_drop_their_diffs() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("lfs-attrs");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(
+            &temp_dir.join(".gitattributes"),
+            "*.psd filter=lfs diff=lfs merge=lfs -text\n*.dat binary\n",
+        )?;
+        write_file(
+            &temp_dir.join("art/cover.psd"),
+            "version https://git-lfs.github.com/spec/v1\noid sha256:abc\nsize 42\n",
+        )?;
+        write_file(&temp_dir.join("data/blob.dat"), "raw\n")?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+
+        let git_repo = GitRepo { repo };
+        git_repo.stage(StageMode::All)?;
+        let files = git_repo.get_staged_summary(PATCH_BUDGET)?.files;
+        let find = |path: &str| files.iter().find(|f| f.path == path).unwrap();
+
+        let psd = find("art/cover.psd");
+        assert_eq!(psd.attr, Some(ContentAttr::Lfs));
+        assert_eq!(psd.annotated_path(), "art/cover.psd (LFS)");
+        assert!(psd.diff.is_empty());
+        assert_eq!(find("data/blob.dat").attr, Some(ContentAttr::Binary));
+        assert_eq!(find("src/file.txt").attr, None);
+        assert!(!find("src/file.txt").diff.is_empty());
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn identity_is_read_from_repository_config() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("identity");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        {
+            let mut config = repo.config()?;
+            config.set_str("user.name", "Yeti Tester")?;
+            config.set_str("user.email", "yeti@example.com")?;
+        }
+
+        let git_repo = GitRepo { repo };
+        let identity = git_repo.identity();
+        assert_eq!(identity.name.as_deref(), Some("Yeti Tester"));
+        assert_eq!(identity.email.as_deref(), Some("yeti@example.com"));
+        assert!(identity.missing_keys().is_empty());
+
+        let unset = Identity {
+            name: None,
+            email: Some("yeti@example.com".to_string()),
+        };
+        assert_eq!(unset.missing_keys(), ["user.name"]);
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn staged_symlinks_are_annotated_with_their_target() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("symlink");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        std::os::unix::fs::symlink("src/file.txt", temp_dir.join("current"))?;
+
+        let git_repo = GitRepo { repo };
+        git_repo.stage(StageMode::All)?;
+        let files = git_repo.get_staged_summary(PATCH_BUDGET)?.files;
+        let link = files.iter().find(|f| f.path == "current").unwrap();
+
+        assert_eq!(link.symlink_target.as_deref(), Some("src/file.txt"));
+        assert_eq!(link.annotated_path(), "symlink current → src/file.txt");
+        assert!(link.diff.is_empty());
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn signing_argument_carries_optional_key_id() {
+        assert_eq!(signing_arg(None), None);
+        assert_eq!(signing_arg(Some("")).as_deref(), Some("-S"));
+        assert_eq!(signing_arg(Some("ABCD1234")).as_deref(), Some("-SABCD1234"));
+
+        let args = commit_args(
+            "fix: sign",
+            None,
+            &CommitOptions {
+                no_verify: false,
+                sign: Some("ABCD1234".to_string()),
+                amend: false,
+            },
+        );
+        assert_eq!(args, vec!["commit", "-m", "fix: sign", "-SABCD1234"]);
+
+        assert!(validate_signing_key("ABCD1234").is_ok());
+        assert!(validate_signing_key("me@example.com").is_ok());
+        assert!(validate_signing_key("--amend").is_err());
+        assert!(validate_signing_key("AB CD").is_err());
+    }
+
+    #[test]
+    fn untracked_directories_are_skipped_without_recursion() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("recurse-untracked");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join("new.txt"), "top level\n")?;
+        write_file(&temp_dir.join("vendor/big/a.txt"), "a\n")?;
+        write_file(&temp_dir.join("vendor/big/b.txt"), "b\n")?;
+        let git = GitRepo { repo };
+
+        let paths = |changes: Vec<WorktreeChange>| {
+            let mut paths: Vec<String> = changes.into_iter().map(|c| c.path).collect();
+            paths.sort();
+            paths
+        };
+        assert_eq!(
+            paths(git.worktree_changes(true)?),
+            ["new.txt", "vendor/big/a.txt", "vendor/big/b.txt"]
+        );
+        assert_eq!(paths(git.worktree_changes(false)?), ["new.txt", "vendor/"]);
+
+        git.stage(StageMode::AllTopLevel)?;
+        let staged: Vec<String> = git
+            .get_staged_summary(PATCH_BUDGET)?
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        assert_eq!(staged, ["new.txt"]);
+
+        git.stage(StageMode::All)?;
+        assert_eq!(git.get_staged_summary(PATCH_BUDGET)?.files.len(), 3);
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn yetiignore_keeps_paths_out_of_staging_and_the_summary() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("yetiignore");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(
+            &temp_dir.join(".yetiignore"),
+            "target/\n*.snap\n!keep.snap\n",
+        )?;
+        write_file(&temp_dir.join("target/debug/out.o"), "object\n")?;
+        write_file(&temp_dir.join("tests/ui.snap"), "snapshot\n")?;
+        write_file(&temp_dir.join("tests/keep.snap"), "snapshot\n")?;
+        write_file(&temp_dir.join("src/file.txt"), "edited\n")?;
+        let git = GitRepo { repo };
+
+        git.stage(StageMode::All)?;
+        let index = git.repo.index()?;
+        assert!(index.get_path(Path::new("tests/ui.snap"), 0).is_none());
+        assert!(index.get_path(Path::new("target/debug/out.o"), 0).is_none());
+
+        let mut staged: Vec<String> = git
+            .get_staged_summary(PATCH_BUDGET)?
+            .files
+            .into_iter()
+            .map(|f| f.path)
+            .collect();
+        staged.sort();
+        assert_eq!(staged, [".yetiignore", "src/file.txt", "tests/keep.snap"]);
+
+        // Staged by hand, an excluded file still stays out of the prompt.
+        let mut index = git.repo.index()?;
+        index.add_path(Path::new("tests/ui.snap"))?;
+        index.write()?;
+        let summary = git.get_staged_summary(PATCH_BUDGET)?;
+        assert!(summary.files.iter().all(|f| f.path != "tests/ui.snap"));
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn selected_paths_are_the_only_ones_staged() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("select");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        write_file(&temp_dir.join("src/file.txt"), "edited\n")?;
+        write_file(&temp_dir.join("notes.md"), "unrelated\n")?;
+        write_file(&temp_dir.join("src/new.rs"), "fn main() {}\n")?;
+        write_file(&temp_dir.join("README.md"), "staged earlier\n")?;
+        let mut index = repo.index()?;
+        index.add_path(Path::new("src/file.txt"))?;
+        index.add_path(Path::new("README.md"))?;
+        index.write()?;
+        drop(index);
+        // Only part of the file.txt edit is staged.
+        write_file(&temp_dir.join("src/file.txt"), "edited\nmore\n")?;
+
+        let git = GitRepo { repo };
+        let mut changes = git.selectable_changes(true)?;
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        let change = |path: &str, status, staged| WorktreeChange {
+            path: path.to_string(),
+            status,
+            staged,
+        };
+        assert_eq!(
+            changes,
+            vec![
+                change("README.md", FileStatus::Added, true),
+                change("notes.md", FileStatus::Added, false),
+                change("src/file.txt", FileStatus::Modified, true),
+                change("src/new.rs", FileStatus::Added, false),
+            ]
+        );
+
+        // Both staged paths are left unpicked.
+        let staged_paths = |git: &GitRepo| -> Result<Vec<String>> {
+            let mut paths: Vec<String> = git
+                .get_staged_summary(PATCH_BUDGET)?
+                .files
+                .into_iter()
+                .map(|f| f.path)
+                .collect();
+            paths.sort();
+            Ok(paths)
+        };
+        let picked = ["notes.md".to_string(), "src/new.rs".to_string()];
+        let snapshot = git.stage_paths(&picked)?;
+        assert_eq!(staged_paths(&git)?, ["notes.md", "src/new.rs"]);
+        assert_eq!(
+            fs::read_to_string(temp_dir.join("src/file.txt"))?,
+            "edited\nmore\n"
+        );
+
+        // A failure puts everything back.
+        git.restore_stage(snapshot)?;
+        assert_eq!(staged_paths(&git)?, ["README.md", "src/file.txt"]);
+
+        // After the commit, the held-back entries are staged again as they were.
+        let mut snapshot = git.stage_paths(&picked)?;
+        let tree = git.repo.find_tree(git.repo.index()?.write_tree()?)?;
+        let parent = git.repo.head()?.peel_to_commit()?;
+        let sig = git2::Signature::now("yeti-tests", "yeti-tests@example.com")?;
+        git.repo
+            .commit(Some("HEAD"), &sig, &sig, "feat: picked", &tree, &[&parent])?;
+        git.restore_stage(snapshot.take_held_back())?;
+        assert_eq!(staged_paths(&git)?, ["README.md", "src/file.txt"]);
+        let index = git.repo.index()?;
+        let entry = index
+            .get_path(Path::new("src/file.txt"), 0)
+            .expect("file.txt stays staged");
+        let blob = git.repo.find_blob(entry.id)?;
+        assert_eq!(blob.content(), b"edited\n");
+
+        drop((blob, index, tree, parent));
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn cached_removals_are_offered_and_picked_as_deletions() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("select-rm-cached");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let mut index = repo.index()?;
+        index.remove_path(Path::new("src/file.txt"))?;
+        index.write()?;
+        drop(index);
+
+        let git = GitRepo { repo };
+        assert_eq!(
+            git.selectable_changes(true)?,
+            vec![WorktreeChange {
+                path: "src/file.txt".to_string(),
+                status: FileStatus::Deleted,
+                staged: true,
+            }]
+        );
+        git.stage_paths(&["src/file.txt".to_string()])?;
+        let files = git.get_staged_summary(PATCH_BUDGET)?.files;
+        assert_eq!(files.len(), 1);
+        assert_eq!(files[0].status, FileStatus::Deleted);
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn partially_staged_files_lists_files_with_unstaged_remainder() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("partial-stage");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let file_path = temp_dir.join("src/file.txt");
+        let other_path = temp_dir.join("src/other.txt");
+
+        write_file(&file_path, "one\ntwo\nstaged\n")?;
+        write_file(&other_path, "fully staged\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.add_path(Path::new("src/other.txt"))?;
+            index.write()?;
+        }
+        write_file(&file_path, "one\ntwo\nstaged\nunstaged\n")?;
+
+        let git_repo = GitRepo { repo };
+        let partial = git_repo.partially_staged_files()?;
+
+        assert_eq!(partial, vec!["src/file.txt".to_string()]);
+
+        drop(git_repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn git_preflight_reports_missing_binary() {
+        let empty_dir = create_temp_repo_dir("no-git-path");
+        let result = check_git_cli(Some(empty_dir.as_os_str()));
+
+        assert!(matches!(result, Err(YetiError::GitNotFound)));
+        let _ = fs::remove_dir_all(&empty_dir);
+    }
+
+    #[test]
+    fn amend_summary_covers_head_and_new_changes() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("amend");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        let git = GitRepo {
+            repo: git2::Repository::open(&temp_dir)?,
+        };
+        assert!(matches!(
+            git.amend_summary(PATCH_BUDGET),
+            Err(YetiError::NothingToAmend)
+        ));
+
+        write_file(&temp_dir.join("notes.md"), "hello\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("notes.md"))?;
+            index.write()?;
+        }
+
+        let summary = git.amend_summary(PATCH_BUDGET)?;
+        let mut paths: Vec<&str> = summary.files.iter().map(|f| f.path.as_str()).collect();
+        paths.sort();
+        assert_eq!(paths, ["notes.md", "src/file.txt"]);
+
+        let options = CommitOptions {
+            amend: true,
+            ..CommitOptions::default()
+        };
+        assert!(commit_args("feat: x", None, &options).contains(&"--amend".to_string()));
+
+        drop(git);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn tag_args_build_an_annotated_tag() {
+        assert_eq!(
+            tag_args("v0.2.0", "feat[CLI]: add --tag"),
+            vec!["tag", "-a", "v0.2.0", "-m", "feat[CLI]: add --tag"]
+        );
+        assert!(validate_tag_name("v0.2.0").is_ok());
+        assert!(validate_tag_name("release/2024-06").is_ok());
+        assert!(validate_tag_name("bad..name").is_err());
+        assert!(validate_tag_name("has space").is_err());
+    }
+
+    #[test]
+    fn tag_lands_on_head_with_the_message() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("tag");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        {
+            let mut config = repo.config()?;
+            config.set_str("user.name", "yeti-tests")?;
+            config.set_str("user.email", "yeti-tests@example.com")?;
+            config.set_bool("tag.gpgSign", false)?;
+        }
+
+        tag_in(Some(&temp_dir), "v1.0.0", "fix[CORE]: handle empty input")?;
+
+        let tag = repo
+            .revparse_single("refs/tags/v1.0.0")?
+            .into_tag()
+            .expect("annotated tag");
+        assert_eq!(tag.message(), Some("fix[CORE]: handle empty input\n"));
+        assert_eq!(tag.target_id(), repo.head()?.peel_to_commit()?.id());
+
+        assert!(matches!(
+            tag_in(Some(&temp_dir), "v1.0.0", "again"),
+            Err(YetiError::TagFailed(_))
+        ));
+
+        drop(tag);
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn committed_message_has_one_blank_line_between_subject_and_body() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("separator");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        {
+            let mut config = repo.config()?;
+            config.set_str("user.name", "yeti-tests")?;
+            config.set_str("user.email", "yeti-tests@example.com")?;
+            config.set_bool("commit.gpgSign", false)?;
+            // Verbatim keeps whatever blank lines we pass, so doubling would show.
+            config.set_str("commit.cleanup", "verbatim")?;
+        }
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        commit_in(
+            Some(&temp_dir),
+            "fix[CORE]: keep a single separator",
+            Some("\n\nBody line one.\nBody line two.\n\n"),
+            &CommitOptions::default(),
+        )?;
+
+        let head = repo.head()?.peel_to_commit()?;
+        assert_eq!(
+            head.message(),
+            Some("fix[CORE]: keep a single separator\n\nBody line one.\nBody line two.\n")
+        );
+
+        drop(head);
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn gitmoji_titles_reach_the_commit_unchanged() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("gitmoji");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        {
+            let mut config = repo.config()?;
+            config.set_str("user.name", "yeti-tests")?;
+            config.set_str("user.email", "yeti-tests@example.com")?;
+            config.set_bool("commit.gpgSign", false)?;
+        }
+        write_file(&temp_dir.join("src/file.txt"), "sparkle\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        commit_in(
+            Some(&temp_dir),
+            "✨ feat[CORE]: add sparkle",
+            None,
+            &CommitOptions::default(),
+        )?;
+
+        let head = repo.head()?.peel_to_commit()?;
+        assert_eq!(head.summary(), Some("✨ feat[CORE]: add sparkle"));
+
+        drop(head);
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn discovering_a_bare_repo_is_refused() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("bare");
+        git2::Repository::init_bare(&temp_dir)?;
+
+        assert!(matches!(
+            GitRepo::discover_from(&temp_dir),
+            Err(YetiError::BareRepo)
+        ));
+
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn hooks_run_and_their_rewrites_are_restaged() -> Result<()> {
+        use std::os::unix::fs::PermissionsExt;
+
+        let temp_dir = create_temp_repo_dir("hooks");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        {
+            let mut config = repo.config()?;
+            config.set_str("user.name", "yeti-tests")?;
+            config.set_str("user.email", "yeti-tests@example.com")?;
+            config.set_bool("commit.gpgSign", false)?;
+        }
+        let hook = repo.path().join("hooks/pre-commit");
+        let install = |script: &str| -> Result<()> {
+            write_file(&hook, script)?;
+            fs::set_permissions(&hook, fs::Permissions::from_mode(0o755))?;
+            Ok(())
+        };
+        let stage = |content: &str| -> Result<()> {
+            write_file(&temp_dir.join("src/file.txt"), content)?;
+            let mut index = repo.index()?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+            Ok(())
+        };
+        let paths = vec!["src/file.txt".to_string()];
+
+        // A lint hook that fails is reported with its output.
+        install("#!/bin/sh\necho 'lint: trailing whitespace' >&2\nexit 1\n")?;
+        stage("messy  \n")?;
+        let failed = commit_respecting_hooks_in(
+            Some(&temp_dir),
+            "fix: lint",
+            None,
+            &CommitOptions::default(),
+            &paths,
+        );
+        let Err(YetiError::CommitFailed(output)) = failed else {
+            panic!("a failing hook should fail the commit");
+        };
+        assert!(output.contains("lint: trailing whitespace"), "{output}");
+
+        // A formatter that rewrites and fails, pre-commit style, is retried with its edits.
+        install(
+            "#!/bin/sh\nif grep -q messy src/file.txt; then\n  printf 'tidy\\n' > src/file.txt\n  exit 1\nfi\n",
+        )?;
+        let rewritten = commit_respecting_hooks_in(
+            Some(&temp_dir),
+            "style: tidy",
+            None,
+            &CommitOptions::default(),
+            &paths,
+        )?;
+        assert_eq!(rewritten, paths);
+        let head = repo.head()?.peel_to_commit()?;
+        let blob = head
+            .tree()?
+            .get_path(Path::new("src/file.txt"))?
+            .to_object(&repo)?;
+        assert_eq!(blob.as_blob().map(|b| b.content()), Some(&b"tidy\n"[..]));
+        assert_eq!(head.summary(), Some("style: tidy"));
+        drop(blob);
+        drop(head);
+
+        // A formatter that rewrites and passes is amended in, and the amend
+        // keeps the trailer a commit-msg hook added.
+        install(
+            "#!/bin/sh\nif grep -q messy src/file.txt; then\n  printf 'neat\\n' > src/file.txt\nfi\n",
+        )?;
+        let commit_msg = repo.path().join("hooks/commit-msg");
+        write_file(
+            &commit_msg,
+            "#!/bin/sh\nprintf '\\nChange-Id: I0123\\n' >> \"$1\"\n",
+        )?;
+        fs::set_permissions(&commit_msg, fs::Permissions::from_mode(0o755))?;
+        stage("messy again\n")?;
+        let rewritten = commit_respecting_hooks_in(
+            Some(&temp_dir),
+            "style: neat",
+            Some("Body text."),
+            &CommitOptions::default(),
+            &paths,
+        )?;
+        assert_eq!(rewritten, paths);
+        let head = repo.head()?.peel_to_commit()?;
+        let blob = head
+            .tree()?
+            .get_path(Path::new("src/file.txt"))?
+            .to_object(&repo)?;
+        assert_eq!(blob.as_blob().map(|b| b.content()), Some(&b"neat\n"[..]));
+        let message = head.message().unwrap_or_default();
+        assert!(
+            message.starts_with("style: neat\n\nBody text."),
+            "{message}"
+        );
+        assert!(message.contains("Change-Id: I0123"), "{message}");
+        assert_eq!(head.parent(0)?.summary(), Some("style: tidy"));
+
+        drop(blob);
+        drop(head);
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn commit_args_respect_no_verify_setting() {
+        let options = |no_verify| CommitOptions {
+            no_verify,
+            ..CommitOptions::default()
+        };
+        let with_flag = commit_args("feat: add thing", Some("Body text."), &options(true));
+        let without_flag = commit_args("feat: add thing", Some("Body text."), &options(false));
+
+        assert!(with_flag.iter().any(|a| a == "--no-verify"));
+        assert!(!without_flag.iter().any(|a| a == "--no-verify"));
+        assert_eq!(
+            without_flag,
+            vec!["commit", "-m", "feat: add thing", "-m", "Body text."]
+        );
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod test_support {
+    use super::GitRepo;
+    use crate::error::Result;
+    use git2::{Repository, Signature};
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    pub fn git_repo(repo: Repository) -> GitRepo {
+        GitRepo { repo }
+    }
+
+    pub fn create_temp_repo_dir(suffix: &str) -> PathBuf {
         let timestamp = SystemTime::now()
             .duration_since(UNIX_EPOCH)
             .expect("time went backwards")
@@ -293,7 +2149,7 @@ mod tests {
         dir
     }
 
-    fn init_repo_with_initial_commit(path: &Path) -> Result<Repository> {
+    pub fn init_repo_with_initial_commit(path: &Path) -> Result<Repository> {
         let repo = Repository::init(path)?;
         let file_path = path.join("src/file.txt");
         write_file(&file_path, "one\ntwo\n")?;
@@ -313,7 +2169,7 @@ mod tests {
         Ok(repo)
     }
 
-    fn write_file(path: &Path, content: &str) -> Result<()> {
+    pub fn write_file(path: &Path, content: &str) -> Result<()> {
         if let Some(parent) = path.parent() {
             fs::create_dir_all(parent)?;
         }
@@ -0,0 +1,178 @@
+use crate::config::{self, Config};
+use crate::error::{Result, YetiError};
+use crate::git::GitRepo;
+use serde::{Deserialize, Serialize};
+use std::fs::OpenOptions;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+
+const GLOBAL_HISTORY_FILE: &str = "history.jsonl";
+const REPO_HISTORY_FILE: &str = "yeti-history.jsonl";
+
+/// Where commits made by yeti are logged, if anywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum HistoryScope {
+    /// Nothing is logged until the user opts in.
+    #[default]
+    Off,
+    /// One log in the config directory shared by every repository.
+    Global,
+    /// A log inside the repository's `.git` directory.
+    Repo,
+}
+
+impl HistoryScope {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "off" => Some(Self::Off),
+            "global" => Some(Self::Global),
+            "repo" => Some(Self::Repo),
+            _ => None,
+        }
+    }
+}
+
+/// One line of the history log.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Commit time in seconds since the epoch.
+    pub timestamp: i64,
+    pub repo: Option<String>,
+    pub branch: String,
+    pub message: String,
+}
+
+/// File the entry goes to: `config_dir/history.jsonl` globally, or
+/// `.git/yeti-history.jsonl` so a repo's log stays with its checkout; `None`
+/// while logging is off.
+pub fn history_path(scope: HistoryScope, config_dir: &Path, git_dir: &Path) -> Option<PathBuf> {
+    match scope {
+        HistoryScope::Off => None,
+        HistoryScope::Global => Some(config_dir.join(GLOBAL_HISTORY_FILE)),
+        HistoryScope::Repo => Some(git_dir.join(REPO_HISTORY_FILE)),
+    }
+}
+
+/// Every entry in the log at `path`, oldest first; lines that don't parse
+/// are skipped.
+pub fn read(path: &Path) -> Result<Vec<HistoryEntry>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    Ok(std::fs::read_to_string(path)?
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect())
+}
+
+/// Runs `yeti --show-history`: the log `history_scope` points at.
+pub fn print_history(config: &Config) -> Result<()> {
+    let scope = config.history_scope();
+    let git_dir = GitRepo::discover().map(|repo| repo.git_dir().to_path_buf());
+    let path = match (scope, git_dir) {
+        (HistoryScope::Off, _) => {
+            println!("history is off; set history_scope = \"repo\" or \"global\" to log commits");
+            return Ok(());
+        }
+        (HistoryScope::Repo, Err(e)) => return Err(e),
+        (_, git_dir) => {
+            let git_dir = git_dir.unwrap_or_default();
+            history_path(scope, &config::config_dir(), &git_dir).unwrap_or_default()
+        }
+    };
+    let entries = read(&path)?;
+    if entries.is_empty() {
+        println!("no commits logged in {}", path.display());
+    }
+    let offset = crate::git::local_time_now().offset_minutes();
+    for entry in entries {
+        let time = git2::Time::new(entry.timestamp, offset);
+        println!(
+            "{}  {}/{}  {}",
+            crate::tui::format_timestamp(time, config.twelve_hour_clock()),
+            entry.repo.as_deref().unwrap_or("-"),
+            entry.branch,
+            entry.message.lines().next().unwrap_or_default()
+        );
+    }
+    Ok(())
+}
+
+/// Runs `yeti --clear-history`: deletes the global log and this
+/// repository's, whatever `history_scope` is set to now.
+pub fn clear_history() -> Result<()> {
+    let mut paths = vec![config::config_dir().join(GLOBAL_HISTORY_FILE)];
+    if let Ok(repo) = GitRepo::discover() {
+        paths.push(repo.git_dir().join(REPO_HISTORY_FILE));
+    }
+    for path in paths {
+        if path.exists() {
+            std::fs::remove_file(&path)?;
+            println!("removed {}", path.display());
+        }
+    }
+    Ok(())
+}
+
+/// Appends `entry` as a single JSON line, creating the file if needed.
+pub fn append(path: &Path, entry: &HistoryEntry) -> Result<()> {
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let line = serde_json::to_string(entry)
+        .map_err(|e| YetiError::IoError(format!("Failed to encode history entry: {}", e)))?;
+    let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+    writeln!(file, "{}", line)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{HistoryEntry, HistoryScope, append, history_path, read};
+    use crate::error::Result;
+    use crate::git::test_support::create_temp_repo_dir;
+    use git2::Repository;
+
+    fn entry(message: &str) -> HistoryEntry {
+        HistoryEntry {
+            timestamp: 1_700_000_000,
+            repo: Some("yeti".to_string()),
+            branch: "main".to_string(),
+            message: message.to_string(),
+        }
+    }
+
+    #[test]
+    fn repo_scoped_entries_land_in_the_git_dir() -> Result<()> {
+        let dir = create_temp_repo_dir("history-repo-scope");
+        let repo = Repository::init(&dir)?;
+        let config_dir = dir.join("config");
+        let git_dir = repo.path();
+
+        assert_eq!(history_path(HistoryScope::Off, &config_dir, git_dir), None);
+        assert!(read(&git_dir.join("yeti-history.jsonl"))?.is_empty());
+        let path = history_path(HistoryScope::Repo, &config_dir, git_dir).unwrap();
+        assert_eq!(path, git_dir.join("yeti-history.jsonl"));
+        append(&path, &entry("feat: first"))?;
+        append(&path, &entry("fix: second"))?;
+
+        let logged = read(&path)?;
+        assert_eq!(logged, vec![entry("feat: first"), entry("fix: second")]);
+        assert!(!config_dir.join("history.jsonl").exists());
+        assert_eq!(
+            history_path(HistoryScope::Global, &config_dir, git_dir),
+            Some(config_dir.join("history.jsonl"))
+        );
+        std::fs::remove_dir_all(&dir)?;
+        Ok(())
+    }
+
+    #[test]
+    fn scope_parses_case_insensitively() {
+        assert_eq!(HistoryScope::parse("Repo"), Some(HistoryScope::Repo));
+        assert_eq!(HistoryScope::parse("global"), Some(HistoryScope::Global));
+        assert_eq!(HistoryScope::parse("OFF"), Some(HistoryScope::Off));
+        assert_eq!(HistoryScope::default(), HistoryScope::Off);
+        assert_eq!(HistoryScope::parse("project"), None);
+    }
+}
@@ -1,14 +1,24 @@
 mod args;
 mod cerebras;
+mod changelog;
+mod check;
+mod color;
 mod config;
+mod editor;
 mod error;
 mod git;
+mod history;
+mod ollama;
 mod prompt;
+mod provider;
 mod tui;
+mod yetiignore;
 
-use args::{Args, print_help};
+use args::{Args, Command, print_help, wants_plain_help};
 use clap::Parser;
 use error::Result;
+use std::io::IsTerminal;
+use std::path::PathBuf;
 use tui::{App, Tui};
 
 fn main() {
@@ -20,19 +30,111 @@ fn main() {
 
 fn run() -> Result<()> {
     let args: Args = Args::parse();
+    if args.no_color {
+        color::disable();
+    }
 
-    if args.help {
-        print_help();
+    if args.help || args.plain_help {
+        print_help(wants_plain_help(&args));
         std::process::exit(0);
     }
 
+    args.validate()?;
+
+    if args.edit_config {
+        return config::edit();
+    }
+    if args.print_config {
+        return config::print_config(args.profile.as_deref());
+    }
+    if args.show_history {
+        let (config, _) = config::load(args.profile.as_deref())?;
+        return history::print_history(&config);
+    }
+    if args.clear_history {
+        return history::clear_history();
+    }
+    if args.list_staged {
+        if !check::list_staged()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    if let Some(Command::Check) = args.command {
+        if !check::run()? {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    git::ensure_git_cli()?;
+
+    let changelog = args.changelog;
+    let summary_line = args.summary_line;
+    let message_out = args.message_out();
+    if !std::io::stdout().is_terminal() {
+        return run_headless(args, changelog, summary_line, message_out);
+    }
     let mut tui: Tui = Tui::new()?;
     let mut app: App = App::new(args)?;
     app.run(&mut tui)?;
 
     if let Some(result) = app.get_result() {
-        Tui::leave_and_print_history(result);
+        if let Some(path) = &message_out {
+            args::write_message_out(path, &result.message)?;
+        }
+        if summary_line {
+            Tui::leave_and_print_summary_line(result);
+        } else {
+            Tui::leave_and_print_history(result);
+        }
+        if changelog {
+            print!("\n{}", changelog::entry(&result.message, &result.files));
+        }
+    } else if let Some(notice) = app.exit_notice() {
+        drop(tui);
+        println!("{}", notice);
+    }
+    for reply in app.provider_replies() {
+        eprintln!("yeti: {}", reply);
+    }
+
+    Ok(())
+}
+
+/// Piped or in CI: no alternate screen, just the message on stdout.
+fn run_headless(
+    args: Args,
+    changelog: bool,
+    summary_line: bool,
+    message_out: Option<PathBuf>,
+) -> Result<()> {
+    let mut app = App::new(args)?;
+    let outcome = app.run_headless();
+    for reply in app.provider_replies() {
+        eprintln!("yeti: {}", reply);
     }
+    outcome?;
 
+    if let Some(result) = app.get_result() {
+        if let Some(path) = &message_out {
+            args::write_message_out(path, &result.message)?;
+        }
+        let shown = if summary_line {
+            tui::summary_line(result)
+        } else {
+            result.message.clone()
+        };
+        // `yeti --changelog >> CHANGELOG.md` must get the entry alone.
+        if changelog {
+            eprintln!("{}", shown);
+            print!("{}", changelog::entry(&result.message, &result.files));
+        } else {
+            println!("{}", shown);
+        }
+    } else if let Some(notice) = app.exit_notice() {
+        println!("{}", notice);
+    }
     Ok(())
 }
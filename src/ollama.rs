@@ -0,0 +1,229 @@
+use crate::cerebras::Completion;
+use crate::error::{Result, YetiError};
+use crate::provider::Generation;
+use serde::{Deserialize, Serialize};
+use std::io::{BufRead, BufReader};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+pub const DEFAULT_URL: &str = "http://localhost:11434";
+
+#[derive(Debug, Serialize)]
+struct ChatRequest<'a> {
+    model: &'a str,
+    messages: Vec<Message<'a>>,
+    stream: bool,
+    options: Options,
+}
+
+#[derive(Debug, Serialize)]
+struct Message<'a> {
+    role: &'a str,
+    content: &'a str,
+}
+
+#[derive(Debug, Serialize)]
+struct Options {
+    temperature: f32,
+    num_predict: u32,
+}
+
+/// One NDJSON line of a streamed `/api/chat` reply.
+#[derive(Debug, Deserialize)]
+struct ChatChunk {
+    message: Option<ChunkMessage>,
+    #[serde(default)]
+    done: bool,
+    done_reason: Option<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ChunkMessage {
+    content: String,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagsResponse {
+    models: Vec<TagModel>,
+}
+
+#[derive(Debug, Deserialize)]
+struct TagModel {
+    name: String,
+}
+
+fn unreachable(base_url: &str, e: impl std::fmt::Display) -> YetiError {
+    YetiError::NetworkError(format!(
+        "Could not reach Ollama at {} ({}); is `ollama serve` running?",
+        base_url, e
+    ))
+}
+
+/// Models pulled into the local Ollama, as `name:tag`.
+pub fn list_models(agent: &ureq::Agent, base_url: &str) -> Result<Vec<String>> {
+    let mut response = agent
+        .get(format!("{}/api/tags", base_url))
+        .call()
+        .map_err(|e| unreachable(base_url, e))?;
+    if !response.status().is_success() {
+        return Err(YetiError::ApiError {
+            status: response.status().as_u16(),
+            message: "Ollama could not list models".to_string(),
+        });
+    }
+    let tags: TagsResponse = response
+        .body_mut()
+        .read_json()
+        .map_err(|e| YetiError::NetworkError(e.to_string()))?;
+    Ok(tags.models.into_iter().map(|m| m.name).collect())
+}
+
+/// `llama3.2` matches `llama3.2:latest`, the way `ollama run` resolves it.
+pub fn has_model(models: &[String], model: &str) -> bool {
+    models
+        .iter()
+        .any(|name| name == model || name.strip_suffix(":latest") == Some(model))
+}
+
+pub fn generate_commit_message(
+    agent: &ureq::Agent,
+    base_url: &str,
+    generation: &Generation<'_>,
+    cancel: &AtomicBool,
+    on_chunk: impl Fn(&str),
+) -> Result<Completion> {
+    let request = ChatRequest {
+        model: generation.model,
+        messages: vec![
+            Message {
+                role: "system",
+                content: generation.system_prompt,
+            },
+            Message {
+                role: "user",
+                content: generation.user_prompt,
+            },
+        ],
+        stream: true,
+        options: Options {
+            temperature: generation.temperature,
+            num_predict: generation.max_completion_tokens,
+        },
+    };
+    let body = serde_json::to_string(&request)?;
+
+    let response = agent
+        .post(format!("{}/api/chat", base_url))
+        .header("Content-Type", "application/json")
+        .send(body)
+        .map_err(|e| unreachable(base_url, e))?;
+
+    let status = response.status();
+    if !status.is_success() {
+        let body_text = response.into_body().read_to_string().unwrap_or_default();
+        let message = serde_json::from_str::<ChatChunk>(&body_text)
+            .ok()
+            .and_then(|chunk| chunk.error)
+            .unwrap_or(body_text);
+        return Err(YetiError::ApiError {
+            status: status.as_u16(),
+            message,
+        });
+    }
+
+    read_stream(
+        BufReader::new(response.into_body().into_reader()),
+        cancel,
+        on_chunk,
+    )
+}
+
+fn read_stream(
+    reader: impl BufRead,
+    cancel: &AtomicBool,
+    on_chunk: impl Fn(&str),
+) -> Result<Completion> {
+    let mut content = String::new();
+    let mut truncated = false;
+
+    for line in reader.lines() {
+        if cancel.load(Ordering::Relaxed) {
+            break;
+        }
+        let line = line.map_err(|e| YetiError::NetworkError(e.to_string()))?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let Ok(chunk) = serde_json::from_str::<ChatChunk>(&line) else {
+            continue;
+        };
+        if let Some(error) = chunk.error {
+            return Err(YetiError::ApiError {
+                status: 500,
+                message: error,
+            });
+        }
+        if let Some(message) = chunk.message
+            && !message.content.is_empty()
+        {
+            on_chunk(&message.content);
+            content.push_str(&message.content);
+        }
+        if chunk.done {
+            truncated = chunk.done_reason.as_deref() == Some("length");
+            break;
+        }
+    }
+
+    if content.trim().is_empty() && !cancel.load(Ordering::Relaxed) {
+        return Err(YetiError::ApiError {
+            status: 500,
+            message: "Ollama returned an empty response".to_string(),
+        });
+    }
+    Ok(Completion { content, truncated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{has_model, read_stream};
+    use std::cell::RefCell;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn ndjson_stream_is_read_chunk_by_chunk() {
+        let stream = concat!(
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"feat[CLI]: \"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"add ollama\"},\"done\":false}\n",
+            "{\"message\":{\"role\":\"assistant\",\"content\":\"\"},\"done\":true,\"done_reason\":\"length\"}\n",
+        );
+        let seen = RefCell::new(Vec::new());
+
+        let completion = read_stream(stream.as_bytes(), &AtomicBool::new(false), |chunk| {
+            seen.borrow_mut().push(chunk.to_string())
+        })
+        .unwrap();
+
+        assert_eq!(completion.content, "feat[CLI]: add ollama");
+        assert!(completion.truncated);
+        assert_eq!(seen.into_inner(), ["feat[CLI]: ", "add ollama"]);
+
+        let failed = read_stream(
+            "{\"error\":\"model 'nope' not found\"}\n".as_bytes(),
+            &AtomicBool::new(false),
+            |_| {},
+        );
+        assert!(failed.is_err());
+    }
+
+    #[test]
+    fn bare_model_names_match_their_latest_tag() {
+        let pulled = vec![
+            "llama3.2:latest".to_string(),
+            "qwen2.5-coder:7b".to_string(),
+        ];
+        assert!(has_model(&pulled, "llama3.2"));
+        assert!(has_model(&pulled, "qwen2.5-coder:7b"));
+        assert!(!has_model(&pulled, "qwen2.5-coder"));
+    }
+}
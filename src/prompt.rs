@@ -11,6 +11,7 @@ Rules:
 - SCOPE: UPPERCASE module name from file paths (e.g., AUTH, API, DB, TUI, CORE)
 - summary: imperative mood, max 50 chars, describe what changed (no period)
 - body: single paragraph, explain WHAT and WHY, reference affected components
+- never cite line numbers or diff hunk headers (@@); describe changes by what they do
 
 Examples:
 
@@ -22,7 +23,583 @@ fix[API]: resolve null pointer in user handler
 
 Add null check before accessing user preferences in profile endpoint. Prevents crash when user record exists but preferences not initialized."#;
 
-pub fn build_user_prompt(branch: &str, files: &[FileInfo]) -> String {
+/// `SYSTEM_PROMPT` for teams on gitmoji: the same rules, with the emoji
+/// for the type leading the title.
+pub const GITMOJI_SYSTEM_PROMPT: &str = r#"Output ONLY a commit message. No markdown. No code blocks. No explanations. No preamble.
+
+Generate a gitmoji conventional commit message with this exact format:
+
+<gitmoji> <type>[SCOPE]: <summary>
+
+<body paragraph>
+
+Rules:
+- gitmoji: the shortcode matching the type: :sparkles: feat, :bug: fix, :recycle: refactor, :memo: docs, :white_check_mark: test, :wrench: chore, :zap: perf, :construction_worker: ci, :package: build, :art: style, :rewind: revert
+- type: feat, fix, refactor, docs, test, chore, perf, ci, build, style, or revert
+- SCOPE: UPPERCASE module name from file paths (e.g., AUTH, API, DB, TUI, CORE)
+- summary: imperative mood, max 50 chars, describe what changed (no period)
+- body: single paragraph, explain WHAT and WHY, reference affected components
+- never cite line numbers or diff hunk headers (@@); describe changes by what they do
+
+Examples:
+
+:sparkles: feat[AUTH]: add OAuth2 login flow
+
+Implement Google OAuth2 provider with JWT token generation and session management. Update auth middleware to validate tokens and handle refresh flows.
+
+:bug: fix[API]: resolve null pointer in user handler
+
+Add null check before accessing user preferences in profile endpoint. Prevents crash when user record exists but preferences not initialized."#;
+
+pub fn system_prompt(gitmoji: bool) -> &'static str {
+    if gitmoji {
+        GITMOJI_SYSTEM_PROMPT
+    } else {
+        SYSTEM_PROMPT
+    }
+}
+
+/// Splits a leading gitmoji (`:sparkles:` or a unicode emoji such as `✨`)
+/// and its space off a title; titles without one come back whole.
+pub fn split_gitmoji(title: &str) -> (&str, &str) {
+    let Some((first, _)) = title.split_once(' ') else {
+        return ("", title);
+    };
+    let shortcode = first.len() > 2
+        && first.starts_with(':')
+        && first.ends_with(':')
+        && first[1..first.len() - 1]
+            .chars()
+            .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || matches!(c, '_' | '-' | '+'));
+    let emoji = !first.is_empty() && first.chars().all(|c| !c.is_ascii() && !c.is_alphanumeric());
+    if shortcode || emoji {
+        title.split_at(first.len() + 1)
+    } else {
+        ("", title)
+    }
+}
+
+/// Appended to the user prompt in strict mode, where commitlint expects the
+/// standard header rather than yeti's `type[SCOPE]` form.
+pub const STRICT_FORMAT_RULE: &str = "Strict mode: the title must be a standard Conventional Commits header, `type(scope): summary` or `type: summary`, with a lowercase type and a lowercase scope in parentheses (letters, digits, hyphens). Do not use square brackets or an uppercase scope.";
+
+/// Appended to the user prompt when the first attempt came back generic.
+pub const SPECIFICITY_NUDGE: &str = "The previous attempt was too generic. Name the specific behavior, function, or component that changed, and explain why in the body. Avoid phrases like \"update files\" or \"various changes\".";
+
+/// Frames the one-line hint typed before a regenerate.
+pub fn steering_hint(hint: &str) -> String {
+    format!(
+        "The author reviewed a previous attempt and asks for this: {}",
+        hint.trim()
+    )
+}
+
+// Summaries that say nothing about what actually changed.
+const VAGUE_PHRASES: [&str; 12] = [
+    "update files",
+    "updated files",
+    "update code",
+    "code changes",
+    "various changes",
+    "various fixes",
+    "misc changes",
+    "miscellaneous",
+    "minor changes",
+    "some changes",
+    "small fixes",
+    "wip",
+];
+
+/// Low-confidence output: a stock phrase, or a one- or two-word title with no body.
+pub fn looks_vague(title: &str, body: Option<&str>) -> bool {
+    let (_, title) = split_gitmoji(title);
+    let summary = title
+        .split_once(": ")
+        .map_or(title, |(_, summary)| summary)
+        .trim()
+        .trim_end_matches(['.', '!'])
+        .to_lowercase();
+    if VAGUE_PHRASES.contains(&summary.as_str()) {
+        return true;
+    }
+    body.is_none() && summary.split_whitespace().count() <= 2
+}
+
+pub const COMMIT_TYPES: [&str; 11] = [
+    "feat", "fix", "refactor", "docs", "test", "chore", "perf", "ci", "build", "style", "revert",
+];
+
+/// `^(feat|fix|...)(\([a-z0-9-]+\))?!?: .+$`, exactly, as commitlint checks it.
+pub fn is_strict_conventional_title(title: &str) -> bool {
+    let Some((prefix, summary)) = title.split_once(": ") else {
+        return false;
+    };
+    if summary.is_empty() {
+        return false;
+    }
+
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let kind = match prefix.split_once('(') {
+        Some((kind, scope)) => {
+            let Some(scope) = scope.strip_suffix(')') else {
+                return false;
+            };
+            let scope_ok = !scope.is_empty()
+                && scope
+                    .chars()
+                    .all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-');
+            if !scope_ok {
+                return false;
+            }
+            kind
+        }
+        None => prefix,
+    };
+    COMMIT_TYPES.contains(&kind)
+}
+
+/// `type[SCOPE]: summary` or `type(scope): summary`, after any gitmoji.
+pub fn is_conventional_title(title: &str) -> bool {
+    let (_, title) = split_gitmoji(title);
+    let Some((prefix, summary)) = title.split_once(": ") else {
+        return false;
+    };
+    if summary.trim().is_empty() {
+        return false;
+    }
+
+    let prefix = prefix.strip_suffix('!').unwrap_or(prefix);
+    let (kind, scope) = match prefix.find(['[', '(']) {
+        Some(idx) => (&prefix[..idx], Some(&prefix[idx..])),
+        None => (prefix, None),
+    };
+
+    let scope_ok = match scope {
+        None => true,
+        Some(s) => {
+            let inner = s
+                .strip_prefix('[')
+                .and_then(|s| s.strip_suffix(']'))
+                .or_else(|| s.strip_prefix('(').and_then(|s| s.strip_suffix(')')));
+            inner.is_some_and(|i| !i.is_empty() && !i.contains(char::is_whitespace))
+        }
+    };
+
+    scope_ok && COMMIT_TYPES.contains(&kind)
+}
+
+// Leading directories that hold code rather than name a module.
+const CONTAINER_DIRS: [&str; 6] = ["src", "lib", "app", "crates", "packages", "source"];
+const BUILD_FILES: [&str; 7] = [
+    "Cargo.toml",
+    "Cargo.lock",
+    "package.json",
+    "package-lock.json",
+    "justfile",
+    "Makefile",
+    "Dockerfile",
+];
+// Words in added lines that usually mean the change is about speed.
+const PERF_KEYWORDS: [&str; 6] = [
+    "optimiz",
+    "cache",
+    "allocation",
+    "hot path",
+    "fast path",
+    "with_capacity",
+];
+
+fn file_scope(path: &str) -> String {
+    let parts: Vec<&str> = path.split('/').collect();
+    let (dirs, name) = parts.split_at(parts.len() - 1);
+    let in_container = dirs.first().is_some_and(|d| CONTAINER_DIRS.contains(d));
+
+    match dirs.iter().find(|d| !CONTAINER_DIRS.contains(d)) {
+        Some(dir) => dir.trim_start_matches('.').to_ascii_uppercase(),
+        None if in_container => "CORE".to_string(),
+        None => name[0]
+            .split('.')
+            .find(|s| !s.is_empty())
+            .unwrap_or(name[0])
+            .to_ascii_uppercase(),
+    }
+}
+
+/// Shared module scope for the change, or `None` when files span several modules.
+pub fn scope_hint(files: &[FileInfo]) -> Option<String> {
+    let mut scopes = files.iter().map(|f| file_scope(&f.path));
+    let first = scopes.next()?;
+    scopes.all(|s| s == first).then_some(first)
+}
+
+/// Scope derived from the repository directory, e.g. `my-app` -> `MY-APP`.
+pub fn repo_scope(repo_name: &str) -> Option<String> {
+    let scope: String = repo_name
+        .trim()
+        .chars()
+        .map(|c| {
+            if c.is_whitespace() || c == '_' {
+                '-'
+            } else {
+                c
+            }
+        })
+        .collect::<String>()
+        .trim_start_matches('.')
+        .to_ascii_uppercase();
+    (!scope.is_empty()).then_some(scope)
+}
+
+/// A commit before the staged change, shown with `--context-commits`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RecentCommit {
+    pub subject: String,
+    /// Patch text against the first parent, already capped by `git.rs`.
+    pub diff: String,
+}
+
+/// A Cargo workspace member: its directory relative to the repo root and its package name.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WorkspaceCrate {
+    pub dir: String,
+    pub name: String,
+}
+
+/// The crate name as a scope when every file sits inside the same workspace member.
+pub fn workspace_scope(files: &[FileInfo], crates: &[WorkspaceCrate]) -> Option<String> {
+    let owner = |path: &str| {
+        crates
+            .iter()
+            .filter(|krate| {
+                path.strip_prefix(krate.dir.as_str())
+                    .is_some_and(|rest| rest.starts_with('/'))
+            })
+            .max_by_key(|krate| krate.dir.len())
+    };
+    let mut owners = files.iter().map(|f| owner(&f.path));
+    let first = owners.next()??;
+    if owners.all(|o| o == Some(first)) {
+        repo_scope(&first.name)
+    } else {
+        None
+    }
+}
+
+/// The workspace crate, else the module the files agree on, else the repository's name.
+pub fn resolve_scope_hint(
+    files: &[FileInfo],
+    repo_name: Option<&str>,
+    crates: &[WorkspaceCrate],
+) -> Option<String> {
+    workspace_scope(files, crates)
+        .or_else(|| scope_hint(files))
+        .or_else(|| repo_name.and_then(repo_scope))
+}
+
+fn is_docs_path(path: &str) -> bool {
+    path.ends_with(".md") || path.starts_with("docs/") || path.contains("/docs/")
+}
+
+fn is_test_path(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    path.starts_with("tests/")
+        || path.contains("/tests/")
+        || name.starts_with("test_")
+        || name.contains("_test.")
+        || name.contains(".test.")
+        || name.contains(".spec.")
+}
+
+fn is_ci_path(path: &str) -> bool {
+    path.starts_with(".github/workflows/") || path == ".gitlab-ci.yml"
+}
+
+fn is_build_path(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    BUILD_FILES.contains(&name)
+}
+
+fn is_bench_path(path: &str) -> bool {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    path.starts_with("benches/") || path.contains("/benches/") || name.contains("bench")
+}
+
+/// Added lines talking about caching, allocations and the like.
+fn mentions_perf(files: &[FileInfo]) -> bool {
+    files.iter().any(|f| {
+        f.diff
+            .lines()
+            .filter(|line| line.starts_with('+'))
+            .any(|line| {
+                let line = line.to_ascii_lowercase();
+                PERF_KEYWORDS.iter().any(|word| line.contains(word))
+            })
+    })
+}
+
+/// Conventional type suggested by the shape of the change, when it is clear-cut.
+pub fn type_hint(files: &[FileInfo]) -> Option<&'static str> {
+    if files.is_empty() {
+        return None;
+    }
+    let all = |pred: fn(&str) -> bool| files.iter().all(|f| pred(&f.path));
+
+    if all(is_docs_path) {
+        return Some("docs");
+    }
+    if all(is_test_path) {
+        return Some("test");
+    }
+    if all(is_ci_path) {
+        return Some("ci");
+    }
+    if all(is_build_path) {
+        return Some("build");
+    }
+
+    let additions: usize = files.iter().map(|f| f.additions).sum();
+    let deletions: usize = files.iter().map(|f| f.deletions).sum();
+    if files.iter().any(|f| f.status == FileStatus::Added) && additions > deletions {
+        return Some("feat");
+    }
+    if files
+        .iter()
+        .all(|f| matches!(f.status, FileStatus::Renamed | FileStatus::Deleted))
+    {
+        return Some("refactor");
+    }
+    None
+}
+
+fn build_hints(files: &[FileInfo], options: &PromptOptions) -> Option<String> {
+    let mut hints = Vec::new();
+    // A pasted failure is the strongest signal there is that this is a fix.
+    // Benchmarks alone outrank the shape of the change; keywords only fill a gap.
+    let only_benches = !files.is_empty() && files.iter().all(|f| is_bench_path(&f.path));
+    let kind = match options.fixes {
+        Some(_) => Some("fix"),
+        None if options.perf_hints && only_benches => Some("perf"),
+        None => type_hint(files)
+            .or_else(|| (options.perf_hints && mentions_perf(files)).then_some("perf")),
+    };
+    if let Some(kind) = kind {
+        hints.push(format!("type {}", kind));
+    }
+    if let Some(scope) = resolve_scope_hint(
+        files,
+        options.repo_name.as_deref(),
+        &options.workspace_crates,
+    ) {
+        hints.push(format!("scope {}", scope));
+    }
+    if hints.is_empty() {
+        None
+    } else {
+        Some(format!(
+            "Hints (derived from paths, override if the diff disagrees): {}",
+            hints.join(", ")
+        ))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum PromptLayout {
+    List,
+    Tree,
+    #[default]
+    Both,
+}
+
+impl PromptLayout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "list" => Some(Self::List),
+            "tree" => Some(Self::Tree),
+            "both" => Some(Self::Both),
+            _ => None,
+        }
+    }
+
+    fn includes_list(self) -> bool {
+        matches!(self, Self::List | Self::Both)
+    }
+
+    fn includes_tree(self) -> bool {
+        matches!(self, Self::Tree | Self::Both)
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct PromptOptions {
+    pub layout: PromptLayout,
+    pub language_tags: bool,
+    /// Repository directory name, used as the scope when files span modules.
+    pub repo_name: Option<String>,
+    /// Cargo workspace members, so a change inside one crate is scoped to it.
+    pub workspace_crates: Vec<WorkspaceCrate>,
+    /// Directory names whose contents are summarized instead of listed.
+    pub generated_dirs: Vec<String>,
+    /// Failing test/lint output from `--fixes-file` that this change fixes.
+    pub fixes: Option<String>,
+    /// Newest-first commits from `--context-commits`, as background only.
+    pub recent_commits: Vec<RecentCommit>,
+    /// Append "(N hunks)" to each file in the list.
+    pub hunk_counts: bool,
+    /// Add a "Languages:" line tallying changed files by extension.
+    pub composition: bool,
+    /// Suggest `perf` for benchmark files and optimization keywords.
+    pub perf_hints: bool,
+}
+
+impl Default for PromptOptions {
+    fn default() -> Self {
+        Self {
+            layout: PromptLayout::default(),
+            language_tags: true,
+            repo_name: None,
+            workspace_crates: Vec::new(),
+            generated_dirs: DEFAULT_GENERATED_DIRS
+                .iter()
+                .map(|d| d.to_string())
+                .collect(),
+            fixes: None,
+            recent_commits: Vec::new(),
+            hunk_counts: true,
+            composition: true,
+            perf_hints: true,
+        }
+    }
+}
+
+// Room for `--fixes-file` output; the tail is kept, where runners print the failures.
+const FIXES_BUDGET_CHARS: usize = 4_000;
+
+fn build_fixes_section(output: &str) -> Option<String> {
+    let output = output.trim();
+    if output.is_empty() {
+        return None;
+    }
+    let chars = output.chars().count();
+    let shown = if chars > FIXES_BUDGET_CHARS {
+        let tail: String = output.chars().skip(chars - FIXES_BUDGET_CHARS).collect();
+        format!("...\n{}", tail)
+    } else {
+        output.to_string()
+    };
+    Some(format!(
+        "This change fixes the following failure:\n```\n{}\n```",
+        shown
+    ))
+}
+
+// Room for `--context-commits` diffs; kept small so they never crowd out the staged patch.
+const RECENT_COMMITS_BUDGET_CHARS: usize = 3_000;
+
+/// Recent commits framed as background, newest first, cut off at the budget.
+fn build_recent_commits_section(commits: &[RecentCommit]) -> Option<String> {
+    if commits.is_empty() {
+        return None;
+    }
+    let mut out = String::from(
+        "Recent commits on this branch, for background and style only. Do not describe them; the message is for the staged change below.",
+    );
+    for commit in commits {
+        let entry = format!("\n\ncommit: {}\n{}", commit.subject, commit.diff.trim_end());
+        let remaining = RECENT_COMMITS_BUDGET_CHARS.saturating_sub(out.len());
+        if entry.len() > remaining {
+            let mut entry = entry;
+            truncate_safely(&mut entry, remaining);
+            if !entry.is_empty() {
+                out.push_str(&entry);
+                out.push_str("\n...[truncated]");
+            }
+            break;
+        }
+        out.push_str(&entry);
+    }
+    Some(out)
+}
+
+/// Vendored or build-output directories whose changes are mostly noise.
+pub const DEFAULT_GENERATED_DIRS: [&str; 4] = ["vendor", "node_modules", "target", "dist"];
+
+/// The path up to and including the first generated directory, e.g. `web/node_modules/`.
+fn generated_root(path: &str, generated_dirs: &[String]) -> Option<String> {
+    let (dirs, _file) = path.rsplit_once('/')?;
+    let mut root = String::new();
+    for component in dirs.split('/') {
+        root.push_str(component);
+        root.push('/');
+        if generated_dirs
+            .iter()
+            .any(|d| d.trim_matches('/') == component)
+        {
+            return Some(root);
+        }
+    }
+    None
+}
+
+/// Changed files tallied by extension, most common first: `3 .rs, 1 .toml, 1 no ext`.
+pub fn composition(files: &[FileInfo]) -> String {
+    let mut counts: std::collections::BTreeMap<String, usize> = std::collections::BTreeMap::new();
+    for file in files {
+        let ext = std::path::Path::new(&file.path)
+            .extension()
+            .map(|ext| format!(".{}", ext.to_string_lossy()))
+            .unwrap_or_else(|| "no ext".to_string());
+        *counts.entry(ext).or_default() += 1;
+    }
+    let mut counts: Vec<(String, usize)> = counts.into_iter().collect();
+    // Stable sort keeps ties in extension order.
+    counts.sort_by_key(|(_, count)| std::cmp::Reverse(*count));
+    counts
+        .iter()
+        .map(|(ext, count)| format!("{} {}", count, ext))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
+
+/// One line per generated directory, so vendored churn doesn't crowd out real changes.
+fn build_generated_summary(files: &[&FileInfo], generated_dirs: &[String]) -> Option<String> {
+    let mut roots: std::collections::BTreeMap<String, (usize, usize, usize)> =
+        std::collections::BTreeMap::new();
+    for file in files {
+        if let Some(root) = generated_root(&file.path, generated_dirs) {
+            let entry = roots.entry(root).or_default();
+            entry.0 += 1;
+            entry.1 += file.additions;
+            entry.2 += file.deletions;
+        }
+    }
+    if roots.is_empty() {
+        return None;
+    }
+
+    let lines = roots
+        .iter()
+        .map(|(root, (count, add, del))| {
+            format!(
+                "- updated {} generated/vendored files under {} (+{}/-{})",
+                count, root, add, del
+            )
+        })
+        .collect::<Vec<_>>()
+        .join("\n");
+    Some(format!(
+        "Generated/vendored changes (summarized):\n{}",
+        lines
+    ))
+}
+
+pub fn build_user_prompt(branch: &str, files: &[FileInfo], options: &PromptOptions) -> String {
+    let (generated, files): (Vec<&FileInfo>, Vec<&FileInfo>) = files
+        .iter()
+        .partition(|f| generated_root(&f.path, &options.generated_dirs).is_some());
+    let generated_summary = build_generated_summary(&generated, &options.generated_dirs);
+    let files: Vec<FileInfo> = files.into_iter().cloned().collect();
+    let files = files.as_slice();
+
     let file_list = files
         .iter()
         .take(30)
@@ -37,15 +614,35 @@ pub fn build_user_prompt(branch: &str, files: &[FileInfo]) -> String {
                 Some(old) => format!(" (from {})", old),
                 None => String::new(),
             };
+            let hunks = match f.hunks {
+                0 => String::new(),
+                _ if !options.hunk_counts => String::new(),
+                1 => " (1 hunk)".to_string(),
+                n => format!(" ({} hunks)", n),
+            };
             format!(
-                "- {}{} ({}: +{}/-{})",
-                f.path, rename_suffix, change_type, f.additions, f.deletions
+                "- {}{} ({}: +{}/-{}){}",
+                f.annotated_path(),
+                rename_suffix,
+                change_type,
+                f.additions,
+                f.deletions,
+                hunks
             )
         })
         .collect::<Vec<_>>()
         .join("\n");
 
-    let change_tree = build_change_tree(files);
+    // A tree of one path says nothing the file list doesn't; spend the room on the diff.
+    let single_file = files.len() == 1;
+    let include_list = options.layout.includes_list() || single_file;
+    let include_tree = options.layout.includes_tree() && !single_file;
+
+    let change_tree = if include_tree {
+        build_change_tree(files)
+    } else {
+        String::new()
+    };
 
     let extra = if files.len() > 30 {
         format!("\n... and {} more files", files.len() - 30)
@@ -53,16 +650,42 @@ pub fn build_user_prompt(branch: &str, files: &[FileInfo]) -> String {
         String::new()
     };
 
-    let diff_hint = build_patch_context(files);
+    let diff_hint = build_patch_context(files, options);
 
-    format!(
-        "Branch: {}\n\nFiles changed ({}):\n{}{}\n\nChange tree:\n{}\n\nUse this staged diff context (including renames/moves) to generate the exact commit message.\n\nGenerate a commit message.",
-        branch,
-        files.len(),
-        file_list,
-        extra,
-        change_tree
-    ) + &diff_hint
+    let mut sections = vec![format!("Branch: {}", branch)];
+    if options.composition && !files.is_empty() {
+        sections.push(format!("Languages: {}", composition(files)));
+    }
+    if include_list {
+        sections.push(format!(
+            "Files changed ({}):\n{}{}",
+            files.len(),
+            file_list,
+            extra
+        ));
+    }
+    if include_tree {
+        sections.push(format!("Change tree:\n{}", change_tree));
+    }
+    if let Some(summary) = generated_summary {
+        sections.push(summary);
+    }
+    if let Some(fixes) = options.fixes.as_deref().and_then(build_fixes_section) {
+        sections.push(fixes);
+    }
+    if let Some(hints) = build_hints(files, options) {
+        sections.push(hints);
+    }
+    if let Some(recent) = build_recent_commits_section(&options.recent_commits) {
+        sections.push(recent);
+    }
+    sections.push(
+        "Use this staged diff context (including renames/moves) to generate the exact commit message."
+            .to_string(),
+    );
+    sections.push("Generate a commit message.".to_string());
+
+    sections.join("\n\n") + &diff_hint
 }
 
 fn build_change_tree(files: &[FileInfo]) -> String {
@@ -89,12 +712,7 @@ fn build_change_tree(files: &[FileInfo]) -> String {
         }
 
         let depth = parts.len().saturating_sub(1);
-        let status = match file.status {
-            FileStatus::Added => "A",
-            FileStatus::Deleted => "D",
-            FileStatus::Renamed => "R",
-            FileStatus::Modified => "M",
-        };
+        let status = file.status.letter();
         let rename_note = file
             .old_path
             .as_ref()
@@ -127,22 +745,78 @@ fn truncate_safely(s: &mut String, mut len: usize) {
     s.truncate(len);
 }
 
-fn build_patch_context(files: &[FileInfo]) -> String {
+fn language_for_path(path: &str) -> Option<&'static str> {
+    let name = path.rsplit('/').next().unwrap_or(path);
+    match name {
+        "Dockerfile" => return Some("dockerfile"),
+        "Makefile" | "makefile" => return Some("make"),
+        "justfile" => return Some("just"),
+        _ => {}
+    }
+
+    let ext = name.rsplit_once('.')?.1.to_ascii_lowercase();
+    let language = match ext.as_str() {
+        "rs" => "rust",
+        "py" => "python",
+        "js" | "mjs" | "cjs" => "javascript",
+        "ts" => "typescript",
+        "tsx" | "jsx" => "react",
+        "go" => "go",
+        "java" => "java",
+        "kt" | "kts" => "kotlin",
+        "swift" => "swift",
+        "c" | "h" => "c",
+        "cc" | "cpp" | "cxx" | "hpp" => "cpp",
+        "cs" => "csharp",
+        "rb" => "ruby",
+        "php" => "php",
+        "sh" | "bash" | "zsh" => "shell",
+        "sql" => "sql",
+        "html" | "htm" => "html",
+        "css" | "scss" => "css",
+        "md" => "markdown",
+        "toml" => "toml",
+        "yml" | "yaml" => "yaml",
+        "json" => "json",
+        "lua" => "lua",
+        "zig" => "zig",
+        _ => return None,
+    };
+    Some(language)
+}
+
+/// Patch text a prompt carries in all, which a single changed file may fill;
+/// staged diffs are read up to this much per file.
+pub const PATCH_BUDGET: usize = 40_000;
+/// Each file's share of the budget when several changed.
+const PATCH_BUDGET_PER_FILE: usize = 6_000;
+
+fn build_patch_context(files: &[FileInfo], options: &PromptOptions) -> String {
     let mut used = 0usize;
     let mut patches = Vec::new();
-    let max_total = 40_000usize;
-    let max_file = 6_000usize;
+    let max_total = PATCH_BUDGET;
+    // A lone file gets the whole budget rather than the per-file slice.
+    let max_file = if files.len() == 1 {
+        max_total
+    } else {
+        PATCH_BUDGET_PER_FILE
+    };
 
     for file in files {
         if file.diff.is_empty() {
             continue;
         }
 
-        let title = if let Some(old) = &file.old_path {
+        let mut title = if let Some(old) = &file.old_path {
             format!("--- {} (renamed from {})\n", file.path, old)
         } else {
             format!("--- {}\n", file.path)
         };
+        if options.language_tags
+            && let Some(language) = language_for_path(&file.path)
+        {
+            title.push_str(&format!("// language: {}\n", language));
+        }
         let mut body = file.diff.clone();
         if body.len() > max_file {
             truncate_safely(&mut body, max_file);
@@ -180,6 +854,99 @@ pub struct FileInfo {
     pub diff: String,
     pub status: FileStatus,
     pub old_path: Option<String>,
+    /// Staged even though `.gitignore` matches it (i.e. force-added).
+    pub ignored: bool,
+    /// Set when `.gitattributes` routes the file through LFS or marks it binary.
+    pub attr: Option<ContentAttr>,
+    /// Where the entry points when it is a symlink (mode 120000).
+    pub symlink_target: Option<String>,
+    /// The file mode changed (e.g. `chmod +x`).
+    pub mode_changed: bool,
+    /// Separate hunks in the staged diff; many means a scattered change.
+    pub hunks: usize,
+    /// Only CRLF/LF line endings changed.
+    pub eol_only: bool,
+}
+
+/// Content whose text diff is meaningless, per `.gitattributes`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentAttr {
+    Lfs,
+    Binary,
+}
+
+impl ContentAttr {
+    pub fn label(self) -> &'static str {
+        match self {
+            ContentAttr::Lfs => "LFS",
+            ContentAttr::Binary => "binary",
+        }
+    }
+}
+
+impl FileInfo {
+    /// Path with its symlink target, or an `(LFS)`/`(binary)` note when
+    /// `.gitattributes` applies.
+    pub fn annotated_path(&self) -> String {
+        if let Some(target) = &self.symlink_target {
+            return format!("symlink {} → {}", self.path, target);
+        }
+        match self.attr {
+            Some(attr) => format!("{} ({})", self.path, attr.label()),
+            None => self.path.clone(),
+        }
+    }
+
+    /// What a 0/0 line count actually means, so tables don't show a bare `- / -`.
+    pub fn zero_change_label(&self) -> Option<&'static str> {
+        if self.additions > 0 || self.deletions > 0 {
+            return None;
+        }
+        if self.symlink_target.is_some() {
+            return Some("symlink");
+        }
+        if let Some(attr) = self.attr {
+            return Some(attr.label());
+        }
+        if self.status == FileStatus::Renamed {
+            return Some("rename");
+        }
+        if self.mode_changed {
+            return Some("mode");
+        }
+        Some("no textual change")
+    }
+
+    /// Status letter for tables, with `!` flagging force-added ignored files.
+    pub fn status_tag(&self) -> String {
+        if self.ignored {
+            format!("{}!", self.status.letter())
+        } else {
+            self.status.letter().to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+impl FileInfo {
+    /// A plain change with no lines counted, for tests to fill in with
+    /// struct-update syntax.
+    pub fn new(path: &str, status: FileStatus) -> Self {
+        FileInfo {
+            path: path.to_string(),
+            additions: 0,
+            deletions: 0,
+            diff: String::new(),
+            status,
+            old_path: None,
+            ignored: false,
+            attr: None,
+            symlink_target: None,
+            mode_changed: false,
+            hunks: 0,
+            eol_only: false,
+        }
+    }
 }
 
 #[derive(Debug, Clone, Copy, PartialEq)]
@@ -190,9 +957,24 @@ pub enum FileStatus {
     Renamed,
 }
 
+impl FileStatus {
+    pub fn letter(self) -> &'static str {
+        match self {
+            FileStatus::Added => "A",
+            FileStatus::Deleted => "D",
+            FileStatus::Renamed => "R",
+            FileStatus::Modified => "M",
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use super::{FileInfo, FileStatus, build_user_prompt};
+    use super::{
+        ContentAttr, FileInfo, FileStatus, PromptLayout, PromptOptions, RecentCommit, build_hints,
+        build_user_prompt, composition, is_strict_conventional_title, looks_vague, repo_scope,
+        resolve_scope_hint, scope_hint, type_hint,
+    };
 
     fn file(
         path: &str,
@@ -203,15 +985,164 @@ mod tests {
         old_path: Option<&str>,
     ) -> FileInfo {
         FileInfo {
-            path: path.to_string(),
             additions,
             deletions,
             diff: diff.to_string(),
-            status,
             old_path: old_path.map(|s| s.to_string()),
+            ..FileInfo::new(path, status)
+        }
+    }
+
+    #[test]
+    fn recent_commits_are_background_within_their_budget() {
+        let files = vec![file(
+            "src/parser.rs",
+            FileStatus::Modified,
+            1,
+            0,
+            "+    // staged\n",
+            None,
+        )];
+        let commit = |subject: &str, lines: usize| RecentCommit {
+            subject: subject.to_string(),
+            diff: "+    let context = true;\n".repeat(lines),
+        };
+        let options = PromptOptions {
+            recent_commits: vec![
+                commit("refactor: split lexer", 2),
+                commit("feat: add parser", 500),
+            ],
+            ..PromptOptions::default()
+        };
+
+        let prompt = build_user_prompt("main", &files, &options);
+        let start = prompt.find("Recent commits on this branch").unwrap();
+        let end = prompt.find("Use this staged diff context").unwrap();
+        let section = &prompt[start..end];
+        assert!(section.contains("commit: refactor: split lexer\n+    let context = true;"));
+        assert!(section.contains("commit: feat: add parser"));
+        assert!(section.trim_end().ends_with("...[truncated]"));
+        assert!(section.len() <= super::RECENT_COMMITS_BUDGET_CHARS + 40);
+        assert!(prompt.find("+    // staged").unwrap() > end);
+
+        let without = build_user_prompt("main", &files, &PromptOptions::default());
+        assert!(!without.contains("Recent commits"));
+    }
+
+    #[test]
+    fn fixes_output_is_framed_as_the_failure_and_hints_fix() {
+        let files = vec![file(
+            "src/parser.rs",
+            FileStatus::Modified,
+            3,
+            1,
+            "+    if input.is_empty() { return None; }\n",
+            None,
+        )];
+        let failure =
+            "test parser::empty_input ... FAILED\nthread panicked at 'index out of bounds'";
+        let options = PromptOptions {
+            fixes: Some(failure.to_string()),
+            ..PromptOptions::default()
+        };
+
+        let prompt = build_user_prompt("main", &files, &options);
+        assert!(prompt.contains(&format!(
+            "This change fixes the following failure:\n```\n{}\n```",
+            failure
+        )));
+        assert!(prompt.contains("type fix"));
+
+        let without = build_user_prompt("main", &files, &PromptOptions::default());
+        assert!(!without.contains("fixes the following failure"));
+        assert!(!without.contains("type fix"));
+
+        let long = PromptOptions {
+            fixes: Some(format!("{}\nFAILED: last line", "noise ".repeat(2_000))),
+            ..PromptOptions::default()
+        };
+        let prompt = build_user_prompt("main", &files, &long);
+        assert!(prompt.contains("...\n"));
+        assert!(prompt.contains("FAILED: last line"));
+        assert!(prompt.len() < 4_000 + 2_000);
+    }
+
+    #[test]
+    fn composition_counts_extensions_including_files_without_one() {
+        let files: Vec<FileInfo> = [
+            "src/main.rs",
+            "src/lib.rs",
+            "Cargo.toml",
+            "src/app.rs",
+            "Makefile",
+            "README.md",
+            ".gitignore",
+        ]
+        .into_iter()
+        .map(|path| file(path, FileStatus::Modified, 1, 0, "", None))
+        .collect();
+
+        assert_eq!(composition(&files), "3 .rs, 2 no ext, 1 .md, 1 .toml");
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
+        assert!(prompt.contains("\nLanguages: 3 .rs, 2 no ext, 1 .md, 1 .toml\n"));
+
+        let off = PromptOptions {
+            composition: false,
+            ..PromptOptions::default()
+        };
+        assert!(!build_user_prompt("main", &files, &off).contains("Languages:"));
+    }
+
+    #[test]
+    fn strict_titles_accept_only_the_standard_header() {
+        for title in [
+            "feat: add strict mode",
+            "fix(api): handle empty body",
+            "refactor(tui-app)!: split draw code",
+            "chore(deps2): bump ureq",
+        ] {
+            assert!(is_strict_conventional_title(title), "{title}");
+        }
+
+        for title in [
+            "feat[TUI]: bracket scope",
+            "fix(API): uppercase scope",
+            "Feat: capitalised type",
+            "feature: unknown type",
+            "fix(): empty scope",
+            "fix(api) : space before colon",
+            "fix(api):no space",
+            "fix(my scope): spaces in scope",
+            "fix: ",
+        ] {
+            assert!(!is_strict_conventional_title(title), "{title}");
         }
     }
 
+    #[test]
+    fn zero_line_changes_are_labelled_by_cause() {
+        let rename = file("src/b.rs", FileStatus::Renamed, 0, 0, "", Some("src/a.rs"));
+        assert_eq!(rename.zero_change_label(), Some("rename"));
+
+        let mut mode = file("run.sh", FileStatus::Modified, 0, 0, "", None);
+        mode.mode_changed = true;
+        assert_eq!(mode.zero_change_label(), Some("mode"));
+
+        let mut binary = file("logo.png", FileStatus::Added, 0, 0, "", None);
+        binary.attr = Some(ContentAttr::Binary);
+        assert_eq!(binary.zero_change_label(), Some("binary"));
+
+        let mut link = file("current", FileStatus::Added, 0, 0, "", None);
+        link.symlink_target = Some("v2".to_string());
+        assert_eq!(link.zero_change_label(), Some("symlink"));
+
+        let empty = file("empty.txt", FileStatus::Added, 0, 0, "", None);
+        assert_eq!(empty.zero_change_label(), Some("no textual change"));
+
+        let edited = file("src/lib.rs", FileStatus::Modified, 0, 2, "", None);
+        assert_eq!(edited.zero_change_label(), None);
+    }
+
     #[test]
     fn user_prompt_includes_files_changed_section_with_status_and_rename() {
         let files = vec![
@@ -241,7 +1172,7 @@ mod tests {
             ),
         ];
 
-        let prompt = build_user_prompt("feature/refactor", &files);
+        let prompt = build_user_prompt("feature/refactor", &files, &PromptOptions::default());
 
         assert!(prompt.contains("Files changed (3):"));
         assert!(prompt.contains("- src/new.rs (added: +8/-0)"));
@@ -263,7 +1194,7 @@ mod tests {
             file("src/prompt.rs", FileStatus::Modified, 2, 0, "+c\n", None),
         ];
 
-        let prompt = build_user_prompt("main", &files);
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
 
         assert!(prompt.contains("Change tree:"));
         assert!(prompt.contains("src/"));
@@ -274,7 +1205,7 @@ mod tests {
 
     #[test]
     fn user_prompt_includes_staged_patch_excerpts_and_truncates_long_diff() {
-        let long_diff = format!("+{}\n", "x".repeat(10000));
+        let long_diff = format!("+{}\n", "x".repeat(50_000));
         let files = vec![file(
             "src/huge.rs",
             FileStatus::Modified,
@@ -284,7 +1215,7 @@ mod tests {
             None,
         )];
 
-        let prompt = build_user_prompt("main", &files);
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
 
         assert!(prompt.contains("Staged patch excerpts:"));
         assert!(prompt.contains("--- src/huge.rs"));
@@ -299,7 +1230,7 @@ mod tests {
             files.push(file(&path, FileStatus::Added, 10, 0, "+fn foo() {}", None));
         }
         
-        let prompt = build_user_prompt("main", &files);
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
         let tree_idx = prompt.find("Change tree:\n").unwrap();
         let diff_idx = prompt.find("\n\nUse this staged diff context").unwrap();
         let tree_len = diff_idx - (tree_idx + "Change tree:\n".len());
@@ -307,4 +1238,219 @@ mod tests {
         assert!(tree_len <= 10000 + "\n...[truncated]".len() + 100);
         assert!(prompt.contains("...[truncated]"));
     }
+
+    #[test]
+    fn user_prompt_layout_controls_list_and_tree_sections() {
+        let files = vec![
+            file("src/lib.rs", FileStatus::Modified, 1, 0, "+x\n", None),
+            file("src/main.rs", FileStatus::Modified, 1, 0, "+y\n", None),
+        ];
+        let prompt_for = |layout| {
+            build_user_prompt(
+                "main",
+                &files,
+                &PromptOptions {
+                    layout,
+                    ..PromptOptions::default()
+                },
+            )
+        };
+
+        let list = prompt_for(PromptLayout::List);
+        assert!(list.contains("Files changed (2):"));
+        assert!(!list.contains("Change tree:"));
+
+        let tree = prompt_for(PromptLayout::Tree);
+        assert!(!tree.contains("Files changed (2):"));
+        assert!(tree.contains("Change tree:"));
+
+        let both = prompt_for(PromptLayout::Both);
+        assert!(both.contains("Files changed (2):"));
+        assert!(both.contains("Change tree:"));
+
+        assert_eq!(PromptLayout::parse(" Tree "), Some(PromptLayout::Tree));
+        assert_eq!(PromptLayout::parse("grid"), None);
+    }
+
+    #[test]
+    fn single_file_prompt_omits_change_tree_and_keeps_full_diff() {
+        let diff = format!("+{}\n", "x".repeat(10_000));
+        let files = vec![file("src/lib.rs", FileStatus::Modified, 1, 0, &diff, None)];
+        let options = PromptOptions {
+            layout: PromptLayout::Tree,
+            ..PromptOptions::default()
+        };
+
+        let prompt = build_user_prompt("main", &files, &options);
+
+        assert!(!prompt.contains("Change tree:"));
+        assert!(prompt.contains("Files changed (1):"));
+        assert!(prompt.contains(&diff));
+        assert!(!prompt.contains("...[truncated]"));
+    }
+
+    #[test]
+    fn vendored_files_are_collapsed_into_one_line() {
+        let mut files = vec![
+            file("src/lib.rs", FileStatus::Modified, 2, 1, "+real\n", None),
+            file("src/main.rs", FileStatus::Modified, 1, 0, "+main\n", None),
+        ];
+        for i in 0..5 {
+            files.push(file(
+                &format!("vendor/dep/file_{}.rs", i),
+                FileStatus::Modified,
+                10,
+                2,
+                "+vendored\n",
+                None,
+            ));
+        }
+
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
+
+        assert!(prompt.contains("Files changed (2):"));
+        assert!(prompt.contains("- updated 5 generated/vendored files under vendor/ (+50/-10)"));
+        assert!(!prompt.contains("vendor/dep/file_0.rs"));
+        assert!(!prompt.contains("+vendored"));
+        assert!(prompt.contains("+real"));
+    }
+
+    #[test]
+    fn patch_excerpts_carry_language_tags_for_known_extensions() {
+        let files = vec![
+            file("src/lib.rs", FileStatus::Modified, 1, 0, "+x\n", None),
+            file("assets/blob.xyz", FileStatus::Modified, 1, 0, "+y\n", None),
+        ];
+
+        let prompt = build_user_prompt("main", &files, &PromptOptions::default());
+        assert!(prompt.contains("--- src/lib.rs\n// language: rust\n+x"));
+        assert!(prompt.contains("--- assets/blob.xyz\n+y"));
+
+        let untagged = build_user_prompt(
+            "main",
+            &files,
+            &PromptOptions {
+                language_tags: false,
+                ..PromptOptions::default()
+            },
+        );
+        assert!(!untagged.contains("// language:"));
+    }
+
+    #[test]
+    fn vague_messages_are_detected() {
+        assert!(looks_vague("chore: update files", None));
+        assert!(looks_vague(
+            "fix[CORE]: various changes",
+            Some("Tweak a few things.")
+        ));
+        assert!(looks_vague(
+            "feat: wip",
+            Some("Work in progress on the parser.")
+        ));
+        assert!(looks_vague("refactor: cleanup", None));
+
+        assert!(!looks_vague(
+            "fix[GIT]: restore user index after failed run",
+            None
+        ));
+        assert!(!looks_vague(
+            "docs: readme",
+            Some("Document the --staged-only flag.")
+        ));
+        assert!(!looks_vague("feat[TUI]: update files pane width", None));
+    }
+
+    #[test]
+    fn scope_hint_uses_shared_module_directory() {
+        let tui = vec![
+            file("src/tui/app.rs", FileStatus::Modified, 1, 1, "", None),
+            file("src/tui/mod.rs", FileStatus::Modified, 1, 1, "", None),
+        ];
+        assert_eq!(scope_hint(&tui).as_deref(), Some("TUI"));
+
+        let core = vec![file("src/main.rs", FileStatus::Modified, 1, 1, "", None)];
+        assert_eq!(scope_hint(&core).as_deref(), Some("CORE"));
+
+        let mixed = vec![
+            file("src/tui/app.rs", FileStatus::Modified, 1, 1, "", None),
+            file("docs/guide.md", FileStatus::Modified, 1, 1, "", None),
+        ];
+        assert_eq!(scope_hint(&mixed), None);
+    }
+
+    #[test]
+    fn mixed_changes_fall_back_to_repository_scope() {
+        let mixed = vec![
+            file("src/tui/app.rs", FileStatus::Modified, 1, 1, "", None),
+            file("docs/guide.md", FileStatus::Modified, 1, 1, "", None),
+        ];
+
+        assert_eq!(repo_scope("my-app").as_deref(), Some("MY-APP"));
+        assert_eq!(repo_scope("my_app").as_deref(), Some("MY-APP"));
+        assert_eq!(
+            resolve_scope_hint(&mixed, Some("my-app"), &[]).as_deref(),
+            Some("MY-APP")
+        );
+        assert_eq!(resolve_scope_hint(&mixed, None, &[]), None);
+
+        let focused = vec![file("src/tui/app.rs", FileStatus::Modified, 1, 1, "", None)];
+        assert_eq!(
+            resolve_scope_hint(&focused, Some("my-app"), &[]).as_deref(),
+            Some("TUI")
+        );
+    }
+
+    #[test]
+    fn benchmark_and_optimization_changes_hint_perf() {
+        let hints = |files: &[FileInfo], options: &PromptOptions| {
+            build_hints(files, options).unwrap_or_default()
+        };
+        let options = PromptOptions::default();
+
+        let bench = vec![file(
+            "benches/diff_parse.rs",
+            FileStatus::Added,
+            30,
+            0,
+            "+fn parse_large_diff(c: &mut Criterion) {\n",
+            None,
+        )];
+        assert!(hints(&bench, &options).contains("type perf"));
+
+        let cached = vec![file(
+            "src/git.rs",
+            FileStatus::Modified,
+            4,
+            4,
+            "+    // Cache attributes per path to avoid repeated lookups\n",
+            None,
+        )];
+        assert!(hints(&cached, &options).contains("type perf"));
+
+        let off = PromptOptions {
+            perf_hints: false,
+            ..PromptOptions::default()
+        };
+        assert!(!hints(&bench, &off).contains("type perf"));
+        assert!(!hints(&cached, &off).contains("type perf"));
+    }
+
+    #[test]
+    fn type_hint_recognizes_docs_tests_and_new_features() {
+        let docs = vec![file("README.md", FileStatus::Modified, 3, 1, "", None)];
+        assert_eq!(type_hint(&docs), Some("docs"));
+
+        let tests = vec![file("tests/cli.rs", FileStatus::Modified, 3, 1, "", None)];
+        assert_eq!(type_hint(&tests), Some("test"));
+
+        let feature = vec![
+            file("src/check.rs", FileStatus::Added, 40, 0, "", None),
+            file("src/main.rs", FileStatus::Modified, 3, 1, "", None),
+        ];
+        assert_eq!(type_hint(&feature), Some("feat"));
+
+        let tweak = vec![file("src/main.rs", FileStatus::Modified, 3, 3, "", None)];
+        assert_eq!(type_hint(&tweak), None);
+    }
 }
@@ -0,0 +1,320 @@
+use crate::cerebras::{self, Completion, Endpoint};
+use crate::config::Config;
+use crate::error::{Result, YetiError};
+use crate::ollama;
+use std::sync::Arc;
+use std::sync::atomic::AtomicBool;
+
+/// Which backend generates messages, from `provider` in the config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ProviderKind {
+    #[default]
+    Cerebras,
+    Ollama,
+}
+
+impl ProviderKind {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "cerebras" => Some(Self::Cerebras),
+            "ollama" => Some(Self::Ollama),
+            _ => None,
+        }
+    }
+}
+
+/// One generation attempt against a single model.
+pub struct Generation<'a> {
+    pub system_prompt: &'a str,
+    pub model: &'a str,
+    pub user_prompt: &'a str,
+    pub max_completion_tokens: u32,
+    pub temperature: f32,
+    /// Completions to pick the best from; backends that can't sample several stream one.
+    pub candidates: u8,
+}
+
+/// A backend that turns a prompt into a commit message.
+pub trait Provider: Send + Sync {
+    /// Where requests go, for the setup screens.
+    fn endpoint(&self) -> &str;
+
+    /// A local backend runs without a key, so key entry is skipped.
+    fn needs_api_key(&self) -> bool {
+        true
+    }
+
+    /// Checks the key (and, where it matters, that `model` is served); the
+    /// note says what came back, for `--verbose`.
+    fn validate(&self, api_key: &str, model: &str) -> Result<String>;
+
+    fn list_models(&self, api_key: &str) -> Result<Vec<String>>;
+
+    fn generate_commit_message(
+        &self,
+        api_key: &str,
+        generation: &Generation<'_>,
+        cancel: &AtomicBool,
+        on_chunk: &dyn Fn(&str),
+    ) -> Result<Completion>;
+}
+
+pub struct CerebrasProvider {
+    agent: ureq::Agent,
+    url: String,
+    max_retries: u32,
+    /// `allow_no_key`: no key prompt, key check or preflight.
+    keyless: bool,
+    /// Set explicitly, `allow_no_key` also leaves a configured key unsent.
+    send_no_key: bool,
+}
+
+impl CerebrasProvider {
+    fn endpoint(&self) -> Endpoint<'_> {
+        Endpoint {
+            agent: &self.agent,
+            url: &self.url,
+            max_retries: self.max_retries,
+        }
+    }
+
+    /// The key to send; empty, and so left off the request, when told not to.
+    fn key<'a>(&self, api_key: &'a str) -> &'a str {
+        if self.send_no_key { "" } else { api_key }
+    }
+}
+
+impl Provider for CerebrasProvider {
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn needs_api_key(&self) -> bool {
+        !self.keyless
+    }
+
+    /// Without a key there is nothing to check ahead of the request itself.
+    fn validate(&self, api_key: &str, model: &str) -> Result<String> {
+        if self.keyless {
+            return Ok(format!("no key needed for {}", model));
+        }
+        cerebras::validate_api_key(CerebrasProvider::endpoint(self), api_key, model)
+    }
+
+    fn list_models(&self, api_key: &str) -> Result<Vec<String>> {
+        cerebras::list_models(CerebrasProvider::endpoint(self), self.key(api_key))
+    }
+
+    fn generate_commit_message(
+        &self,
+        api_key: &str,
+        generation: &Generation<'_>,
+        cancel: &AtomicBool,
+        on_chunk: &dyn Fn(&str),
+    ) -> Result<Completion> {
+        let endpoint = CerebrasProvider::endpoint(self);
+        let api_key = self.key(api_key);
+        if !self.keyless {
+            cerebras::check_provider_ready(endpoint, api_key, generation.model, cancel)?;
+        }
+        if generation.candidates > 1 {
+            let best = cerebras::generate_best_of(endpoint, api_key, generation, cancel)?;
+            on_chunk(&best.content);
+            return Ok(best);
+        }
+        cerebras::generate_commit_message(endpoint, api_key, generation, cancel, on_chunk)
+    }
+}
+
+pub struct OllamaProvider {
+    agent: ureq::Agent,
+    url: String,
+}
+
+impl Provider for OllamaProvider {
+    fn endpoint(&self) -> &str {
+        &self.url
+    }
+
+    fn needs_api_key(&self) -> bool {
+        false
+    }
+
+    /// There is no key to check; the server must be up with `model` pulled.
+    fn validate(&self, api_key: &str, model: &str) -> Result<String> {
+        let models = self.list_models(api_key)?;
+        if ollama::has_model(&models, model) {
+            Ok(format!("{} is pulled", model))
+        } else {
+            Err(YetiError::ApiError {
+                status: 404,
+                message: format!(
+                    "Model {} is not pulled into Ollama; run `ollama pull {}`",
+                    model, model
+                ),
+            })
+        }
+    }
+
+    fn list_models(&self, _api_key: &str) -> Result<Vec<String>> {
+        ollama::list_models(&self.agent, &self.url)
+    }
+
+    fn generate_commit_message(
+        &self,
+        _api_key: &str,
+        generation: &Generation<'_>,
+        cancel: &AtomicBool,
+        on_chunk: &dyn Fn(&str),
+    ) -> Result<Completion> {
+        ollama::generate_commit_message(&self.agent, &self.url, generation, cancel, on_chunk)
+    }
+}
+
+/// The provider `config` selects, sharing one HTTP agent for the run.
+pub fn from_config(config: &Config) -> Arc<dyn Provider> {
+    let agent = cerebras::http_agent();
+    match config.provider() {
+        ProviderKind::Cerebras => Arc::new(CerebrasProvider {
+            agent,
+            url: config.chat_url(),
+            max_retries: config.max_retries(),
+            keyless: config.allow_no_key(),
+            send_no_key: config.send_no_key(),
+        }),
+        ProviderKind::Ollama => Arc::new(OllamaProvider {
+            agent,
+            url: config.ollama_url(),
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Generation, ProviderKind, from_config};
+    use crate::config::Config;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::atomic::AtomicBool;
+
+    #[test]
+    fn config_selects_the_provider() {
+        let cerebras = from_config(&Config::default());
+        assert!(cerebras.needs_api_key());
+        assert_eq!(cerebras.endpoint(), crate::cerebras::API_URL);
+
+        let ollama = from_config(&Config {
+            provider: Some("Ollama".to_string()),
+            ollama_url: Some("http://127.0.0.1:11434/".to_string()),
+            ..Config::default()
+        });
+        assert!(!ollama.needs_api_key());
+        assert_eq!(ollama.endpoint(), "http://127.0.0.1:11434");
+
+        assert_eq!(ProviderKind::parse("openai"), None);
+    }
+
+    /// Serves one streamed completion on localhost and reports the request's
+    /// headers, lowercased.
+    fn serve_once() -> (String, std::sync::mpsc::Receiver<Vec<String>>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let base = format!("http://{}/v1", listener.local_addr().unwrap());
+        let (seen_tx, seen) = std::sync::mpsc::channel();
+        std::thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+            let mut headers = Vec::new();
+            let mut content_length = 0;
+            loop {
+                let mut line = String::new();
+                reader.read_line(&mut line).unwrap();
+                if line == "\r\n" {
+                    break;
+                }
+                let line = line.trim_end().to_ascii_lowercase();
+                if let Some(value) = line.strip_prefix("content-length:") {
+                    content_length = value.trim().parse().unwrap();
+                }
+                headers.push(line);
+            }
+            let mut body = vec![0; content_length];
+            reader.read_exact(&mut body).unwrap();
+            let sse = "data: {\"choices\":[{\"delta\":{\"content\":\"feat: add thing\"}}]}\n\ndata: [DONE]\n\n";
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/event-stream\r\nContent-Length: {}\r\n\r\n{}",
+                sse.len(),
+                sse
+            );
+            stream.write_all(reply.as_bytes()).unwrap();
+            seen_tx.send(headers).unwrap();
+        });
+        (base, seen)
+    }
+
+    /// The Authorization header a generation through `config` sent with `key`.
+    fn sent_authorization(config: Config, key: &str) -> Option<String> {
+        let generation = Generation {
+            system_prompt: "system",
+            model: "local-llama",
+            user_prompt: "diff",
+            max_completion_tokens: 64,
+            temperature: 0.0,
+            candidates: 1,
+        };
+        let (base, seen) = serve_once();
+        let provider = from_config(&Config {
+            base_url: Some(base),
+            ..config
+        });
+        let completion = provider
+            .generate_commit_message(key, &generation, &AtomicBool::new(false), &|_| {})
+            .unwrap();
+        assert_eq!(completion.content, "feat: add thing");
+        seen.recv()
+            .unwrap()
+            .into_iter()
+            .find(|h| h.starts_with("authorization:"))
+    }
+
+    #[test]
+    fn keyless_mode_sends_no_authorization_header() {
+        let local = Config {
+            base_url: Some("http://127.0.0.1:8080/v1".to_string()),
+            ..Config::default()
+        };
+        assert!(local.allow_no_key());
+        assert!(!local.send_no_key());
+        let provider = from_config(&local);
+        assert!(!provider.needs_api_key());
+        assert_eq!(
+            provider.validate("", "local-llama").unwrap(),
+            "no key needed for local-llama"
+        );
+
+        // Detected from localhost: no prompt, but a configured key still goes out.
+        assert_eq!(
+            sent_authorization(Config::default(), "sk-local").as_deref(),
+            Some("authorization: bearer sk-local")
+        );
+        assert_eq!(sent_authorization(Config::default(), ""), None);
+        let explicit = Config {
+            allow_no_key: Some(true),
+            ..Config::default()
+        };
+        assert_eq!(sent_authorization(explicit, "sk-stale"), None);
+
+        let remote = Config {
+            base_url: Some("https://llm.example.com/v1".to_string()),
+            ..Config::default()
+        };
+        assert!(!remote.allow_no_key());
+        assert!(from_config(&remote).needs_api_key());
+        let opted_in = Config {
+            allow_no_key: Some(true),
+            ..remote
+        };
+        assert!(opted_in.allow_no_key());
+        assert!(opted_in.send_no_key());
+        assert!(!Config::default().allow_no_key());
+    }
+}
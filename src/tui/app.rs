@@ -1,24 +1,53 @@
 use crate::args::Args;
 use crate::args::MASCOT_MINI;
-use crate::cerebras;
-use crate::config::{self, Config};
-use crate::error::Result;
-use crate::git::{GitRepo, StagedSummary, unstage_all_with_git_cli};
-use crate::prompt::{self, FileInfo};
-use crate::tui::{Theme, Tui, draw_error, draw_key_input, draw_status_panel};
-use crossterm::event::{Event, KeyCode};
+use crate::cerebras::{self, IssuePattern};
+use crate::config::{self, Config, SizeLimits};
+use crate::editor;
+use crate::error::{Result, YetiError};
+use crate::git::{
+    CommitOptions, GitRepo, Identity, StageMode, StageSnapshot, StagedSummary, WorktreeChange,
+    is_duplicate_subject, local_time_now,
+};
+use crate::history::{self, HistoryEntry};
+use crate::prompt::{self, FileInfo, FileStatus};
+use crate::provider::{self, Generation, Provider};
+use crate::tui::{
+    Theme, Tui, draw_diff_view, draw_error, draw_file_picker, draw_key_input, draw_message_picker,
+    draw_status_panel, format_count,
+};
+use crossterm::event::{Event, KeyCode, KeyEvent, KeyModifiers};
 use ratatui::{
     Frame,
     layout::{Constraint, Layout},
+    style::Modifier,
     text::{Line, Span},
     widgets::{Block, BorderType, Padding, Paragraph, Wrap},
 };
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::{Arc, Mutex};
 use std::thread;
-use std::time::Instant;
+use std::time::{Duration, Instant};
+use unicode_width::UnicodeWidthStr;
 
 const NO_CHUNK_TIMEOUT_SECS: u64 = 45;
 const MAX_GENERATION_TIMEOUT_SECS: u64 = 120;
+// Regenerations allowed in strict mode before giving up on a malformed title.
+const MAX_STRICT_RETRIES: u8 = 2;
+const TYPEWRITER_CHARS_PER_SEC: f64 = 160.0;
+const DIFF_PAGE_LINES: u16 = 20;
+const FOOTER_NOTE_SECS: u64 = 1;
+/// Most message lines the main screen's preview pane shows.
+const MESSAGE_PREVIEW_LINES: usize = 12;
+/// Temperature added per `--pick` generation so the choices differ; eight
+/// of them still stay within 1.0 of the configured temperature.
+const CANDIDATE_TEMPERATURE_STEP: f32 = 0.14;
+/// Temperature added for the one retry after the model refused.
+const REFUSAL_RETRY_TEMPERATURE_STEP: f32 = 0.3;
+/// Generation threads allowed at once: the current one and one cancelled
+/// request still waiting out its network read.
+const MAX_LIVE_GENERATIONS: usize = 2;
+const KEY_REJECTED: &str = "The provider rejected this API key (401). Enter a new one.";
 
 #[derive(Debug, Clone)]
 pub enum AppState {
@@ -28,14 +57,58 @@ pub enum AppState {
         error: Option<String>,
     },
     ApiKeyValidating,
+    /// First run only: confirm (or retype) the model before anything is generated.
+    ConfirmModel {
+        input: String,
+        cursor: usize,
+    },
     Staging {
         branch: String,
     },
+    PartialStage {
+        files: Vec<String>,
+    },
+    /// `--stage-prompt`: nothing is staged, but these paths could be.
+    OfferStage {
+        files: Vec<String>,
+    },
+    /// `--select`: pick which work tree changes to stage.
+    SelectFiles {
+        changes: Vec<WorktreeChange>,
+        selected: Vec<bool>,
+        cursor: usize,
+    },
     Generating {
         branch: String,
         files: Vec<FileInfo>,
         generated: String,
         started_at: Instant,
+        last_progress: Instant,
+        /// Characters of `generated` shown so far when typewriter mode is on.
+        revealed: usize,
+        last_reveal: Instant,
+    },
+    /// `--pick`: choose one of several generated messages.
+    PickMessage {
+        branch: String,
+        files: Vec<FileInfo>,
+        candidates: Vec<(String, Option<String>)>,
+        selected: usize,
+    },
+    /// One-line hint to steer a regenerate (`h`).
+    Hinting {
+        input: String,
+        cursor: usize,
+    },
+    /// The user stopped the stream to edit the message by hand.
+    Editing {
+        branch: String,
+        files: Vec<FileInfo>,
+        buffer: String,
+        /// Byte offset into `buffer`, always on a char boundary.
+        cursor: usize,
+        /// Rescued from a stream that timed out, so it needs a look first.
+        salvaged: bool,
     },
     Committing {
         branch: String,
@@ -48,31 +121,323 @@ pub enum AppState {
         message: String,
         done_at: Instant,
     },
+    /// `d` from the main screen: one file's stored patch, drawn over `back`.
+    DiffView {
+        file_index: usize,
+        scroll: u16,
+        /// The main screen to return to; events keep updating it meanwhile.
+        back: Box<AppState>,
+    },
+    /// Staged files past the size limits; waits for an explicit go-ahead.
+    LargeFiles {
+        report: SizeReport,
+        summary: StagedSummary,
+    },
+    ConfirmUnstage {
+        message: String,
+        retryable: bool,
+    },
     Error {
         message: String,
         retryable: bool,
     },
 }
 
-#[derive(Debug, Clone)]
+#[derive(Debug)]
 pub enum AppEvent {
     ApiKeyEntered(String),
     ApiKeyValidated,
     ApiKeyValidationFailed(String),
-    StagingComplete(StagedSummary),
+    /// A 401 from validation or mid-generation: back to key entry either way.
+    KeyRejected,
+    /// `model: note` from a key check that passed, for `--verbose`.
+    ProviderReply(String),
+    PartialStageDetected(Vec<String>),
+    /// The index matches HEAD while these work tree paths have changes.
+    NothingStaged(Vec<String>),
+    /// Work tree changes to offer in the `--select` picker.
+    ChangesListed(Vec<WorktreeChange>),
+    StagingComplete(StagedSummary, StageSnapshot),
     StagingFailed(String),
+    /// `--amend` with the index still matching HEAD: nothing to generate.
+    NothingToAmend,
+    ModelFallback(String),
     GenerationChunk(String),
+    GenerationTruncated,
     GenerationComplete(String),
+    /// One `--pick` generation finished, successfully or not.
+    CandidateReady,
+    /// Every `--pick` reply that came back, unparsed.
+    CandidatesComplete(Vec<String>),
     GenerationFailed(String),
-    CommitComplete,
+    /// Carries the files a commit hook rewrote and yeti re-staged.
+    CommitComplete(Vec<String>),
     CommitFailed(String),
 }
 
+/// The staging thread's work: run the stage hook, stage per `mode`, then
+/// summarize what the commit will contain. With `amend`, an index that still
+/// matches HEAD ends the run before any API call.
+fn stage_and_summarize(
+    repo: &GitRepo,
+    mode: StageMode,
+    plan: StagePlan,
+    include_ignored: bool,
+    amend: bool,
+    stage_hook: Option<&str>,
+    context_commits: usize,
+) -> Result<AppEvent> {
+    // Checked before staging so an empty repo is left exactly as it was.
+    if amend && !repo.has_commits() {
+        return Err(YetiError::NoCommitToAmend);
+    }
+    if plan == StagePlan::Auto && mode != StageMode::StagedOnly {
+        let partial = repo.partially_staged_files()?;
+        if !partial.is_empty() {
+            return Ok(AppEvent::PartialStageDetected(partial));
+        }
+    }
+    if let Some(command) = stage_hook
+        && mode != StageMode::StagedOnly
+    {
+        repo.run_stage_hook(command)?;
+    }
+    let snapshot = repo.stage(mode)?;
+    match summarize(repo, snapshot, include_ignored, amend, context_commits) {
+        // "No changes" is misleading when the changes are merely unstaged.
+        Err(YetiError::NoChangesToCommit) if mode == StageMode::StagedOnly => {
+            let unstaged: Vec<String> = repo
+                .worktree_changes(false)?
+                .into_iter()
+                .map(|change| change.path)
+                .collect();
+            if unstaged.is_empty() {
+                Err(YetiError::NoChangesToCommit)
+            } else {
+                Ok(AppEvent::NothingStaged(unstaged))
+            }
+        }
+        result => result,
+    }
+}
+
+/// `--select`'s first step: run the stage hook so its edits can be picked,
+/// then list what there is to stage.
+fn list_selectable(
+    repo: &GitRepo,
+    amend: bool,
+    stage_hook: Option<&str>,
+    recurse_untracked: bool,
+) -> Result<AppEvent> {
+    if amend && !repo.has_commits() {
+        return Err(YetiError::NoCommitToAmend);
+    }
+    if let Some(command) = stage_hook {
+        repo.run_stage_hook(command)?;
+    }
+    Ok(AppEvent::ChangesListed(
+        repo.selectable_changes(recurse_untracked)?,
+    ))
+}
+
+/// What the commit will contain once staging is done, with the last
+/// `context_commits` commits as background; `snapshot` rides along so a later
+/// failure can restore the index.
+fn summarize(
+    repo: &GitRepo,
+    snapshot: StageSnapshot,
+    include_ignored: bool,
+    amend: bool,
+    context_commits: usize,
+) -> Result<AppEvent> {
+    let summary = if amend {
+        repo.amend_summary(prompt::PATCH_BUDGET)
+    } else {
+        repo.get_staged_summary(prompt::PATCH_BUDGET)
+    };
+    let mut summary = match summary {
+        Err(YetiError::NothingToAmend) => return Ok(AppEvent::NothingToAmend),
+        summary => summary?,
+    };
+    if include_ignored {
+        repo.mark_ignored(&mut summary.files)?;
+    }
+    if context_commits > 0 {
+        // An amend rewrites HEAD, so its own diff is not background.
+        let from = if amend { "HEAD^" } else { "HEAD" };
+        summary.recent_commits = repo
+            .recent_commits(from, context_commits)
+            .unwrap_or_default();
+    }
+    Ok(AppEvent::StagingComplete(summary, snapshot))
+}
+
+/// A committed title and body with the staged tree they describe.
+type CachedMessage = (git2::Oid, String, Option<String>);
+
+/// Background work; returns its final event, or `None` when it was cancelled
+/// and has nothing left to report.
+type Job = Box<dyn FnOnce(&Sender<AppEvent>) -> Option<AppEvent> + Send>;
+
+/// Runs background operations on the repository (staging, validation,
+/// commit) one at a time. A trigger that arrives while one is running waits
+/// its turn; a newer trigger replaces an older one that is still waiting.
+#[derive(Clone, Default)]
+struct OpLane {
+    state: Arc<Mutex<LaneState>>,
+}
+
+#[derive(Default)]
+struct LaneState {
+    busy: bool,
+    next: Option<Job>,
+}
+
+impl OpLane {
+    /// Runs `job` on the worker thread and sends the event it returns, if any.
+    fn submit(&self, tx: &Sender<AppEvent>, job: Job) {
+        let mut lane = self.state.lock().unwrap_or_else(|e| e.into_inner());
+        if lane.busy {
+            lane.next = Some(job);
+            return;
+        }
+        lane.busy = true;
+        drop(lane);
+
+        let state = Arc::clone(&self.state);
+        let tx = tx.clone();
+        thread::spawn(move || {
+            let mut job = job;
+            loop {
+                if let Some(event) = job(&tx) {
+                    let _ = tx.send(event);
+                }
+                let mut lane = state.lock().unwrap_or_else(|e| e.into_inner());
+                match lane.next.take() {
+                    Some(next) => job = next,
+                    None => {
+                        lane.busy = false;
+                        return;
+                    }
+                }
+            }
+        });
+    }
+}
+
+/// Runs `job` on its own thread, outside the lane. Generation only talks to
+/// the provider, so a request still stuck on the network after Esc can't hold
+/// up the staging or commit that follows it. A cancelled job returns at its
+/// next cancel check or when the request times out; until then it counts in
+/// `live`, and nothing is spawned (`false`) once `MAX_LIVE_GENERATIONS` run.
+fn run_detached(tx: &Sender<AppEvent>, live: &Arc<AtomicUsize>, job: Job) -> bool {
+    let reserved = live.fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| {
+        (n < MAX_LIVE_GENERATIONS).then_some(n + 1)
+    });
+    if reserved.is_err() {
+        return false;
+    }
+    let tx = tx.clone();
+    let live = Arc::clone(live);
+    thread::spawn(move || {
+        if let Some(event) = job(&tx) {
+            let _ = tx.send(event);
+        }
+        live.fetch_sub(1, Ordering::SeqCst);
+    });
+    true
+}
+
+/// Staged files over `max_file_mb`, largest first, and the staged total.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SizeReport {
+    pub oversized: Vec<(String, u64)>,
+    pub total: u64,
+    pub total_exceeded: bool,
+}
+
+fn size_report(mut sizes: Vec<(String, u64)>, limits: SizeLimits) -> Option<SizeReport> {
+    let total = sizes.iter().map(|(_, size)| size).sum();
+    let total_exceeded = limits.total_bytes > 0 && total > limits.total_bytes;
+    sizes.retain(|(_, size)| limits.file_bytes > 0 && *size > limits.file_bytes);
+    sizes.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+    (total_exceeded || !sizes.is_empty()).then_some(SizeReport {
+        oversized: sizes,
+        total,
+        total_exceeded,
+    })
+}
+
+fn format_size(bytes: u64) -> String {
+    const MB: f64 = 1024.0 * 1024.0;
+    if bytes as f64 >= MB {
+        format!("{:.1} MB", bytes as f64 / MB)
+    } else {
+        format!("{} KB", bytes.div_ceil(1024))
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum CleanupAction {
+    Keep,
+    Ask,
+    Unstage,
+}
+
+/// Interactive runs ask before touching the index; headless runs clean up as before.
+fn cleanup_action(should_unstage: bool, interactive: bool) -> CleanupAction {
+    match (should_unstage, interactive) {
+        (false, _) => CleanupAction::Keep,
+        (true, true) => CleanupAction::Ask,
+        (true, false) => CleanupAction::Unstage,
+    }
+}
+
+/// Answer to "unstage changes? [y/N]"; anything but an explicit yes keeps the stage.
+fn unstage_confirmed(code: KeyCode) -> Option<bool> {
+    match code {
+        KeyCode::Char('y') | KeyCode::Char('Y') => Some(true),
+        KeyCode::Char('n') | KeyCode::Char('N') | KeyCode::Enter => Some(false),
+        _ => None,
+    }
+}
+
+/// `mode` as staging runs it: without `recurse_untracked`, staging
+/// everything leaves the inside of untracked directories alone.
+fn effective_stage_mode(mode: StageMode, recurse_untracked: bool) -> StageMode {
+    match mode {
+        StageMode::All if !recurse_untracked => StageMode::AllTopLevel,
+        mode => mode,
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum StagePlan {
+    /// Stage everything unless some files are only partially staged.
+    Auto,
+    StageRemainder,
+    StagedOnly,
+}
+
 pub struct AppResult {
     pub branch: String,
     pub files: Vec<FileInfo>,
     pub message: String,
     pub dry_run: bool,
+    pub finished_at: git2::Time,
+    pub twelve_hour_clock: bool,
+    /// Message lines to print in the summary box before eliding the rest.
+    pub max_message_lines: Option<usize>,
+    /// The title repeats the previous commit's subject.
+    pub duplicate_subject: bool,
+    /// Who the commit would be authored as; only filled for dry runs.
+    pub author: Option<Identity>,
+    /// Set when a malformed config file was ignored.
+    pub config_warning: Option<String>,
+    /// Abbreviated hash of the new commit; `None` on a dry run.
+    pub sha: Option<String>,
+    /// Files a commit hook reformatted; their new content was committed.
+    pub rewritten_by_hooks: Vec<String>,
 }
 
 pub struct App {
@@ -80,6 +445,81 @@ pub struct App {
     config: Config,
     api_key: Option<String>,
     dry_run: bool,
+    /// `--force`: skip the large-file and line-ending-only stops.
+    force: bool,
+    amend: bool,
+    /// Contents of `--fixes-file`, passed to every prompt this run.
+    fixes: Option<String>,
+    /// `--context-commits`: how many earlier commits to show the model.
+    context_commits: usize,
+    /// Printed after the TUI closes when the run ended early without a commit.
+    exit_notice: Option<String>,
+    stage_mode: StageMode,
+    sign: Option<String>,
+    /// `--tag`: annotated tag to create once the commit lands.
+    tag: Option<String>,
+    tag_message: Option<String>,
+    /// `--type` and `--scope`, forced onto the title's head.
+    commit_type: Option<String>,
+    scope: Option<String>,
+    /// Finds the issue for the body's `Refs:` footer in the branch name.
+    issue_pattern: IssuePattern,
+    /// The last message sent to commit, as finally edited, and the staged
+    /// tree it was written for, so a retry after a failed commit can skip the
+    /// API call without losing the user's edits.
+    cached_message: Option<CachedMessage>,
+    /// Set by `R`: the next generation reuses `cached_message` if the stage
+    /// is unchanged.
+    reuse_message: bool,
+    /// `--stage-prompt`: offer to stage instead of failing on an empty index.
+    stage_prompt: bool,
+    /// `--pick`: how many messages to generate and pick from.
+    pick: Option<u8>,
+    /// `(ready, total)` for the `--pick` generations in flight.
+    candidate_progress: Option<(u8, u8)>,
+    /// Set when the primary model was overloaded and a fallback took over.
+    fallback_model: Option<String>,
+    /// Backend every API call this run goes through; one shared HTTP agent.
+    provider: Arc<dyn Provider>,
+    verbose: bool,
+    /// `--select`: pick the files to stage instead of staging everything.
+    select_files: bool,
+    provider_replies: Vec<String>,
+    /// The provider stopped at the token limit, so the body may end abruptly.
+    truncated: bool,
+    /// The generated title matches HEAD's subject; advisory only.
+    duplicate_subject: bool,
+    /// Set when a malformed config file was ignored in favor of defaults.
+    config_warning: Option<String>,
+    /// Serializes staging, validation and commit so two never race on the
+    /// index; generation runs detached, outside it.
+    lane: OpLane,
+    /// Generation threads still running, cancelled ones included.
+    live_generations: Arc<AtomicUsize>,
+    /// Tells the streaming thread to stop consuming chunks.
+    cancel_stream: Arc<AtomicBool>,
+    /// Kept so a vague first message can be regenerated from the same stage.
+    last_summary: Option<StagedSummary>,
+    /// The current generation is already the one sharper retry.
+    vague_retry: bool,
+    /// The model declined once already since the last staging.
+    refusal_retry: bool,
+    /// Generation failed with the stage intact, so `m` can open `$EDITOR`
+    /// to write the message by hand.
+    offer_manual: bool,
+    /// Steering hint typed with `h`; lives only as long as this run.
+    hint: Option<String>,
+    /// Opened on the first copy and kept so the text stays pasteable (an X11
+    /// selection lives only as long as its owner).
+    clipboard: Option<arboard::Clipboard>,
+    /// Short-lived footer text such as "copied", and when it was set.
+    footer_note: Option<(String, Instant)>,
+    /// Titles rejected by strict mode since the last staging.
+    strict_rejections: u8,
+    /// Whether a person is at the keyboard to answer prompts.
+    interactive: bool,
+    /// What the index held before yeti staged anything, for cleanup on failure.
+    stage_snapshot: StageSnapshot,
     theme: Theme,
     event_rx: Receiver<AppEvent>,
     event_tx: Sender<AppEvent>,
@@ -92,11 +532,32 @@ impl App {
             config::clear_local_cache()?;
         }
 
-        let config = config::load()?;
-        let api_key = config::get_effective_api_key(&config);
+        let (mut config, mut config_warning) = config::load(args.profile.as_deref())?;
+        let issue_pattern = IssuePattern::new(config.issue_pattern()).unwrap_or_else(|e| {
+            let warning = format!("issue_pattern is not a valid regex ({}); ignoring it", e);
+            config_warning = Some(match config_warning.take() {
+                Some(existing) => format!("{}; {}", existing, warning),
+                None => warning,
+            });
+            IssuePattern::Default
+        });
+        let fixes = args
+            .fixes_file
+            .as_deref()
+            .map(std::fs::read_to_string)
+            .transpose()?;
+        args.apply_to(&mut config);
+        let api_key =
+            config::get_effective_api_key(&config, args.api_key.as_ref().map(|k| k.expose()));
+        let dry_run = args.is_dry_run(&config);
+        let provider = provider::from_config(&config);
+        let stage_mode = effective_stage_mode(args.stage_mode(), config.recurse_untracked());
+        // A local provider has no key to enter; an empty one keeps the flow uniform.
+        let api_key = api_key.or_else(|| (!provider.needs_api_key()).then(String::new));
         let (event_tx, event_rx) = mpsc::channel();
 
-        let state = if args.reset_cache || args.reset_key || api_key.is_none() {
+        let needs_key = provider.needs_api_key() && (args.reset_cache || args.reset_key);
+        let state = if needs_key || api_key.is_none() {
             AppState::ApiKeyInput {
                 input: String::new(),
                 cursor: 0,
@@ -112,8 +573,50 @@ impl App {
             state,
             config,
             api_key,
-            dry_run: args.dry_run,
-            theme: Theme::gruvbox(),
+            dry_run,
+            force: args.force,
+            amend: args.amend,
+            exit_notice: None,
+            fixes,
+            context_commits: args.context_commits,
+            stage_mode,
+            sign: args.sign,
+            tag: args.tag,
+            tag_message: args.tag_message,
+            commit_type: args.commit_type,
+            scope: args.scope,
+            issue_pattern,
+            cached_message: None,
+            reuse_message: false,
+            stage_prompt: args.stage_prompt,
+            pick: args.pick,
+            candidate_progress: None,
+            fallback_model: None,
+            provider,
+            verbose: args.verbose,
+            select_files: args.select,
+            provider_replies: Vec::new(),
+            truncated: false,
+            duplicate_subject: false,
+            config_warning,
+            lane: OpLane::default(),
+            live_generations: Arc::new(AtomicUsize::new(0)),
+            cancel_stream: Arc::new(AtomicBool::new(false)),
+            last_summary: None,
+            vague_retry: false,
+            refusal_retry: false,
+            offer_manual: false,
+            hint: None,
+            clipboard: None,
+            footer_note: None,
+            strict_rejections: 0,
+            interactive: true,
+            stage_snapshot: StageSnapshot::default(),
+            theme: if crate::color::color_enabled() {
+                Theme::gruvbox()
+            } else {
+                Theme::plain()
+            },
             event_rx,
             event_tx,
             result: None,
@@ -131,31 +634,45 @@ impl App {
             {
                 break;
             }
-
-            let generation_timed_out = matches!(
-                &self.state,
-                AppState::Generating {
-                    started_at,
-                    generated,
-                    ..
-                } if (generated.is_empty() && started_at.elapsed().as_secs() >= NO_CHUNK_TIMEOUT_SECS)
-                    || started_at.elapsed().as_secs() >= MAX_GENERATION_TIMEOUT_SECS
-            );
-            if generation_timed_out {
-                self.fail_with_cleanup(
-                    "Provider timed out while generating commit message. Press R to retry or K to re-enter API key."
-                        .into(),
-                    true,
-                );
+            if self.exit_notice.is_some() {
+                break;
             }
 
+            self.check_generation_timeout();
+
             if let Some(event) = tui.poll_event(50)
                 && let Event::Key(key) = event
                 && key.kind == crossterm::event::KeyEventKind::Press
             {
                 match key.code {
+                    KeyCode::Esc if matches!(self.state, AppState::DiffView { .. }) => {
+                        self.close_diff_view()
+                    }
+                    KeyCode::Esc if matches!(self.state, AppState::Generating { .. }) => {
+                        self.cancel_generation()
+                    }
+                    KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('Q')
+                        if matches!(self.state, AppState::ApiKeyInput { .. })
+                            && !self.stage_snapshot.is_empty() =>
+                    {
+                        self.abandon_key_reentry()
+                    }
                     KeyCode::Esc => break,
+                    _ if matches!(
+                        self.state,
+                        AppState::Editing { .. }
+                            | AppState::ConfirmModel { .. }
+                            | AppState::Hinting { .. }
+                    ) =>
+                    {
+                        self.handle_text_key(key)
+                    }
                     KeyCode::Char('q') | KeyCode::Char('Q') => break,
+                    KeyCode::Char('m') | KeyCode::Char('M')
+                        if self.offer_manual && matches!(self.state, AppState::Error { .. }) =>
+                    {
+                        self.write_message_by_hand(tui)?
+                    }
                     _ => self.handle_key(key.code),
                 }
             }
@@ -164,6 +681,7 @@ impl App {
                 self.handle_event(event);
             }
 
+            self.tick_typewriter();
             tui.terminal().draw(|f| self.draw(f))?;
         }
 
@@ -174,27 +692,201 @@ impl App {
         self.result.as_ref()
     }
 
-    fn start_staging(&mut self) {
-        let tx = self.event_tx.clone();
-        thread::spawn(move || {
-            let result = (|| {
-                let repo = GitRepo::discover()?;
-                repo.stage_all()?;
-                repo.get_staged_summary()
-            })();
+    pub fn exit_notice(&self) -> Option<&str> {
+        self.exit_notice.as_deref()
+    }
+
+    /// What the model said to each key check, kept only with `--verbose`.
+    pub fn provider_replies(&self) -> &[String] {
+        &self.provider_replies
+    }
+
+    /// The pipeline without a terminal: stage, generate and commit with no
+    /// prompts. Anything that would wait for a keypress fails instead.
+    pub fn run_headless(&mut self) -> Result<()> {
+        self.interactive = false;
+        if self.pick.is_some() {
+            return Err(YetiError::Headless(
+                "--pick needs a terminal to pick a message in".to_string(),
+            ));
+        }
+        if matches!(self.state, AppState::Staging { .. }) {
+            self.start_staging();
+        }
 
-            match result {
-                Ok(summary) => {
-                    let _ = tx.send(AppEvent::StagingComplete(summary));
+        loop {
+            match &self.state {
+                AppState::Done { .. } => return Ok(()),
+                AppState::ApiKeyInput { .. } if self.stage_snapshot.is_empty() => {
+                    return Err(YetiError::InvalidApiKey(format!(
+                        "no usable API key; set ${} or run yeti in a terminal to enter one",
+                        self.config.key_env()
+                    )));
                 }
-                Err(e) => {
-                    let _ = tx.send(AppEvent::StagingFailed(e.to_string()));
+                AppState::ApiKeyInput { .. } => self.abandon_key_reentry(),
+                AppState::SelectFiles { .. } => {
+                    return Err(YetiError::Headless(
+                        "--select needs a terminal to pick files in".to_string(),
+                    ));
+                }
+                AppState::PartialStage { files } => {
+                    return Err(YetiError::Headless(format!(
+                        "Some files are partially staged ({}); pass --staged-only to commit the index as-is",
+                        files.join(", ")
+                    )));
                 }
+                // These fail through the cleanup path, so what yeti staged is unstaged.
+                AppState::Editing { .. } => self.fail_with_cleanup(
+                    "The provider timed out mid-message; run yeti in a terminal to review the partial message"
+                        .to_string(),
+                    false,
+                ),
+                AppState::LargeFiles { .. } => self.fail_with_cleanup(
+                    "Staged files exceed the size limits; pass --force to commit them anyway"
+                        .to_string(),
+                    false,
+                ),
+                AppState::Error { message, .. } => {
+                    return Err(YetiError::Headless(message.clone()));
+                }
+                _ => {}
             }
-        });
+            if self.exit_notice.is_some() {
+                return Ok(());
+            }
+
+            self.check_generation_timeout();
+            if let Ok(event) = self.event_rx.recv_timeout(Duration::from_millis(50)) {
+                self.handle_event(event);
+            }
+        }
+    }
+
+    fn check_generation_timeout(&mut self) {
+        let generation_timed_out = matches!(
+            &self.state,
+            AppState::Generating {
+                started_at,
+                last_progress,
+                ..
+            } if generation_timed_out(started_at.elapsed(), last_progress.elapsed())
+        );
+        if !generation_timed_out {
+            return;
+        }
+        let partial = match &mut self.state {
+            AppState::Generating {
+                branch,
+                files,
+                generated,
+                ..
+            } if self.config.commit_partial_on_timeout() => {
+                cerebras::salvage_partial_message(generated)
+                    .map(str::to_string)
+                    .map(|raw| (std::mem::take(branch), std::mem::take(files), raw))
+            }
+            _ => None,
+        };
+        match partial {
+            // A cut-off message is never committed unseen: it opens for editing.
+            Some((branch, files, buffer)) => {
+                self.cancel_stream.store(true, Ordering::Relaxed);
+                self.state = AppState::Editing {
+                    branch,
+                    files,
+                    cursor: buffer.len(),
+                    buffer,
+                    salvaged: true,
+                };
+            }
+            None => self.fail_with_cleanup(
+                "Provider timed out while generating commit message. Press R to retry or K to re-enter API key."
+                    .into(),
+                true,
+            ),
+        }
+    }
+
+    fn start_staging(&mut self) {
+        self.start_staging_with(StagePlan::Auto);
+    }
+
+    fn start_staging_with(&mut self, plan: StagePlan) {
+        if self.select_files && plan == StagePlan::Auto {
+            let amend = self.amend;
+            let stage_hook = self.config.pre_stage_command().map(str::to_string);
+            let recurse = self.config.recurse_untracked();
+            self.lane.submit(
+                &self.event_tx,
+                Box::new(move |_| {
+                    let result = GitRepo::discover().and_then(|repo| {
+                        list_selectable(&repo, amend, stage_hook.as_deref(), recurse)
+                    });
+                    Some(result.unwrap_or_else(|e| AppEvent::StagingFailed(e.to_string())))
+                }),
+            );
+            return;
+        }
+        let include_ignored = self.config.include_ignored();
+        let mode = if plan == StagePlan::StagedOnly {
+            StageMode::StagedOnly
+        } else {
+            self.stage_mode
+        };
+        let amend = self.amend;
+        let stage_hook = self.config.pre_stage_command().map(str::to_string);
+        let context_commits = self.context_commits;
+        self.lane.submit(
+            &self.event_tx,
+            Box::new(move |_| {
+                let result = GitRepo::discover().and_then(|repo| {
+                    stage_and_summarize(
+                        &repo,
+                        mode,
+                        plan,
+                        include_ignored,
+                        amend,
+                        stage_hook.as_deref(),
+                        context_commits,
+                    )
+                });
+                Some(result.unwrap_or_else(|e| AppEvent::StagingFailed(e.to_string())))
+            }),
+        );
+    }
+
+    /// Stages the paths picked in `SelectFiles` and unstages the rest, so the
+    /// commit holds the selection and nothing else.
+    fn start_staging_selected(&mut self, paths: Vec<String>) {
+        self.state = AppState::Staging {
+            branch: "unknown".into(),
+        };
+        let include_ignored = self.config.include_ignored();
+        let amend = self.amend;
+        let context_commits = self.context_commits;
+        self.lane.submit(
+            &self.event_tx,
+            Box::new(move |_| {
+                let result = GitRepo::discover().and_then(|repo| {
+                    let snapshot = repo.stage_paths(&paths)?;
+                    summarize(&repo, snapshot, include_ignored, amend, context_commits)
+                });
+                Some(result.unwrap_or_else(|e| AppEvent::StagingFailed(e.to_string())))
+            }),
+        );
     }
 
     fn start_generation(&mut self, summary: StagedSummary) {
+        if std::mem::take(&mut self.reuse_message) {
+            let stage = GitRepo::discover()
+                .and_then(|repo| repo.staged_tree_id())
+                .ok();
+            if let Some((title, body)) = reusable_message(self.cached_message.as_ref(), stage) {
+                self.last_summary = Some(summary.clone());
+                self.start_commit(summary.branch, summary.files, title, body);
+                return;
+            }
+        }
         let Some(api_key) = self.api_key.clone() else {
             self.state = AppState::Error {
                 message: "No API key".into(),
@@ -203,43 +895,166 @@ impl App {
             return;
         };
 
-        let model = self.config.model().to_string();
+        let models = self.config.model_chain();
+        let max_tokens = self.config.max_completion_tokens();
+        let mut temperature = self.config.temperature();
+        if self.refusal_retry {
+            // The same sampling just refused; nudge it off that answer.
+            temperature =
+                (temperature + REFUSAL_RETRY_TEMPERATURE_STEP).min(cerebras::MAX_TEMPERATURE);
+        }
+        let gitmoji = self.config.gitmoji();
+        let candidates = self.config.candidates();
+        let pick = self.pick;
+        self.candidate_progress = pick.map(|n| (0, n));
+        self.fallback_model = None;
+        self.truncated = false;
+        self.duplicate_subject = false;
         let branch = summary.branch.clone();
         let files = summary.files.clone();
-        let user_prompt = prompt::build_user_prompt(&branch, &files);
+        let mut prompt_options = self.config.prompt_options();
+        prompt_options.repo_name = summary.repo_name.clone();
+        prompt_options.workspace_crates = summary.workspace_crates.clone();
+        prompt_options.fixes = self.fixes.clone();
+        prompt_options.recent_commits = summary.recent_commits.clone();
+        let user_prompt = compose_prompt(
+            prompt::build_user_prompt(&branch, &files, &prompt_options),
+            self.vague_retry,
+            self.config.strict(),
+            self.hint.as_deref(),
+        );
+        self.last_summary = Some(summary);
+        // An older generation still waiting on the network must not report in.
+        self.cancel_stream.store(true, Ordering::Relaxed);
+        self.cancel_stream = Arc::new(AtomicBool::new(false));
+        let cancel = Arc::clone(&self.cancel_stream);
 
         self.state = AppState::Generating {
             branch: branch.clone(),
             files: files.clone(),
             generated: String::new(),
             started_at: Instant::now(),
+            last_progress: Instant::now(),
+            revealed: 0,
+            last_reveal: Instant::now(),
         };
 
-        let tx = self.event_tx.clone();
-        thread::spawn(move || {
-            if let Err(e) = cerebras::validate_api_key(&api_key) {
-                let _ = tx.send(AppEvent::GenerationFailed(format!(
-                    "API key validation failed before generation: {}",
-                    e
-                )));
-                return;
+        let provider = Arc::clone(&self.provider);
+        let validation_model = self.config.validation_model().to_string();
+        let job = move |tx: &Sender<AppEvent>| {
+            match provider.validate(&api_key, &validation_model) {
+                Ok(reply) => {
+                    let _ = tx.send(AppEvent::ProviderReply(format!(
+                        "{}: {}",
+                        validation_model, reply
+                    )));
+                }
+                Err(e) => {
+                    return Some(failure_event(e, |msg| {
+                        AppEvent::GenerationFailed(format!(
+                            "API key validation failed before generation: {}",
+                            msg
+                        ))
+                    }));
+                }
             }
-            if let Err(e) = cerebras::check_provider_ready(&api_key, &model) {
-                let _ = tx.send(AppEvent::GenerationFailed(format!(
-                    "Provider readiness check failed: {}",
-                    e
-                )));
-                return;
+
+            // Cancelled while the key was being checked: don't start a request.
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Some(n) = pick {
+                let replies = thread::scope(|scope| {
+                    let handles: Vec<_> = (0..n)
+                        .map(|i| {
+                            let (provider, api_key, models) = (&provider, &api_key, &models);
+                            let (user_prompt, cancel) = (&user_prompt, &cancel);
+                            let temperature = candidate_temperature(temperature, i);
+                            scope.spawn(move || {
+                                let result =
+                                    cerebras::generate_with_fallback(models, cancel, |model| {
+                                        let generation = Generation {
+                                            system_prompt: prompt::system_prompt(gitmoji),
+                                            model,
+                                            user_prompt,
+                                            max_completion_tokens: max_tokens,
+                                            temperature,
+                                            candidates: 1,
+                                        };
+                                        provider.generate_commit_message(
+                                            api_key,
+                                            &generation,
+                                            cancel,
+                                            &|_| {},
+                                        )
+                                    });
+                                let _ = tx.send(AppEvent::CandidateReady);
+                                result.map(|(_, completion)| completion.content)
+                            })
+                        })
+                        .collect();
+                    handles
+                        .into_iter()
+                        .filter_map(|handle| handle.join().ok())
+                        .collect::<Vec<_>>()
+                });
+                if cancel.load(Ordering::Relaxed) {
+                    return None;
+                }
+                let mut first_error = None;
+                let mut raws = Vec::new();
+                for reply in replies {
+                    match reply {
+                        Ok(raw) => raws.push(raw),
+                        Err(e) => {
+                            first_error.get_or_insert(e);
+                        }
+                    }
+                }
+                return Some(match first_error {
+                    Some(e) if raws.is_empty() => failure_event(e, AppEvent::GenerationFailed),
+                    _ => AppEvent::CandidatesComplete(raws),
+                });
             }
 
-            let result = cerebras::generate_commit_message(&api_key, &model, &user_prompt, |c| {
-                let _ = tx.send(AppEvent::GenerationChunk(c.to_string()));
-            });
-            let _ = tx.send(match result {
-                Ok(msg) => AppEvent::GenerationComplete(msg),
-                Err(e) => AppEvent::GenerationFailed(e.to_string()),
+            let result = cerebras::generate_with_fallback(&models, &cancel, |model| {
+                if model != models[0] {
+                    let _ = tx.send(AppEvent::ModelFallback(model.to_string()));
+                }
+                let generation = Generation {
+                    system_prompt: prompt::system_prompt(gitmoji),
+                    model,
+                    user_prompt: &user_prompt,
+                    max_completion_tokens: max_tokens,
+                    temperature,
+                    candidates,
+                };
+                provider.generate_commit_message(&api_key, &generation, &cancel, &|c| {
+                    let _ = tx.send(AppEvent::GenerationChunk(c.to_string()));
+                })
             });
-        });
+            // A stopped stream (edit, hint) must not report into whatever
+            // generation replaced it.
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            Some(match result {
+                Ok((_, completion)) => {
+                    if completion.truncated {
+                        let _ = tx.send(AppEvent::GenerationTruncated);
+                    }
+                    AppEvent::GenerationComplete(completion.content)
+                }
+                Err(e) => failure_event(e, AppEvent::GenerationFailed),
+            })
+        };
+        if !run_detached(&self.event_tx, &self.live_generations, Box::new(job)) {
+            self.fail_with_cleanup(
+                "An earlier request is still closing its connection. Press R to retry in a moment."
+                    .into(),
+                true,
+            );
+        }
     }
 
     fn handle_key(&mut self, code: KeyCode) {
@@ -275,8 +1090,176 @@ impl App {
                 }
             }
             AppState::ApiKeyValidating => {}
+            AppState::SelectFiles {
+                changes,
+                selected,
+                cursor,
+            } => match code {
+                KeyCode::Up | KeyCode::Char('k') => *cursor = cursor.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') if *cursor + 1 < changes.len() => {
+                    *cursor += 1;
+                }
+                KeyCode::Char(' ') => selected[*cursor] = !selected[*cursor],
+                KeyCode::Char('a') | KeyCode::Char('A') => {
+                    let all = selected.iter().all(|s| *s);
+                    selected.fill(!all);
+                }
+                KeyCode::Enter => {
+                    let paths = changes
+                        .iter()
+                        .zip(selected.iter())
+                        .filter(|(_, picked)| **picked)
+                        .map(|(change, _)| change.path.clone())
+                        .collect();
+                    self.start_staging_selected(paths);
+                }
+                _ => {}
+            },
+            AppState::PartialStage { .. } => match code {
+                KeyCode::Char('s') | KeyCode::Char('S') => {
+                    self.state = AppState::Staging {
+                        branch: "unknown".into(),
+                    };
+                    self.start_staging_with(StagePlan::StageRemainder);
+                }
+                KeyCode::Char('c') | KeyCode::Char('C') => {
+                    self.state = AppState::Staging {
+                        branch: "unknown".into(),
+                    };
+                    self.start_staging_with(StagePlan::StagedOnly);
+                }
+                _ => {}
+            },
+            AppState::OfferStage { .. } => {
+                if matches!(code, KeyCode::Char('s') | KeyCode::Char('S')) {
+                    self.stage_mode =
+                        effective_stage_mode(StageMode::All, self.config.recurse_untracked());
+                    self.state = AppState::Staging {
+                        branch: "unknown".into(),
+                    };
+                    self.start_staging();
+                }
+            }
+            state @ (AppState::Generating { .. }
+            | AppState::Committing { .. }
+            | AppState::Done { .. })
+                if matches!(code, KeyCode::Char('d') | KeyCode::Char('D'))
+                    && main_files(state).is_some_and(|files| !files.is_empty()) =>
+            {
+                let back = Box::new(self.take_state());
+                self.state = AppState::DiffView {
+                    file_index: 0,
+                    scroll: 0,
+                    back,
+                };
+            }
+            AppState::DiffView {
+                file_index,
+                scroll,
+                back,
+            } => {
+                let files = main_files(back).unwrap_or_default();
+                navigate_diff(files, file_index, scroll, code);
+            }
+            AppState::Generating { generated, .. }
+                if matches!(code, KeyCode::Char('c') | KeyCode::Char('C')) =>
+            {
+                let text = generated.clone();
+                self.copy_to_clipboard(&text);
+            }
+            AppState::Done { message, .. }
+                if matches!(code, KeyCode::Char('c') | KeyCode::Char('C')) =>
+            {
+                let text = message.clone();
+                self.copy_to_clipboard(&text);
+            }
+            AppState::Generating {
+                branch,
+                files,
+                generated,
+                ..
+            } if matches!(code, KeyCode::Char('e') | KeyCode::Char('E')) => {
+                // Freeze the buffer so the user isn't editing a moving target.
+                self.cancel_stream.store(true, Ordering::Relaxed);
+                let buffer = std::mem::take(generated);
+                self.state = AppState::Editing {
+                    branch: std::mem::take(branch),
+                    files: std::mem::take(files),
+                    cursor: buffer.len(),
+                    buffer,
+                    salvaged: false,
+                };
+            }
+            AppState::Generating { .. }
+                if matches!(code, KeyCode::Char('h') | KeyCode::Char('H')) =>
+            {
+                self.cancel_stream.store(true, Ordering::Relaxed);
+                self.state = AppState::Hinting {
+                    input: String::new(),
+                    cursor: 0,
+                };
+            }
+            // A dry run leaves the message uncommitted, so it can still be redone.
+            AppState::Done { .. }
+                if self.dry_run && matches!(code, KeyCode::Char('h') | KeyCode::Char('H')) =>
+            {
+                self.state = AppState::Hinting {
+                    input: String::new(),
+                    cursor: 0,
+                };
+            }
+            AppState::PickMessage {
+                candidates,
+                selected,
+                ..
+            } => match code {
+                KeyCode::Up | KeyCode::Char('k') => *selected = selected.saturating_sub(1),
+                KeyCode::Down | KeyCode::Char('j') if *selected + 1 < candidates.len() => {
+                    *selected += 1;
+                }
+                KeyCode::Enter => {
+                    if let AppState::PickMessage {
+                        branch,
+                        files,
+                        mut candidates,
+                        selected,
+                    } = self.take_state()
+                    {
+                        let (title, body) = candidates.swap_remove(selected);
+                        self.finish_message(branch, files, title, body);
+                    }
+                }
+                _ => {}
+            },
+            AppState::LargeFiles { summary, .. } => match code {
+                KeyCode::Char('c') | KeyCode::Char('C') => {
+                    let summary = summary.clone();
+                    self.start_generation(summary);
+                }
+                KeyCode::Char('u') | KeyCode::Char('U') => {
+                    self.state = AppState::Error {
+                        message: unstage_after_failure(
+                            "Stopped before committing large files.".to_string(),
+                            std::mem::take(&mut self.stage_snapshot),
+                        ),
+                        retryable: false,
+                    };
+                }
+                _ => {}
+            },
+            AppState::ConfirmUnstage { .. } => {
+                if let Some(unstage) = unstage_confirmed(code) {
+                    self.resolve_unstage(unstage);
+                }
+            }
             AppState::Error { retryable, .. } => match code {
-                KeyCode::Char('r') | KeyCode::Char('R') if *retryable => {
+                KeyCode::Char('r')
+                | KeyCode::Char('R')
+                | KeyCode::Char('g')
+                | KeyCode::Char('G')
+                    if *retryable =>
+                {
+                    self.reuse_message = matches!(code, KeyCode::Char('r') | KeyCode::Char('R'));
                     self.state = AppState::Staging {
                         branch: "unknown".into(),
                     };
@@ -295,23 +1278,108 @@ impl App {
         }
     }
 
-    fn handle_event(&mut self, event: AppEvent) {
+    /// Keys for states with free-form text, where letters like `q` are input.
+    fn handle_text_key(&mut self, key: KeyEvent) {
+        if let AppState::ConfirmModel { input, cursor } = &mut self.state {
+            if key.code != KeyCode::Enter {
+                apply_edit_key(input, cursor, key.code);
+                return;
+            }
+            let picked = first_run_pick(self.config.model(), input);
+            let _ = config::complete_first_run(picked);
+            self.config.finish_first_run(input);
+            self.state = AppState::Staging {
+                branch: "unknown".into(),
+            };
+            self.start_staging();
+            return;
+        }
+
+        if let AppState::Hinting { input, cursor } = &mut self.state {
+            if key.code != KeyCode::Enter {
+                apply_edit_key(input, cursor, key.code);
+                return;
+            }
+            if !input.trim().is_empty() {
+                self.hint = Some(input.trim().to_string());
+            }
+            match self.last_summary.clone() {
+                Some(summary) => self.start_generation(summary),
+                None => {
+                    self.state = AppState::Error {
+                        message: "Nothing to regenerate".into(),
+                        retryable: true,
+                    }
+                }
+            }
+            return;
+        }
+
+        let AppState::Editing {
+            branch,
+            files,
+            buffer,
+            cursor,
+            ..
+        } = &mut self.state
+        else {
+            return;
+        };
+        if key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            let text = buffer.clone();
+            self.copy_to_clipboard(&text);
+            return;
+        }
+        if key.code == KeyCode::Char('s') && key.modifiers.contains(KeyModifiers::CONTROL) {
+            let (title, body) =
+                cerebras::parse_commit_message(buffer, &self.config.message_options());
+            let (branch, files) = (std::mem::take(branch), std::mem::take(files));
+            self.finish_message(branch, files, title, body);
+            return;
+        }
+        apply_edit_key(buffer, cursor, key.code);
+    }
+
+    fn handle_event(&mut self, event: AppEvent) {
+        if matches!(self.state, AppState::DiffView { .. }) {
+            self.handle_event_behind_diff_view(event);
+            return;
+        }
+        if !event_applies(&self.state, &event) {
+            return;
+        }
         match event {
             AppEvent::ApiKeyEntered(key) => {
                 self.api_key = Some(key.clone());
                 self.state = AppState::ApiKeyValidating;
-                let tx = self.event_tx.clone();
-                thread::spawn(move || {
-                    let _ = tx.send(match cerebras::validate_api_key(&key) {
-                        Ok(_) => AppEvent::ApiKeyValidated,
-                        Err(e) => AppEvent::ApiKeyValidationFailed(e.to_string()),
-                    });
-                });
+                let provider = Arc::clone(&self.provider);
+                let model = self.config.validation_model().to_string();
+                self.lane.submit(
+                    &self.event_tx,
+                    Box::new(move |tx| {
+                        Some(match provider.validate(&key, &model) {
+                            Ok(reply) => {
+                                let _ = tx
+                                    .send(AppEvent::ProviderReply(format!("{}: {}", model, reply)));
+                                AppEvent::ApiKeyValidated
+                            }
+                            Err(e) => failure_event(e, AppEvent::ApiKeyValidationFailed),
+                        })
+                    }),
+                );
             }
             AppEvent::ApiKeyValidated => {
                 if let Some(ref key) = self.api_key {
                     let _ = config::save_api_key(key);
                 }
+                if self.config.is_first_run() {
+                    let input = self.config.model().to_string();
+                    self.state = AppState::ConfirmModel {
+                        cursor: input.len(),
+                        input,
+                    };
+                    return;
+                }
                 self.state = AppState::Staging {
                     branch: "unknown".into(),
                 };
@@ -324,86 +1392,242 @@ impl App {
                     error: Some(err),
                 };
             }
-            AppEvent::StagingComplete(summary) => {
+            AppEvent::KeyRejected => {
+                if let Some(key) = self.api_key.take() {
+                    let _ = config::forget_api_key(&key);
+                }
+                self.state = key_reentry_state();
+            }
+            AppEvent::PartialStageDetected(files) => {
+                self.state = AppState::PartialStage { files };
+            }
+            AppEvent::NothingStaged(files) => {
+                if self.stage_prompt && self.interactive {
+                    self.state = AppState::OfferStage { files };
+                } else {
+                    self.fail_with_cleanup(YetiError::NothingStaged(files.len()).to_string(), true);
+                }
+            }
+            AppEvent::ChangesListed(changes) => {
+                if changes.is_empty() {
+                    // Nothing changed at all; staging reports it.
+                    self.start_staging_with(StagePlan::StagedOnly);
+                } else {
+                    self.state = AppState::SelectFiles {
+                        selected: vec![true; changes.len()],
+                        changes,
+                        cursor: 0,
+                    };
+                }
+            }
+            AppEvent::StagingComplete(summary, snapshot) => {
+                self.stage_snapshot.merge(snapshot);
+                self.vague_retry = false;
+                self.refusal_retry = false;
+                self.strict_rejections = 0;
+                if !self.force
+                    && let Err(err) = crate::git::check_line_endings(&summary.files)
+                {
+                    self.fail_with_cleanup(err.to_string(), false);
+                    return;
+                }
+                if !self.force
+                    && let Some(report) = GitRepo::discover()
+                        .and_then(|repo| repo.staged_sizes(&summary.files))
+                        .ok()
+                        .and_then(|sizes| size_report(sizes, self.config.size_limits()))
+                {
+                    self.state = AppState::LargeFiles { report, summary };
+                    return;
+                }
                 self.start_generation(summary);
             }
             AppEvent::StagingFailed(err) => {
                 self.fail_with_cleanup(err, false);
             }
+            AppEvent::NothingToAmend => {
+                self.exit_notice = Some(YetiError::NothingToAmend.to_string());
+            }
+            AppEvent::ProviderReply(line) => {
+                if self.verbose {
+                    self.provider_replies.push(line);
+                }
+            }
+            AppEvent::ModelFallback(model) => {
+                if let AppState::Generating {
+                    generated,
+                    last_progress,
+                    revealed,
+                    ..
+                } = &mut self.state
+                {
+                    generated.clear();
+                    *revealed = 0;
+                    *last_progress = Instant::now();
+                    self.fallback_model = Some(model);
+                }
+            }
             AppEvent::GenerationChunk(chunk) => {
-                if let AppState::Generating { generated, .. } = &mut self.state {
+                if let AppState::Generating {
+                    generated,
+                    last_progress,
+                    ..
+                } = &mut self.state
+                {
                     generated.push_str(&chunk);
+                    *last_progress = Instant::now();
                 }
             }
+            AppEvent::GenerationTruncated => {
+                self.truncated = true;
+            }
             AppEvent::GenerationComplete(raw) => {
-                let (title, body) = cerebras::parse_commit_message(&raw);
-                let message = match &body {
-                    Some(b) => format!("{}\n\n{}", title, b),
-                    None => title.clone(),
+                let AppState::Generating { branch, files, .. } = &self.state else {
+                    return;
                 };
-
-                if self.dry_run {
-                    if let AppState::Generating { branch, files, .. } = &self.state {
-                        self.result = Some(AppResult {
-                            branch: branch.clone(),
-                            files: files.clone(),
-                            message: message.clone(),
-                            dry_run: true,
-                        });
-                        self.state = AppState::Done {
-                            branch: branch.clone(),
-                            files: files.clone(),
-                            message,
-                            done_at: Instant::now(),
-                        };
+                let (branch, files) = (branch.clone(), files.clone());
+                let (title, body) =
+                    cerebras::parse_commit_message(&raw, &self.config.message_options());
+                if cerebras::looks_like_refusal(&raw) {
+                    if !self.refusal_retry
+                        && let Some(summary) = self.last_summary.take()
+                    {
+                        self.refusal_retry = true;
+                        self.start_generation(summary);
+                        return;
                     }
+                    let model = self
+                        .fallback_model
+                        .clone()
+                        .unwrap_or_else(|| self.config.model().to_string());
+                    self.fail_with_cleanup(
+                        refusal_message(&model, &title, self.refusal_retry),
+                        true,
+                    );
                     return;
                 }
-
-                if let AppState::Generating { branch, files, .. } = &self.state {
-                    let branch_clone = branch.clone();
-                    let files_clone = files.clone();
-                    let message_clone = message.clone();
-
-                    self.state = AppState::Committing {
-                        branch: branch.clone(),
-                        files: files.clone(),
-                        message: message.clone(),
-                    };
-
-                    let title_for_commit = title.clone();
-                    let body_for_commit = body.clone();
-                    let tx = self.event_tx.clone();
-                    thread::spawn(move || {
-                        let _ = tx.send(
-                            match crate::git::commit_with_git_cli(
-                                &title_for_commit,
-                                body_for_commit.as_deref(),
-                            ) {
-                                Ok(_) => AppEvent::CommitComplete,
-                                Err(e) => AppEvent::CommitFailed(e.to_string()),
-                            },
+                if self.config.strict() && !prompt::is_strict_conventional_title(&title) {
+                    if self.strict_rejections >= MAX_STRICT_RETRIES {
+                        self.fail_with_cleanup(
+                            format!(
+                                "Strict mode: \"{}\" is not a `type(scope): summary` title after {} regenerations; nothing was committed. Press R to retry.",
+                                title, MAX_STRICT_RETRIES
+                            ),
+                            true,
                         );
-                    });
-
-                    self.result = Some(AppResult {
-                        branch: branch_clone,
-                        files: files_clone,
-                        message: message_clone,
-                        dry_run: false,
-                    });
+                        return;
+                    }
+                    if let Some(summary) = self.last_summary.take() {
+                        self.strict_rejections += 1;
+                        self.start_generation(summary);
+                        return;
+                    }
+                }
+                if self.config.regenerate_vague()
+                    && !self.vague_retry
+                    && prompt::looks_vague(&title, body.as_deref())
+                    && let Some(summary) = self.last_summary.take()
+                {
+                    self.vague_retry = true;
+                    self.start_generation(summary);
+                    return;
+                }
+                self.finish_message(branch, files, title, body);
+            }
+            AppEvent::CandidateReady => {
+                if let AppState::Generating { last_progress, .. } = &mut self.state
+                    && let Some((ready, _)) = self.candidate_progress.as_mut()
+                {
+                    *ready += 1;
+                    *last_progress = Instant::now();
+                }
+            }
+            AppEvent::CandidatesComplete(raws) => {
+                let AppState::Generating { branch, files, .. } = &self.state else {
+                    return;
+                };
+                let (branch, files) = (branch.clone(), files.clone());
+                let total = raws.len();
+                let mut candidates =
+                    usable_candidates(&raws, &self.config.message_options(), self.config.strict());
+                match candidates.len() {
+                    0 => self.fail_with_cleanup(
+                        format!(
+                            "None of the {} generated messages was usable; nothing was committed. Press R to retry.",
+                            total
+                        ),
+                        true,
+                    ),
+                    1 => {
+                        let (title, body) = candidates.remove(0);
+                        self.finish_message(branch, files, title, body);
+                    }
+                    _ => {
+                        self.state = AppState::PickMessage {
+                            branch,
+                            files,
+                            candidates,
+                            selected: 0,
+                        };
+                    }
                 }
             }
             AppEvent::GenerationFailed(err) => {
-                self.fail_with_cleanup(err, true);
+                // A stream abandoned after a timeout may still report back late.
+                if matches!(self.state, AppState::Generating { .. }) {
+                    self.fail_with_cleanup(err, true);
+                }
             }
-            AppEvent::CommitComplete => {
+            AppEvent::CommitComplete(rewritten) => {
                 if let AppState::Committing {
                     branch,
                     files,
                     message,
                 } = &self.state
                 {
+                    let repo = GitRepo::discover().ok();
+                    // Re-stage what `--select` left out; the commit stands
+                    // even if that fails.
+                    if let Some(repo) = &repo {
+                        let _ = repo.restore_stage(self.stage_snapshot.take_held_back());
+                    }
+                    if let Some(result) = self.result.as_mut()
+                        && let Some(time) = repo.as_ref().and_then(|repo| repo.head_commit_time())
+                    {
+                        result.finished_at = time;
+                    }
+                    if let Some(result) = self.result.as_mut() {
+                        result.sha = repo.as_ref().and_then(|repo| repo.head_short_id());
+                        result.rewritten_by_hooks = rewritten;
+                    }
+                    if let (Some(repo), Some(result)) = (&repo, &self.result)
+                        && let Some(path) = history::history_path(
+                            self.config.history_scope(),
+                            &config::config_dir(),
+                            repo.git_dir(),
+                        )
+                    {
+                        let entry = HistoryEntry {
+                            timestamp: result.finished_at.seconds(),
+                            repo: repo.repo_name(),
+                            branch: branch.clone(),
+                            message: message.clone(),
+                        };
+                        // A log that can't be written shouldn't undo a successful commit.
+                        let _ = history::append(&path, &entry);
+                    }
+                    // The commit stands either way; a failed tag is reported, not undone.
+                    if let Some(name) = &self.tag {
+                        let title = message.lines().next().unwrap_or_default();
+                        let tag_message = self.tag_message.as_deref().unwrap_or(title);
+                        if let Err(e) = crate::git::tag_with_git_cli(name, tag_message) {
+                            self.state = AppState::Error {
+                                message: format!("Committed, but tagging {} failed. {}", name, e),
+                                retryable: false,
+                            };
+                            return;
+                        }
+                    }
                     self.state = AppState::Done {
                         branch: branch.clone(),
                         files: files.clone(),
@@ -413,31 +1637,304 @@ impl App {
                 }
             }
             AppEvent::CommitFailed(err) => {
-                self.fail_with_cleanup(err, false);
+                // A hook may pass next time; R retries with the same message.
+                self.fail_with_cleanup(err, true);
             }
         }
     }
 
-    fn fail_with_cleanup(&mut self, message: String, retryable: bool) {
-        let should_unstage = matches!(
-            self.state,
-            AppState::Staging { .. } | AppState::Generating { .. } | AppState::Committing { .. }
+    /// Esc while generating: the stream stops at its next line and its
+    /// worker ends without reporting; the stage is handled like any failure.
+    fn cancel_generation(&mut self) {
+        self.cancel_stream.store(true, Ordering::Relaxed);
+        self.fail_with_cleanup(
+            "Generation cancelled; nothing was committed. Press R to start over.".to_string(),
+            true,
         );
-        let final_message = if should_unstage {
-            match unstage_all_with_git_cli() {
-                Ok(_) => message,
-                Err(e) => format!("{}\nAlso failed to unstage changes: {}", message, e),
+    }
+
+    /// The fallback when the model can't deliver: the user writes the
+    /// message in `$EDITOR` and it is committed over the same stage.
+    fn write_message_by_hand(&mut self, tui: &mut Tui) -> Result<()> {
+        let Some(summary) = self.last_summary.clone() else {
+            return Ok(());
+        };
+        let path = match GitRepo::discover() {
+            Ok(repo) => repo.git_dir().join(editor::MESSAGE_FILE),
+            Err(e) => {
+                self.state = AppState::Error {
+                    message: e.to_string(),
+                    retryable: true,
+                };
+                return Ok(());
+            }
+        };
+        let template = editor::message_template(&summary.branch, &summary.files);
+        match tui.suspend(|| editor::write_message(&path, &template))? {
+            Ok(Some(text)) => {
+                self.offer_manual = false;
+                let (title, body) =
+                    cerebras::parse_commit_message(&text, &self.config.message_options());
+                self.finish_message(summary.branch, summary.files, title, body);
+            }
+            Ok(None) => {
+                self.state = AppState::Error {
+                    message: "Aborting commit due to empty message; staged changes were kept."
+                        .to_string(),
+                    retryable: true,
+                };
+            }
+            Err(e) => {
+                self.state = AppState::Error {
+                    message: e.to_string(),
+                    retryable: true,
+                };
             }
+        }
+        Ok(())
+    }
+
+    /// Puts `text` on the system clipboard and says so in the footer; with
+    /// no clipboard (headless Linux without X or Wayland) only the note shows.
+    fn copy_to_clipboard(&mut self, text: &str) {
+        let text = text.trim();
+        let note = if text.is_empty() {
+            "nothing to copy yet"
         } else {
-            message
+            if self.clipboard.is_none() {
+                self.clipboard = arboard::Clipboard::new().ok();
+            }
+            let copied = self
+                .clipboard
+                .as_mut()
+                .is_some_and(|clipboard| clipboard.set_text(text).is_ok());
+            if copied {
+                "copied"
+            } else {
+                "no clipboard available"
+            }
+        };
+        self.footer_note = Some((note.to_string(), Instant::now()));
+    }
+
+    /// Moves the state out, leaving a placeholder the caller overwrites.
+    fn take_state(&mut self) -> AppState {
+        std::mem::replace(&mut self.state, AppState::ApiKeyValidating)
+    }
+
+    fn close_diff_view(&mut self) {
+        if let AppState::DiffView { back, .. } = self.take_state() {
+            self.state = *back;
+        }
+    }
+
+    /// The screen under the diff keeps running; the diff stays open only
+    /// while there is still a main screen to return to.
+    fn handle_event_behind_diff_view(&mut self, event: AppEvent) {
+        let AppState::DiffView {
+            file_index,
+            scroll,
+            back,
+        } = self.take_state()
+        else {
+            return;
+        };
+        self.state = *back;
+        self.handle_event(event);
+        if let Some(count) = main_files(&self.state).map(<[FileInfo]>::len)
+            && count > 0
+        {
+            let back = Box::new(self.take_state());
+            self.state = AppState::DiffView {
+                file_index: file_index.min(count - 1),
+                scroll,
+                back,
+            };
+        }
+    }
+
+    fn tick_typewriter(&mut self) {
+        if !self.config.typewriter() {
+            return;
+        }
+        if let AppState::Generating {
+            generated,
+            revealed,
+            last_reveal,
+            ..
+        } = &mut self.state
+        {
+            let available = generated.chars().count();
+            *revealed = advance_reveal(*revealed, available, last_reveal.elapsed());
+            *last_reveal = Instant::now();
+        }
+    }
+
+    /// Commits (or, on a dry run, just records) a parsed message.
+    fn finish_message(
+        &mut self,
+        branch: String,
+        files: Vec<FileInfo>,
+        mut title: String,
+        mut body: Option<String>,
+    ) {
+        title = cerebras::override_title_head(
+            &title,
+            self.commit_type.as_deref(),
+            self.scope.as_deref(),
+            self.config.strict(),
+        );
+        if let Some(template) = self.config.title_prefix_template()
+            && let Some(prefix) = cerebras::render_title_prefix(template, &branch)
+            && !title.contains(prefix.trim())
+        {
+            title = cerebras::apply_title_prefix(&title, &prefix);
+        }
+        if let Some(issue) = self.issue_pattern.find(&branch) {
+            body = cerebras::add_refs_footer(body, &issue);
+        }
+        self.start_commit(branch, files, title, body);
+    }
+
+    /// Commits (or records) a finished message, remembering it for a retry.
+    fn start_commit(
+        &mut self,
+        branch: String,
+        files: Vec<FileInfo>,
+        title: String,
+        body: Option<String>,
+    ) {
+        self.cached_message = GitRepo::discover()
+            .and_then(|repo| repo.staged_tree_id())
+            .ok()
+            .map(|stage| (stage, title.clone(), body.clone()));
+        let message = match &body {
+            Some(b) => format!("{}\n\n{}", title, b),
+            None => title.clone(),
+        };
+        let previous = GitRepo::discover()
+            .ok()
+            .and_then(|repo| repo.head_subject());
+        // An amend replaces HEAD, so matching its subject is expected.
+        self.duplicate_subject = !self.amend && is_duplicate_subject(&title, previous.as_deref());
+
+        if self.dry_run {
+            self.result = Some(AppResult {
+                branch: branch.clone(),
+                files: files.clone(),
+                message: message.clone(),
+                dry_run: true,
+                finished_at: local_time_now(),
+                twelve_hour_clock: self.config.twelve_hour_clock(),
+                max_message_lines: self.config.summary_max_lines(),
+                duplicate_subject: self.duplicate_subject,
+                author: GitRepo::discover().ok().map(|repo| repo.identity()),
+                config_warning: self.config_warning.clone(),
+                sha: None,
+                rewritten_by_hooks: Vec::new(),
+            });
+            self.state = AppState::Done {
+                branch,
+                files,
+                message,
+                done_at: Instant::now(),
+            };
+            return;
+        }
+
+        self.result = Some(AppResult {
+            branch: branch.clone(),
+            files: files.clone(),
+            message: message.clone(),
+            dry_run: false,
+            finished_at: local_time_now(),
+            twelve_hour_clock: self.config.twelve_hour_clock(),
+            max_message_lines: self.config.summary_max_lines(),
+            duplicate_subject: self.duplicate_subject,
+            author: None,
+            config_warning: self.config_warning.clone(),
+            sha: None,
+            rewritten_by_hooks: Vec::new(),
+        });
+        let paths: Vec<String> = files.iter().map(|f| f.path.clone()).collect();
+        self.state = AppState::Committing {
+            branch,
+            files,
+            message,
         };
 
-        self.state = AppState::Error {
-            message: final_message,
-            retryable,
+        let commit_options = CommitOptions {
+            no_verify: self.config.skip_hooks(),
+            sign: self.sign.clone(),
+            amend: self.amend,
+        };
+        self.lane.submit(
+            &self.event_tx,
+            Box::new(move |_| {
+                Some(
+                    match crate::git::commit_respecting_hooks(
+                        &title,
+                        body.as_deref(),
+                        &commit_options,
+                        &paths,
+                    ) {
+                        Ok(rewritten) => AppEvent::CommitComplete(rewritten),
+                        Err(e) => AppEvent::CommitFailed(e.to_string()),
+                    },
+                )
+            }),
+        );
+    }
+
+    /// Leaving the key prompt a rejected key sent us to: whatever this run
+    /// staged gets the same cleanup as any other failure.
+    fn abandon_key_reentry(&mut self) {
+        let message = format!(
+            "The provider rejected the API key and no new one was entered (set ${} to skip the prompt); nothing was committed.",
+            self.config.key_env()
+        );
+        self.fail_with_cleanup(message, false);
+    }
+
+    fn fail_with_cleanup(&mut self, message: String, retryable: bool) {
+        let should_unstage = matches!(
+            self.state,
+            AppState::Staging { .. }
+                | AppState::Generating { .. }
+                | AppState::Committing { .. }
+                | AppState::ApiKeyInput { .. }
+                | AppState::LargeFiles { .. }
+                | AppState::Editing { .. }
+        ) && !self.stage_snapshot.is_empty();
+        let action = cleanup_action(should_unstage, self.interactive);
+        self.offer_manual = matches!(self.state, AppState::Generating { .. })
+            && self.last_summary.is_some()
+            && action != CleanupAction::Unstage;
+        self.state = match action {
+            CleanupAction::Keep => AppState::Error { message, retryable },
+            CleanupAction::Ask => AppState::ConfirmUnstage { message, retryable },
+            CleanupAction::Unstage => AppState::Error {
+                message: unstage_after_failure(message, std::mem::take(&mut self.stage_snapshot)),
+                retryable,
+            },
         };
     }
 
+    fn resolve_unstage(&mut self, unstage: bool) {
+        if let AppState::ConfirmUnstage { message, retryable } = &self.state {
+            self.offer_manual &= !unstage;
+            let message = if unstage {
+                unstage_after_failure(message.clone(), std::mem::take(&mut self.stage_snapshot))
+            } else {
+                format!("{}\nStaged changes were kept.", message)
+            };
+            self.state = AppState::Error {
+                message,
+                retryable: *retryable,
+            };
+        }
+    }
+
     fn draw(&self, f: &mut Frame) {
         match &self.state {
             AppState::ApiKeyInput {
@@ -445,7 +1942,14 @@ impl App {
                 cursor,
                 error,
             } => {
-                draw_key_input(f, &self.theme, input, *cursor, error.as_deref());
+                draw_key_input(
+                    f,
+                    &self.theme,
+                    input,
+                    *cursor,
+                    error.as_deref(),
+                    self.provider.endpoint(),
+                );
             }
             AppState::ApiKeyValidating => {
                 draw_status_panel(
@@ -457,6 +1961,22 @@ impl App {
                     "Please wait  ·  Esc/Q exit",
                 );
             }
+            AppState::ConfirmModel { input, cursor } => {
+                let detail = format!(
+                    "Model: {}▏{}\nEndpoint: {}",
+                    &input[..*cursor],
+                    &input[*cursor..],
+                    self.provider.endpoint()
+                );
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " first run ",
+                    "confirm model",
+                    &detail,
+                    "Type to change  ·  Enter continue  ·  Esc exit",
+                );
+            }
             AppState::Staging { branch } => {
                 let detail = format!(
                     "Branch: {}  ·  staging changes and building commit context.",
@@ -471,14 +1991,113 @@ impl App {
                     "Please wait  ·  Esc/Q exit",
                 );
             }
+            AppState::SelectFiles {
+                changes,
+                selected,
+                cursor,
+            } => {
+                let rows: Vec<(String, bool)> = changes
+                    .iter()
+                    .zip(selected.iter())
+                    .map(|(change, picked)| {
+                        let status = match change.status {
+                            FileStatus::Added => "new",
+                            FileStatus::Deleted => "deleted",
+                            FileStatus::Renamed => "renamed",
+                            FileStatus::Modified => "modified",
+                        };
+                        let staged = if change.staged { ", staged" } else { "" };
+                        (format!("{}  ({}{})", change.path, status, staged), *picked)
+                    })
+                    .collect();
+                draw_file_picker(f, &self.theme, &rows, *cursor);
+            }
+            AppState::PartialStage { files } => {
+                let shown: Vec<&str> = files.iter().take(5).map(String::as_str).collect();
+                let more = if files.len() > shown.len() {
+                    format!(" (+{} more)", files.len() - shown.len())
+                } else {
+                    String::new()
+                };
+                let detail = format!(
+                    "Staged files with further unstaged edits: {}{}",
+                    shown.join(", "),
+                    more
+                );
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " partial stage ",
+                    "some files are only partly staged",
+                    &detail,
+                    "S stage remainder  ·  C commit staged only  ·  Esc/Q exit",
+                );
+            }
+            AppState::OfferStage { files } => {
+                let shown: Vec<&str> = files.iter().take(5).map(String::as_str).collect();
+                let more = if files.len() > shown.len() {
+                    format!(" (+{} more)", files.len() - shown.len())
+                } else {
+                    String::new()
+                };
+                let detail = format!("Unstaged changes: {}{}", shown.join(", "), more);
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " nothing staged ",
+                    "changes exist, but none are staged",
+                    &detail,
+                    "S stage all and continue  ·  Esc/Q exit",
+                );
+            }
             AppState::Generating {
                 branch,
                 files,
                 generated,
                 started_at,
+                revealed,
+                ..
             } => {
                 let status = generation_status(*started_at, generated);
-                self.draw_main(f, branch, files, generated, &status);
+                let shown: String = if self.config.typewriter() {
+                    generated.chars().take(*revealed).collect()
+                } else {
+                    generated.clone()
+                };
+                self.draw_main(f, branch, files, &shown, &status);
+            }
+            AppState::PickMessage {
+                candidates,
+                selected,
+                ..
+            } => {
+                draw_message_picker(f, &self.theme, candidates, *selected);
+            }
+            AppState::Hinting { input, cursor } => {
+                let detail = format!("Hint: {}▏{}", &input[..*cursor], &input[*cursor..]);
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " regenerate ",
+                    "steer the next message",
+                    &detail,
+                    "Type a one-line hint  ·  Enter regenerate  ·  Esc exit",
+                );
+            }
+            AppState::Editing {
+                branch,
+                files,
+                buffer,
+                cursor,
+                salvaged,
+            } => {
+                let shown = format!("{}▏{}", &buffer[..*cursor], &buffer[*cursor..]);
+                let status = if *salvaged {
+                    "timed out · review the partial message"
+                } else {
+                    "editing message"
+                };
+                self.draw_main(f, branch, files, &shown, status);
             }
             AppState::Committing {
                 branch,
@@ -500,8 +2119,64 @@ impl App {
                 };
                 self.draw_main(f, branch, files, message, status);
             }
+            AppState::DiffView {
+                file_index,
+                scroll,
+                back,
+            } => {
+                let files = main_files(back).unwrap_or_default();
+                if let Some(file) = files.get(*file_index) {
+                    draw_diff_view(f, &self.theme, file, (*file_index, files.len()), *scroll);
+                }
+            }
+            AppState::LargeFiles { report, .. } => {
+                let mut lines: Vec<String> = report
+                    .oversized
+                    .iter()
+                    .take(5)
+                    .map(|(path, size)| format!("{}  {}", path, format_size(*size)))
+                    .collect();
+                if report.oversized.len() > lines.len() {
+                    lines.push(format!("+{} more", report.oversized.len() - lines.len()));
+                }
+                lines.push(format!(
+                    "Staged total: {}{}",
+                    format_size(report.total),
+                    if report.total_exceeded {
+                        " (over the limit)"
+                    } else {
+                        ""
+                    }
+                ));
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " large files ",
+                    "that's a big one to commit",
+                    &lines.join("\n"),
+                    "C commit anyway  ·  U unstage and stop  ·  Esc/Q exit",
+                );
+            }
+            AppState::ConfirmUnstage { message, .. } => {
+                let detail = format!("{}\n\nUnstage the changes yeti staged?", message);
+                draw_status_panel(
+                    f,
+                    &self.theme,
+                    " operation failed ",
+                    "generation failed; unstage changes? [y/N]",
+                    &detail,
+                    "Y unstage  ·  N/Enter keep stage  ·  Esc/Q exit",
+                );
+            }
             AppState::Error { message, retryable } => {
-                draw_error(f, &self.theme, message, *retryable);
+                draw_error(
+                    f,
+                    &self.theme,
+                    message,
+                    *retryable,
+                    self.offer_manual,
+                    self.cached_message.is_some(),
+                );
             }
         }
     }
@@ -522,6 +2197,20 @@ impl App {
         } else {
             self.theme.accent_style()
         };
+        // Emphasize whichever side dominates so lopsided changes read at a glance.
+        let (add_style, del_style) = if total_add > total_del {
+            (
+                self.theme.green_style().add_modifier(Modifier::BOLD),
+                self.theme.red_style(),
+            )
+        } else if total_del > total_add {
+            (
+                self.theme.green_style(),
+                self.theme.red_style().add_modifier(Modifier::BOLD),
+            )
+        } else {
+            (self.theme.green_style(), self.theme.red_style())
+        };
 
         let [header_area, body_area, footer_area] = Layout::vertical([
             Constraint::Length(3),
@@ -529,9 +2218,12 @@ impl App {
             Constraint::Length(3),
         ])
         .areas(f.area());
-        let [files_area, msg_area] =
-            Layout::horizontal([Constraint::Percentage(46), Constraint::Percentage(54)])
-                .areas(body_area);
+        let (files_percent, message_percent) = self.config.pane_split();
+        let [files_area, msg_area] = Layout::horizontal([
+            Constraint::Percentage(files_percent),
+            Constraint::Percentage(message_percent),
+        ])
+        .areas(body_area);
 
         let header_block = Block::bordered()
             .border_type(BorderType::Rounded)
@@ -539,20 +2231,57 @@ impl App {
             .padding(Padding::horizontal(1));
         let header_inner = header_block.inner(header_area);
         f.render_widget(header_block, header_area);
-        let header_line = Line::from(vec![
+        let mut header_spans = vec![
             Span::styled(format!("{} yeti", MASCOT_MINI), self.theme.accent_style()),
             Span::styled("   ", self.theme.dim_style()),
             Span::styled(branch, self.theme.fg_style()),
             Span::styled("   ", self.theme.fg_style()),
             Span::styled(format!("{} files", files.len()), self.theme.dim_style()),
             Span::styled("  ", self.theme.dim_style()),
-            Span::styled(format!("+{}", total_add), self.theme.green_style()),
+            Span::styled(format!("+{}", format_count(total_add)), add_style),
             Span::styled(" ", self.theme.dim_style()),
-            Span::styled(format!("-{}", total_del), self.theme.red_style()),
-            Span::styled("   ", self.theme.dim_style()),
-            Span::styled(status, status_style),
-        ]);
-        f.render_widget(Paragraph::new(header_line), header_inner);
+            Span::styled(format!("-{}", format_count(total_del)), del_style),
+        ];
+        if self.config.prompt_options().composition && !files.is_empty() {
+            header_spans.push(Span::styled(
+                format!("  {}", prompt::composition(files)),
+                self.theme.dim_style(),
+            ));
+        }
+        header_spans.push(Span::styled("   ", self.theme.dim_style()));
+        header_spans.push(Span::styled(status, status_style));
+        if self.truncated {
+            header_spans.push(Span::styled(
+                "   cut at token limit",
+                self.theme.yellow_style(),
+            ));
+        }
+        if self.config_warning.is_some() {
+            header_spans.push(Span::styled(
+                "   bad config, using defaults",
+                self.theme.yellow_style(),
+            ));
+        }
+        if self.duplicate_subject {
+            header_spans.push(Span::styled(
+                "   same subject as HEAD",
+                self.theme.yellow_style(),
+            ));
+        }
+        let ignored = files.iter().filter(|f| f.ignored).count();
+        if ignored > 0 {
+            header_spans.push(Span::styled(
+                format!("   {} ignored", ignored),
+                self.theme.yellow_style(),
+            ));
+        }
+        if let Some(model) = &self.fallback_model {
+            header_spans.push(Span::styled(
+                format!("   via {}", model),
+                self.theme.dim_style(),
+            ));
+        }
+        f.render_widget(Paragraph::new(Line::from(header_spans)), header_inner);
 
         let files_block = Block::bordered()
             .title(Span::styled(" changes ", self.theme.dim_style()))
@@ -574,20 +2303,37 @@ impl App {
         ])];
 
         for file in files.iter().take(10) {
-            let (status_tag, status_style) = match file.status {
-                crate::prompt::FileStatus::Added => ("A", self.theme.green_style()),
-                crate::prompt::FileStatus::Deleted => ("D", self.theme.red_style()),
-                crate::prompt::FileStatus::Renamed => ("R", self.theme.accent_style()),
-                crate::prompt::FileStatus::Modified => ("M", self.theme.yellow_style()),
+            let status_style = match file.status {
+                _ if file.ignored => self.theme.yellow_style(),
+                crate::prompt::FileStatus::Added => self.theme.green_style(),
+                crate::prompt::FileStatus::Deleted => self.theme.red_style(),
+                crate::prompt::FileStatus::Renamed => self.theme.accent_style(),
+                crate::prompt::FileStatus::Modified => self.theme.yellow_style(),
             };
-            let path_display = ellipsize_path(&file.path, path_width);
+            if let Some(label) = file.zero_change_label() {
+                let width = path_width.saturating_sub(label.len().saturating_sub(7));
+                file_lines.push(Line::from(vec![
+                    Span::styled(format!("{:<2} ", file.status_tag()), status_style),
+                    Span::styled(
+                        format!(
+                            "{:<width$}",
+                            ellipsize_path(&file.annotated_path(), width),
+                            width = width
+                        ),
+                        self.theme.fg_style(),
+                    ),
+                    Span::styled(format!("{:>7}", label), self.theme.dim_style()),
+                ]));
+                continue;
+            }
+            let path_display = ellipsize_path(&file.annotated_path(), path_width);
             let add_text = if file.additions > 0 {
-                format!("+{}", file.additions)
+                format!("+{}", format_count(file.additions))
             } else {
                 "-".to_string()
             };
             let del_text = if file.deletions > 0 {
-                format!("-{}", file.deletions)
+                format!("-{}", format_count(file.deletions))
             } else {
                 "-".to_string()
             };
@@ -603,7 +2349,7 @@ impl App {
             };
 
             file_lines.push(Line::from(vec![
-                Span::styled(format!("{:<2} ", status_tag), status_style),
+                Span::styled(format!("{:<2} ", file.status_tag()), status_style),
                 Span::styled(
                     format!("{:<width$}", path_display, width = path_width),
                     self.theme.fg_style(),
@@ -634,7 +2380,8 @@ impl App {
 
         let mut msg_lines = Vec::new();
         let mut first = true;
-        for line in message.lines().take(12) {
+        let (shown, truncated) = message_preview(message, msg_inner.width, msg_inner.height);
+        for line in message.lines().take(shown) {
             if first {
                 msg_lines.push(Line::from(vec![Span::styled(
                     line,
@@ -647,17 +2394,39 @@ impl App {
                 msg_lines.push(Line::from(vec![Span::styled(line, self.theme.fg_style())]));
             }
         }
-        if msg_lines.is_empty() {
+        if truncated {
             msg_lines.push(Line::from(Span::styled(
-                "waiting for generated message...",
+                "(body truncated in preview)",
                 self.theme.dim_style(),
             )));
         }
+        if msg_lines.is_empty() {
+            let waiting = match self.candidate_progress {
+                Some((ready, total)) => format!("writing {} messages... {} ready", total, ready),
+                None => "waiting for generated message...".to_string(),
+            };
+            msg_lines.push(Line::from(Span::styled(waiting, self.theme.dim_style())));
+        }
         f.render_widget(
             Paragraph::new(msg_lines).wrap(Wrap { trim: false }),
             msg_inner,
         );
 
+        let footer_hint = match self.state {
+            AppState::Generating { .. } => {
+                "E edit  ·  H regenerate with hint  ·  C copy  ·  D diff  ·  Esc cancel  ·  Q exit"
+            }
+            AppState::Editing { .. } => "Ctrl+S commit  ·  Ctrl+C copy  ·  Esc exit",
+            AppState::Done { .. } if self.dry_run => {
+                "H regenerate with hint  ·  C copy  ·  D diff  ·  Esc/Q exit"
+            }
+            AppState::Done { .. } => "C copy  ·  D diff  ·  Esc/Q exit",
+            _ => "D diff  ·  Esc/Q exit",
+        };
+        let footer_hint = match &self.footer_note {
+            Some((note, at)) if at.elapsed().as_secs() < FOOTER_NOTE_SECS => note.as_str(),
+            _ => footer_hint,
+        };
         let footer_block = Block::bordered()
             .border_type(BorderType::Rounded)
             .border_style(status_style)
@@ -669,12 +2438,108 @@ impl App {
             Span::styled("  |  ", self.theme.dim_style()),
             Span::styled(status, status_style),
             Span::styled("  |  ", self.theme.dim_style()),
-            Span::styled("Esc/Q exit", self.theme.dim_style()),
+            Span::styled(footer_hint, self.theme.dim_style()),
         ]);
         f.render_widget(Paragraph::new(footer_line), footer_inner);
     }
 }
 
+/// Basic single-buffer editing; `cursor` is a byte offset kept on char boundaries.
+fn apply_edit_key(buffer: &mut String, cursor: &mut usize, code: KeyCode) {
+    let prev_boundary = |cursor: usize| {
+        buffer[..cursor]
+            .chars()
+            .next_back()
+            .map(|c| cursor - c.len_utf8())
+    };
+    match code {
+        KeyCode::Char(c) => {
+            buffer.insert(*cursor, c);
+            *cursor += c.len_utf8();
+        }
+        KeyCode::Enter => {
+            buffer.insert(*cursor, '\n');
+            *cursor += 1;
+        }
+        KeyCode::Backspace => {
+            if let Some(prev) = prev_boundary(*cursor) {
+                buffer.remove(prev);
+                *cursor = prev;
+            }
+        }
+        KeyCode::Delete if *cursor < buffer.len() => {
+            buffer.remove(*cursor);
+        }
+        KeyCode::Left => {
+            if let Some(prev) = prev_boundary(*cursor) {
+                *cursor = prev;
+            }
+        }
+        KeyCode::Right => {
+            if let Some(c) = buffer[*cursor..].chars().next() {
+                *cursor += c.len_utf8();
+            }
+        }
+        KeyCode::Home => *cursor = buffer[..*cursor].rfind('\n').map_or(0, |i| i + 1),
+        KeyCode::End => {
+            *cursor += buffer[*cursor..]
+                .find('\n')
+                .unwrap_or(buffer.len() - *cursor);
+        }
+        _ => {}
+    }
+}
+
+/// How many message lines the preview pane shows, and whether the rest is
+/// hidden; a hidden tail keeps the last row for saying so. Only the preview
+/// is cut, the whole message is still committed.
+fn message_preview(message: &str, width: u16, height: u16) -> (usize, bool) {
+    let rows = |line: &&str| line.width().max(1).div_ceil(usize::from(width.max(1)));
+    let lines: Vec<&str> = message.lines().collect();
+    let height = usize::from(height);
+    if lines.len() <= MESSAGE_PREVIEW_LINES && lines.iter().map(rows).sum::<usize>() <= height {
+        return (lines.len(), false);
+    }
+    let budget = height.saturating_sub(1);
+    let mut used = 0;
+    let shown = lines
+        .iter()
+        .take(MESSAGE_PREVIEW_LINES)
+        .take_while(|line| {
+            used += rows(line);
+            used <= budget
+        })
+        .count();
+    (shown, true)
+}
+
+/// Reveals received text at a steady rate regardless of how it was chunked,
+/// speeding up when far behind so the preview never lags the stream for long.
+fn advance_reveal(revealed: usize, available: usize, elapsed: Duration) -> usize {
+    if revealed >= available {
+        return available;
+    }
+    let steady = (elapsed.as_secs_f64() * TYPEWRITER_CHARS_PER_SEC) as usize;
+    let catch_up = (available - revealed) / 20;
+    let step = steady.max(catch_up).max(1);
+    (revealed + step).min(available)
+}
+
+/// Unstages only what yeti staged, leaving the user's own staged work in place.
+fn unstage_after_failure(message: String, snapshot: StageSnapshot) -> String {
+    match GitRepo::discover().and_then(|repo| repo.restore_stage(snapshot)) {
+        Ok(_) => message,
+        Err(e) => format!("{}\nAlso failed to unstage changes: {}", message, e),
+    }
+}
+
+/// The no-chunk limit counts only time since the last received chunk, so a slow
+/// but steady stream (or one resumed after the terminal was backgrounded) survives.
+fn generation_timed_out(since_start: Duration, since_progress: Duration) -> bool {
+    since_progress >= Duration::from_secs(NO_CHUNK_TIMEOUT_SECS)
+        || since_start >= Duration::from_secs(MAX_GENERATION_TIMEOUT_SECS)
+}
+
 fn generation_status(started_at: Instant, generated: &str) -> String {
     const FRAMES: [&str; 8] = ["⠋", "⠙", "⠚", "⠞", "⠖", "⠦", "⠴", "⠸"];
     let elapsed = started_at.elapsed();
@@ -713,3 +2578,753 @@ fn ellipsize_path(path: &str, max_chars: usize) -> String {
     tail.reverse();
     format!("...{}", tail.into_iter().collect::<String>())
 }
+
+/// The user prompt plus the retry nudge, strict-format rule and steering
+/// hint that apply to this attempt.
+fn compose_prompt(
+    mut user_prompt: String,
+    vague_retry: bool,
+    strict: bool,
+    hint: Option<&str>,
+) -> String {
+    if vague_retry {
+        user_prompt.push_str("\n\n");
+        user_prompt.push_str(prompt::SPECIFICITY_NUDGE);
+    }
+    if strict {
+        user_prompt.push_str("\n\n");
+        user_prompt.push_str(prompt::STRICT_FORMAT_RULE);
+    }
+    if let Some(hint) = hint {
+        user_prompt.push_str("\n\n");
+        user_prompt.push_str(&prompt::steering_hint(hint));
+    }
+    user_prompt
+}
+
+/// Routes a rejected key to re-entry no matter which call hit the 401;
+/// anything else becomes the caller's own failure event.
+fn failure_event(err: YetiError, otherwise: impl FnOnce(String) -> AppEvent) -> AppEvent {
+    if cerebras::is_key_rejected(&err) {
+        AppEvent::KeyRejected
+    } else {
+        otherwise(err.to_string())
+    }
+}
+
+/// The model typed on the first-run screen, or `None` when the one it was
+/// pre-filled with was accepted as is.
+fn first_run_pick<'a>(offered: &str, input: &'a str) -> Option<&'a str> {
+    let input = input.trim();
+    (!input.is_empty() && input != offered).then_some(input)
+}
+
+fn key_reentry_state() -> AppState {
+    AppState::ApiKeyInput {
+        input: String::new(),
+        cursor: 0,
+        error: Some(KEY_REJECTED.to_string()),
+    }
+}
+
+/// Scrolls the diff view or, with Tab/Shift+Tab, moves to the next or
+/// previous file from its top; scrolling stops at the last line.
+fn navigate_diff(files: &[FileInfo], file_index: &mut usize, scroll: &mut u16, code: KeyCode) {
+    let count = files.len().max(1);
+    match code {
+        KeyCode::Tab => (*file_index, *scroll) = ((*file_index + 1) % count, 0),
+        KeyCode::BackTab => (*file_index, *scroll) = ((*file_index + count - 1) % count, 0),
+        KeyCode::Down | KeyCode::Char('j') => *scroll = scroll.saturating_add(1),
+        KeyCode::Up | KeyCode::Char('k') => *scroll = scroll.saturating_sub(1),
+        KeyCode::PageDown | KeyCode::Char(' ') => *scroll = scroll.saturating_add(DIFF_PAGE_LINES),
+        KeyCode::PageUp => *scroll = scroll.saturating_sub(DIFF_PAGE_LINES),
+        _ => {}
+    }
+    let lines = files
+        .get(*file_index)
+        .map_or(0, |file| file.diff.lines().count());
+    *scroll = (*scroll).min(u16::try_from(lines.saturating_sub(1)).unwrap_or(u16::MAX));
+}
+
+/// The cached message, if it was written for exactly the content staged now.
+fn reusable_message(
+    cached: Option<&CachedMessage>,
+    stage: Option<git2::Oid>,
+) -> Option<(String, Option<String>)> {
+    let (cached_stage, title, body) = cached?;
+    (Some(*cached_stage) == stage).then(|| (title.clone(), body.clone()))
+}
+
+/// Why a refused generation was given up on; "twice" only once it was retried.
+fn refusal_message(model: &str, title: &str, retried: bool) -> String {
+    format!(
+        "{} answered \"{}\" instead of a commit message{}; nothing was committed. Try a different `model`, or press R to retry.",
+        model,
+        title,
+        if retried { ", twice" } else { "" }
+    )
+}
+
+/// The temperature the `i`th `--pick` generation samples at.
+fn candidate_temperature(base: f32, i: u8) -> f32 {
+    (base + CANDIDATE_TEMPERATURE_STEP * f32::from(i)).min(cerebras::MAX_TEMPERATURE)
+}
+
+/// Parsed `--pick` replies worth offering: refusals, and in strict
+/// mode non-standard titles, are dropped, and so are repeats.
+fn usable_candidates(
+    raws: &[String],
+    options: &cerebras::MessageOptions,
+    strict: bool,
+) -> Vec<(String, Option<String>)> {
+    let mut candidates: Vec<(String, Option<String>)> = Vec::new();
+    for raw in raws {
+        if cerebras::looks_like_refusal(raw) {
+            continue;
+        }
+        let candidate = cerebras::parse_commit_message(raw, options);
+        if (strict && !prompt::is_strict_conventional_title(&candidate.0))
+            || candidates.contains(&candidate)
+        {
+            continue;
+        }
+        candidates.push(candidate);
+    }
+    candidates
+}
+
+/// The files behind a main-screen state, the ones `d` can show diffs of.
+fn main_files(state: &AppState) -> Option<&[FileInfo]> {
+    match state {
+        AppState::Generating { files, .. }
+        | AppState::Editing { files, .. }
+        | AppState::Committing { files, .. }
+        | AppState::Done { files, .. } => Some(files),
+        _ => None,
+    }
+}
+
+/// Whether `event` still fits `state`. Events queued behind the one that
+/// moved the state on (a double Enter on the key screen, a stream cancelled
+/// for editing that reports back late) are dropped instead of starting a
+/// second validation or overwriting the message.
+fn event_applies(state: &AppState, event: &AppEvent) -> bool {
+    match event {
+        AppEvent::ApiKeyEntered(_) => matches!(state, AppState::ApiKeyInput { .. }),
+        AppEvent::ApiKeyValidated | AppEvent::ApiKeyValidationFailed(_) => {
+            matches!(state, AppState::ApiKeyValidating)
+        }
+        AppEvent::KeyRejected => {
+            matches!(
+                state,
+                AppState::ApiKeyValidating | AppState::Generating { .. }
+            )
+        }
+        AppEvent::ChangesListed(_) => matches!(state, AppState::Staging { .. }),
+        AppEvent::GenerationComplete(_)
+        | AppEvent::CandidatesComplete(_)
+        | AppEvent::GenerationFailed(_) => {
+            matches!(state, AppState::Generating { .. })
+        }
+        _ => true,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        AppEvent, AppState, CleanupAction, KEY_REJECTED, MAX_GENERATION_TIMEOUT_SECS,
+        MAX_LIVE_GENERATIONS, NO_CHUNK_TIMEOUT_SECS, OpLane, StagePlan, advance_reveal,
+        apply_edit_key, candidate_temperature, cleanup_action, compose_prompt,
+        effective_stage_mode, event_applies, failure_event, first_run_pick, format_size,
+        generation_timed_out, key_reentry_state, main_files, message_preview, navigate_diff,
+        refusal_message, reusable_message, size_report, stage_and_summarize, unstage_confirmed,
+        usable_candidates,
+    };
+    use crate::args::Args;
+    use crate::cerebras;
+    use crate::config::{Config, SizeLimits};
+    use crate::error::{Result, YetiError};
+    use crate::git::StageMode;
+    use crate::git::test_support::{
+        create_temp_repo_dir, git_repo, init_repo_with_initial_commit, write_file,
+    };
+    use crate::prompt::{FileInfo, FileStatus};
+    use clap::Parser;
+    use crossterm::event::KeyCode;
+    use std::fs;
+    use std::path::Path;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+    use std::time::{Duration, Instant};
+
+    #[test]
+    fn triggers_while_busy_wait_and_only_the_newest_runs() {
+        let lane = OpLane::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let running = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+        let job = |name: &'static str, gate: Option<std::sync::mpsc::Receiver<()>>| {
+            let running = std::sync::Arc::clone(&running);
+            Box::new(move |_: &std::sync::mpsc::Sender<AppEvent>| {
+                let overlapping = running.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                if let Some(gate) = gate {
+                    let _ = gate.recv();
+                }
+                running.fetch_sub(1, std::sync::atomic::Ordering::SeqCst);
+                Some(AppEvent::StagingFailed(format!(
+                    "{name} overlapped={overlapping}"
+                )))
+            }) as super::Job
+        };
+
+        lane.submit(&tx, job("first", Some(gate)));
+        lane.submit(&tx, job("second", None));
+        lane.submit(&tx, job("third", None));
+        release.send(()).unwrap();
+
+        let timeout = Duration::from_secs(5);
+        let names: Vec<String> = (0..2)
+            .map(|_| match rx.recv_timeout(timeout).unwrap() {
+                AppEvent::StagingFailed(name) => name,
+                other => panic!("unexpected event {:?}", other),
+            })
+            .collect();
+        assert_eq!(names, ["first overlapped=0", "third overlapped=0"]);
+        assert!(rx.recv_timeout(Duration::from_millis(100)).is_err());
+
+        lane.submit(&tx, job("after idle", None));
+        assert!(matches!(
+            rx.recv_timeout(timeout).unwrap(),
+            AppEvent::StagingFailed(name) if name == "after idle overlapped=0"
+        ));
+    }
+
+    #[test]
+    fn a_stuck_generation_does_not_hold_up_the_lane() {
+        let lane = OpLane::default();
+        let (tx, rx) = std::sync::mpsc::channel();
+        let (release, gate) = std::sync::mpsc::channel::<()>();
+        let gate = Arc::new(Mutex::new(gate));
+        let live = Arc::new(AtomicUsize::new(0));
+        let stuck = || -> super::Job {
+            let gate = Arc::clone(&gate);
+            Box::new(move |_: &std::sync::mpsc::Sender<AppEvent>| {
+                let _ = gate.lock().unwrap().recv();
+                None
+            })
+        };
+        assert!(super::run_detached(&tx, &live, stuck()));
+        lane.submit(
+            &tx,
+            Box::new(|_: &std::sync::mpsc::Sender<AppEvent>| {
+                Some(AppEvent::StagingFailed("staged".to_string()))
+            }),
+        );
+        assert!(matches!(
+            rx.recv_timeout(Duration::from_secs(5)).unwrap(),
+            AppEvent::StagingFailed(name) if name == "staged"
+        ));
+
+        // Stale generations are capped until one of them returns.
+        assert!(super::run_detached(&tx, &live, stuck()));
+        assert!(!super::run_detached(&tx, &live, stuck()));
+        release.send(()).unwrap();
+        let deadline = Instant::now() + Duration::from_secs(5);
+        while live.load(Ordering::SeqCst) == MAX_LIVE_GENERATIONS && Instant::now() < deadline {
+            thread::sleep(Duration::from_millis(10));
+        }
+        assert!(super::run_detached(&tx, &live, stuck()));
+        release.send(()).unwrap();
+        release.send(()).unwrap();
+    }
+
+    #[test]
+    fn steering_hint_is_appended_to_the_rebuilt_prompt() {
+        let base = || "Branch: main\n- src/auth.rs (modified: +4/-1)".to_string();
+
+        let steered = compose_prompt(base(), false, true, Some("emphasize the security fix "));
+        assert!(steered.starts_with(&base()));
+        assert!(steered.ends_with(
+            "The author reviewed a previous attempt and asks for this: emphasize the security fix"
+        ));
+        assert!(
+            steered.find(crate::prompt::STRICT_FORMAT_RULE) < steered.find("emphasize"),
+            "the hint should come last so it takes precedence"
+        );
+        assert_eq!(compose_prompt(base(), false, false, None), base());
+    }
+
+    #[test]
+    fn rejected_key_at_validation_or_stream_returns_to_key_entry() {
+        let at_validation = failure_event(
+            YetiError::InvalidApiKey("Invalid API key".to_string()),
+            AppEvent::ApiKeyValidationFailed,
+        );
+        assert!(matches!(at_validation, AppEvent::KeyRejected));
+        assert!(event_applies(&AppState::ApiKeyValidating, &at_validation));
+
+        let mid_stream = failure_event(
+            YetiError::ApiError {
+                status: 401,
+                message: "token revoked".to_string(),
+            },
+            AppEvent::GenerationFailed,
+        );
+        assert!(matches!(mid_stream, AppEvent::KeyRejected));
+        let generating = AppState::Generating {
+            branch: "main".to_string(),
+            files: Vec::new(),
+            generated: String::new(),
+            started_at: std::time::Instant::now(),
+            last_progress: std::time::Instant::now(),
+            revealed: 0,
+            last_reveal: std::time::Instant::now(),
+        };
+        assert!(event_applies(&generating, &mid_stream));
+
+        let AppState::ApiKeyInput { error, .. } = key_reentry_state() else {
+            panic!("a rejected key should land on key entry");
+        };
+        assert_eq!(error.as_deref(), Some(KEY_REJECTED));
+
+        let overloaded = failure_event(
+            YetiError::ApiError {
+                status: 503,
+                message: "busy".to_string(),
+            },
+            AppEvent::GenerationFailed,
+        );
+        assert!(matches!(overloaded, AppEvent::GenerationFailed(_)));
+    }
+
+    #[test]
+    fn refusals_say_twice_only_after_a_retry() {
+        let once = refusal_message("gpt-oss-120b", "I'm sorry", false);
+        assert!(
+            once.starts_with("gpt-oss-120b answered \"I'm sorry\" instead of a commit message;")
+        );
+        let retried = refusal_message("gpt-oss-120b", "I'm sorry", true);
+        assert!(
+            retried.contains("instead of a commit message, twice;"),
+            "{retried}"
+        );
+    }
+
+    #[test]
+    fn staging_everything_respects_recurse_untracked() {
+        assert_eq!(
+            effective_stage_mode(StageMode::All, false),
+            StageMode::AllTopLevel
+        );
+        assert_eq!(effective_stage_mode(StageMode::All, true), StageMode::All);
+        assert_eq!(
+            effective_stage_mode(StageMode::Tracked, false),
+            StageMode::Tracked
+        );
+    }
+
+    #[test]
+    fn first_run_pins_only_a_model_the_user_typed() {
+        // Pre-filled from `.yeti.toml` or a profile and accepted unchanged.
+        assert_eq!(first_run_pick("qwen-3-32b", "qwen-3-32b"), None);
+        assert_eq!(first_run_pick("qwen-3-32b", " qwen-3-32b "), None);
+        assert_eq!(first_run_pick("qwen-3-32b", ""), None);
+        assert_eq!(
+            first_run_pick("qwen-3-32b", "gpt-oss-120b"),
+            Some("gpt-oss-120b")
+        );
+    }
+
+    #[test]
+    fn amend_without_new_changes_ends_before_generation() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("amend-clean");
+        let repo = git_repo(init_repo_with_initial_commit(&temp_dir)?);
+
+        for mode in [StageMode::StagedOnly, StageMode::All] {
+            let event = stage_and_summarize(&repo, mode, StagePlan::Auto, false, true, None, 0)?;
+            assert!(matches!(event, AppEvent::NothingToAmend), "{mode:?}");
+        }
+
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        let event =
+            stage_and_summarize(&repo, StageMode::All, StagePlan::Auto, false, true, None, 0)?;
+        let AppEvent::StagingComplete(summary, _) = event else {
+            panic!("expected a summary once something new is staged");
+        };
+        assert_eq!(summary.files.len(), 1);
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn staged_only_with_unstaged_changes_says_so() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("nothing-staged");
+        let repo = git_repo(init_repo_with_initial_commit(&temp_dir)?);
+
+        let clean = stage_and_summarize(
+            &repo,
+            StageMode::StagedOnly,
+            StagePlan::Auto,
+            false,
+            false,
+            None,
+            0,
+        );
+        assert!(matches!(clean, Err(YetiError::NoChangesToCommit)));
+
+        write_file(&temp_dir.join("src/file.txt"), "edited\n")?;
+        let event = stage_and_summarize(
+            &repo,
+            StageMode::StagedOnly,
+            StagePlan::Auto,
+            false,
+            false,
+            None,
+            0,
+        )?;
+        let AppEvent::NothingStaged(files) = event else {
+            panic!("unstaged edits should not read as no changes");
+        };
+        assert_eq!(files, ["src/file.txt"]);
+        assert_eq!(
+            YetiError::NothingStaged(files.len()).to_string(),
+            "1 changed file in the work tree, but nothing is staged; run without --staged-only or stage files first"
+        );
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn retry_reuses_the_final_edited_message_only_for_the_same_stage() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("reuse-message");
+        let repo = git_repo(init_repo_with_initial_commit(&temp_dir)?);
+        write_file(&temp_dir.join("src/file.txt"), "edited\n")?;
+
+        let snapshot = repo.stage(StageMode::All)?;
+        // Edited by hand (E, then Ctrl+S) before the commit failed.
+        let edited = (
+            "fix: keep edits".to_string(),
+            Some("Written by hand, not by the model.\n\nRefs: ABC-12".to_string()),
+        );
+        let cached = (repo.staged_tree_id()?, edited.0.clone(), edited.1.clone());
+        // Unstaged after the failed commit, then staged again by the retry.
+        repo.restore_stage(snapshot)?;
+        repo.stage(StageMode::All)?;
+        assert_eq!(
+            reusable_message(Some(&cached), Some(repo.staged_tree_id()?)),
+            Some(edited)
+        );
+
+        write_file(&temp_dir.join("src/file.txt"), "edited again\n")?;
+        repo.stage(StageMode::All)?;
+        assert_eq!(
+            reusable_message(Some(&cached), Some(repo.staged_tree_id()?)),
+            None
+        );
+        assert_eq!(reusable_message(None, Some(cached.0)), None);
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn amend_in_an_empty_repo_fails_before_staging() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("amend-empty");
+        let repo = git2::Repository::init(&temp_dir)?;
+        write_file(&temp_dir.join("README.md"), "hello\n")?;
+
+        let result = stage_and_summarize(
+            &git_repo(repo),
+            StageMode::All,
+            StagePlan::Auto,
+            false,
+            true,
+            None,
+            0,
+        );
+        assert!(matches!(result, Err(YetiError::NoCommitToAmend)));
+        let index = git2::Repository::open(&temp_dir)?.index()?;
+        assert!(index.is_empty());
+
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn failing_stage_hook_aborts_before_anything_is_staged() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("stage-hook");
+        let repo = git_repo(init_repo_with_initial_commit(&temp_dir)?);
+        write_file(&temp_dir.join("src/new.rs"), "fn main(){}\n")?;
+
+        let failed = stage_and_summarize(
+            &repo,
+            StageMode::All,
+            StagePlan::Auto,
+            false,
+            false,
+            Some("echo 'src/new.rs: parse error' >&2; exit 2"),
+            0,
+        );
+        let Err(YetiError::StageHookFailed { output, .. }) = failed else {
+            panic!("a failing hook should abort staging");
+        };
+        assert_eq!(output, "src/new.rs: parse error");
+        let index = git2::Repository::open(&temp_dir)?.index()?;
+        assert!(index.get_path(Path::new("src/new.rs"), 0).is_none());
+
+        let event = stage_and_summarize(
+            &repo,
+            StageMode::All,
+            StagePlan::Auto,
+            false,
+            false,
+            Some("echo 'fn main() {}' > src/new.rs"),
+            0,
+        )?;
+        let AppEvent::StagingComplete(summary, _) = event else {
+            panic!("a passing hook should go on to stage");
+        };
+        assert!(summary.files[0].diff.contains("+fn main() {}"));
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn large_staged_file_trips_the_size_check() -> Result<()> {
+        let temp_dir = create_temp_repo_dir("large-file");
+        let repo = init_repo_with_initial_commit(&temp_dir)?;
+        fs::write(temp_dir.join("dump.bin"), vec![0u8; 11 * 1024 * 1024])?;
+        write_file(&temp_dir.join("src/file.txt"), "one\ntwo\nthree\n")?;
+        {
+            let mut index = repo.index()?;
+            index.add_path(Path::new("dump.bin"))?;
+            index.add_path(Path::new("src/file.txt"))?;
+            index.write()?;
+        }
+
+        let repo = git_repo(repo);
+        let summary = repo.get_staged_summary(crate::prompt::PATCH_BUDGET)?;
+        let sizes = repo.staged_sizes(&summary.files)?;
+        let limits = Config::default().size_limits();
+
+        let report = size_report(sizes.clone(), limits).expect("11 MB file reported");
+        assert_eq!(
+            report.oversized,
+            vec![("dump.bin".to_string(), 11 * 1024 * 1024)]
+        );
+        assert!(!report.total_exceeded);
+        assert_eq!(format_size(report.total), "11.0 MB");
+
+        let relaxed = SizeLimits {
+            file_bytes: 0,
+            total_bytes: 0,
+        };
+        assert_eq!(size_report(sizes.clone(), relaxed), None);
+        let tight_total = SizeLimits {
+            file_bytes: 0,
+            total_bytes: 1024 * 1024,
+        };
+        assert!(size_report(sizes, tight_total).is_some_and(|r| r.total_exceeded));
+
+        drop(repo);
+        let _ = fs::remove_dir_all(&temp_dir);
+        Ok(())
+    }
+
+    #[test]
+    fn repeated_key_submissions_are_dropped_once_validation_starts() {
+        let input = AppState::ApiKeyInput {
+            input: "csk-key".to_string(),
+            cursor: 7,
+            error: None,
+        };
+        let entered = AppEvent::ApiKeyEntered("csk-key".to_string());
+
+        assert!(event_applies(&input, &entered));
+        assert!(!event_applies(&AppState::ApiKeyValidating, &entered));
+        assert!(event_applies(
+            &AppState::ApiKeyValidating,
+            &AppEvent::ApiKeyValidated
+        ));
+        assert!(!event_applies(&input, &AppEvent::ApiKeyValidated));
+
+        let editing = AppState::Editing {
+            branch: "main".to_string(),
+            files: Vec::new(),
+            buffer: String::new(),
+            cursor: 0,
+            salvaged: false,
+        };
+        assert!(!event_applies(
+            &editing,
+            &AppEvent::GenerationComplete("feat: late".to_string())
+        ));
+        assert!(!event_applies(
+            &editing,
+            &AppEvent::GenerationFailed("cancelled".to_string())
+        ));
+    }
+
+    #[test]
+    fn generation_timeout_tracks_stalls_not_total_time() {
+        let secs = Duration::from_secs;
+
+        assert!(!generation_timed_out(secs(10), secs(10)));
+        assert!(generation_timed_out(
+            secs(NO_CHUNK_TIMEOUT_SECS),
+            secs(NO_CHUNK_TIMEOUT_SECS)
+        ));
+        // Long-running but still streaming.
+        assert!(!generation_timed_out(
+            secs(NO_CHUNK_TIMEOUT_SECS + 30),
+            secs(2)
+        ));
+        // Stalled after earlier progress.
+        assert!(generation_timed_out(
+            secs(NO_CHUNK_TIMEOUT_SECS + 30),
+            secs(NO_CHUNK_TIMEOUT_SECS)
+        ));
+        assert!(generation_timed_out(
+            secs(MAX_GENERATION_TIMEOUT_SECS),
+            secs(1)
+        ));
+    }
+
+    #[test]
+    fn long_bodies_are_marked_truncated_in_the_preview() {
+        let short = "fix: keep the stage\n\n- one\n- two";
+        assert_eq!(message_preview(short, 40, 10), (4, false));
+
+        let long = format!("feat: add login\n\n{}", "- a point\n\n".repeat(10));
+        assert_eq!(message_preview(&long, 40, 30), (12, true));
+        // Wrapped lines take more rows, and one row goes to the marker.
+        let wide = format!("feat: add login\n\n{}", "word ".repeat(30));
+        assert_eq!(message_preview(&wide, 40, 4), (2, true));
+        assert_eq!(message_preview(&wide, 40, 6), (3, false));
+    }
+
+    #[test]
+    fn typewriter_reveals_at_steady_rate_and_catches_up() {
+        // 50ms at 160 chars/sec reveals 8 characters.
+        assert_eq!(advance_reveal(0, 40, Duration::from_millis(50)), 8);
+        // Always progresses at least one character per tick.
+        assert_eq!(advance_reveal(3, 40, Duration::ZERO), 4);
+        // Never runs past what was received.
+        assert_eq!(advance_reveal(38, 40, Duration::from_secs(1)), 40);
+        assert_eq!(advance_reveal(40, 40, Duration::from_secs(1)), 40);
+        // A large backlog is drained faster than the steady rate.
+        assert_eq!(advance_reveal(0, 1000, Duration::from_millis(50)), 50);
+    }
+
+    #[test]
+    fn failure_cleanup_asks_interactively_and_defaults_to_keeping_stage() {
+        assert_eq!(cleanup_action(false, true), CleanupAction::Keep);
+        assert_eq!(cleanup_action(true, true), CleanupAction::Ask);
+        assert_eq!(cleanup_action(true, false), CleanupAction::Unstage);
+
+        assert_eq!(unstage_confirmed(KeyCode::Char('y')), Some(true));
+        assert_eq!(unstage_confirmed(KeyCode::Char('N')), Some(false));
+        assert_eq!(unstage_confirmed(KeyCode::Enter), Some(false));
+        assert_eq!(unstage_confirmed(KeyCode::Char('x')), None);
+    }
+
+    #[test]
+    fn candidates_drop_refusals_repeats_and_non_strict_titles() {
+        let raws = [
+            "feat[AUTH]: add login form\n\n- validate the email".to_string(),
+            "I'm sorry, but I can't help with that.".to_string(),
+            "feat[AUTH]: add login form\n\n- validate the email".to_string(),
+            "feat(auth): add a login form".to_string(),
+        ];
+        let options = cerebras::MessageOptions::default();
+
+        let loose = usable_candidates(&raws, &options, false);
+        assert_eq!(
+            loose,
+            [
+                (
+                    "feat[AUTH]: add login form".to_string(),
+                    Some("- validate the email".to_string())
+                ),
+                ("feat(auth): add a login form".to_string(), None),
+            ]
+        );
+        let strict = usable_candidates(&raws, &options, true);
+        assert_eq!(strict, [("feat(auth): add a login form".to_string(), None)]);
+
+        assert!(Args::try_parse_from(["yeti", "--pick", "3"]).is_ok());
+        assert!(Args::try_parse_from(["yeti", "--pick", "1"]).is_err());
+        assert!(Args::try_parse_from(["yeti", "--pick", "9"]).is_err());
+
+        assert_eq!(candidate_temperature(0.2, 0), 0.2);
+        assert!(candidate_temperature(0.2, 7) - 0.2 <= 1.0);
+        assert_eq!(candidate_temperature(1.9, 7), cerebras::MAX_TEMPERATURE);
+    }
+
+    #[test]
+    fn diff_view_scrolls_within_the_patch_and_tab_cycles_files() {
+        let file = |path: &str, lines: usize| FileInfo {
+            additions: lines,
+            diff: "+line\n".repeat(lines),
+            hunks: 1,
+            ..FileInfo::new(path, FileStatus::Modified)
+        };
+        let files = [file("src/a.rs", 50), file("src/b.rs", 3)];
+        let generating = AppState::Generating {
+            branch: "main".to_string(),
+            files: files.to_vec(),
+            generated: String::new(),
+            started_at: std::time::Instant::now(),
+            last_progress: std::time::Instant::now(),
+            revealed: 0,
+            last_reveal: std::time::Instant::now(),
+        };
+        assert_eq!(main_files(&generating).map(<[FileInfo]>::len), Some(2));
+
+        let (mut index, mut scroll) = (0, 0);
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::PageDown);
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::PageDown);
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::PageDown);
+        assert_eq!(scroll, 49);
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::PageUp);
+        assert_eq!(scroll, 29);
+
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::Tab);
+        assert_eq!((index, scroll), (1, 0));
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::PageDown);
+        assert_eq!(scroll, 2);
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::Tab);
+        assert_eq!((index, scroll), (0, 0));
+        navigate_diff(&files, &mut index, &mut scroll, KeyCode::BackTab);
+        assert_eq!(index, 1);
+    }
+
+    #[test]
+    fn edit_keys_respect_multibyte_characters() {
+        let mut buffer = "fix: naïve".to_string();
+        let mut cursor = buffer.len();
+
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Left);
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Left);
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Backspace);
+        assert_eq!(buffer, "fix: nave");
+
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Char('ï'));
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::End);
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Enter);
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Char('x'));
+        assert_eq!(buffer, "fix: naïve\nx");
+
+        apply_edit_key(&mut buffer, &mut cursor, KeyCode::Home);
+        assert_eq!(cursor, buffer.len() - 1);
+    }
+}
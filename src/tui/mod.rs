@@ -4,10 +4,15 @@ mod widgets;
 
 pub use app::{App, AppResult};
 pub use theme::Theme;
-pub use widgets::{draw_error, draw_key_input, draw_status_panel};
+pub use widgets::{
+    draw_diff_view, draw_error, draw_file_picker, draw_key_input, draw_message_picker,
+    draw_status_panel, format_count, format_timestamp,
+};
 
 use crate::args::{MASCOT_LINES, MASCOT_MINI};
+use crate::color::Palette;
 use crate::error::Result;
+use crate::prompt::FileInfo;
 use comfy_table::{Attribute, Cell, Color, Table, presets::UTF8_FULL};
 use crossterm::event::{self, Event};
 use crossterm::execute;
@@ -16,6 +21,7 @@ use crossterm::terminal::{
 };
 use ratatui::{Terminal, backend::CrosstermBackend};
 use std::io::{self, Stdout, Write};
+use unicode_width::{UnicodeWidthChar, UnicodeWidthStr};
 
 pub struct Tui {
     terminal: Terminal<CrosstermBackend<Stdout>>,
@@ -46,185 +52,322 @@ impl Tui {
     }
 
     pub fn leave_and_print_history(result: &AppResult) {
+        Self::leave();
+        print!("{}", render_history(result, &Palette::detect()));
+    }
+
+    /// `--summary-line`: the one parseable line instead of the summary box.
+    pub fn leave_and_print_summary_line(result: &AppResult) {
+        Self::leave();
+        println!("{}", summary_line(result));
+    }
+
+    /// Hands the terminal back for `run` (an editor, say) and takes it
+    /// over again afterwards.
+    pub fn suspend<T>(&mut self, run: impl FnOnce() -> T) -> Result<T> {
+        Self::leave();
+        let out = run();
+        enable_raw_mode().map_err(|e| crate::error::YetiError::IoError(e.to_string()))?;
+        execute!(io::stdout(), EnterAlternateScreen)
+            .map_err(|e| crate::error::YetiError::IoError(e.to_string()))?;
+        self.terminal
+            .clear()
+            .map_err(|e| crate::error::YetiError::IoError(e.to_string()))?;
+        Ok(out)
+    }
+
+    fn leave() {
         let mut stdout = io::stdout();
         let _ = disable_raw_mode();
         let _ = execute!(stdout, LeaveAlternateScreen);
         let _ = stdout.flush();
+    }
+}
 
-        let total_add: usize = result.files.iter().map(|f| f.additions).sum();
-        let total_del: usize = result.files.iter().map(|f| f.deletions).sum();
+/// `yeti|<branch>|<sha>|<N files>|+A -D|<title>` for status lines and
+/// notifications; the sha is `-` on a dry run. The title comes last so a
+/// `|` inside it doesn't shift the other fields.
+pub fn summary_line(result: &AppResult) -> String {
+    let additions: usize = result.files.iter().map(|f| f.additions).sum();
+    let deletions: usize = result.files.iter().map(|f| f.deletions).sum();
+    let files = match result.files.len() {
+        1 => "1 file".to_string(),
+        n => format!("{} files", n),
+    };
+    format!(
+        "yeti|{}|{}|{}|+{} -{}|{}",
+        result.branch,
+        result.sha.as_deref().unwrap_or("-"),
+        files,
+        additions,
+        deletions,
+        result.message.lines().next().unwrap_or_default()
+    )
+}
 
-        let orange = Color::AnsiValue(208);
-        let green = Color::AnsiValue(142);
-        let red = Color::AnsiValue(167);
-        let yellow = Color::AnsiValue(214);
-        let dim = Color::AnsiValue(246);
+/// The staged-file table shared by the post-commit summary and `--list-staged`.
+pub fn render_file_table(files: &[FileInfo], p: &Palette) -> String {
+    let total_add: usize = files.iter().map(|f| f.additions).sum();
+    let total_del: usize = files.iter().map(|f| f.deletions).sum();
 
-        println!();
-        println!(
-            "  \x1b[1m\x1b[38;5;208m{} yeti\x1b[0m \x1b[38;5;246m\x1b[0m \x1b[38;5;109m{}\x1b[0m",
-            MASCOT_MINI, result.branch
-        );
-        println!();
+    let orange = Color::AnsiValue(208);
+    let green = Color::AnsiValue(142);
+    let red = Color::AnsiValue(167);
+    let yellow = Color::AnsiValue(214);
+    let dim = Color::AnsiValue(246);
 
-        let mut table = Table::new();
-        table.load_preset(UTF8_FULL);
+    let mut table = Table::new();
+    table.load_preset(UTF8_FULL);
+    if p.is_plain() {
+        table.force_no_tty();
+    }
 
-        table.set_header(vec![
-            Cell::new("st").fg(dim).add_attribute(Attribute::Bold),
-            Cell::new("file").fg(dim).add_attribute(Attribute::Bold),
-            Cell::new("add").fg(dim).add_attribute(Attribute::Bold),
-            Cell::new("del").fg(dim).add_attribute(Attribute::Bold),
-        ]);
+    table.set_header(vec![
+        Cell::new("st").fg(dim).add_attribute(Attribute::Bold),
+        Cell::new("file").fg(dim).add_attribute(Attribute::Bold),
+        Cell::new("add").fg(dim).add_attribute(Attribute::Bold),
+        Cell::new("del").fg(dim).add_attribute(Attribute::Bold),
+    ]);
 
-        for file in result.files.iter().take(10) {
-            let (status_text, status_color) = match file.status {
-                crate::prompt::FileStatus::Added => ("A", green),
-                crate::prompt::FileStatus::Deleted => ("D", red),
-                crate::prompt::FileStatus::Renamed => ("R", yellow),
-                crate::prompt::FileStatus::Modified => ("M", orange),
-            };
-
-            let path_display = if file.path.len() > 50 {
-                format!("...{}", &file.path[file.path.len() - 47..])
-            } else {
-                file.path.clone()
-            };
-
-            table.add_row(vec![
-                Cell::new(status_text).fg(status_color),
-                Cell::new(path_display),
-                Cell::new(format!("+{}", file.additions)).fg(green),
-                Cell::new(format!("-{}", file.deletions)).fg(red),
-            ]);
-        }
+    for file in files.iter().take(10) {
+        let status_color = match file.status {
+            _ if file.ignored => yellow,
+            crate::prompt::FileStatus::Added => green,
+            crate::prompt::FileStatus::Deleted => red,
+            crate::prompt::FileStatus::Renamed => yellow,
+            crate::prompt::FileStatus::Modified => orange,
+        };
 
-        if result.files.len() > 10 {
-            table.add_row(vec![
-                Cell::new(""),
-                Cell::new(format!("... {} more files", result.files.len() - 10)).fg(dim),
-                Cell::new(""),
-                Cell::new(""),
-            ]);
-        }
+        let path = file.annotated_path();
+        let chars = path.chars().count();
+        let path_display = if chars > 50 {
+            let tail: String = path.chars().skip(chars - 47).collect();
+            format!("...{}", tail)
+        } else {
+            path
+        };
 
+        let counts = match file.zero_change_label() {
+            Some(label) => [Cell::new(label).fg(dim), Cell::new("")],
+            None => [
+                Cell::new(format!("+{}", format_count(file.additions))).fg(green),
+                Cell::new(format!("-{}", format_count(file.deletions))).fg(red),
+            ],
+        };
+        let [add, del] = counts;
         table.add_row(vec![
-            Cell::new("total").add_attribute(Attribute::Bold),
-            Cell::new(format!("{} files", result.files.len())).add_attribute(Attribute::Bold),
-            Cell::new(format!("+{}", total_add))
-                .fg(green)
-                .add_attribute(Attribute::Bold),
-            Cell::new(format!("-{}", total_del))
-                .fg(red)
-                .add_attribute(Attribute::Bold),
+            Cell::new(file.status_tag()).fg(status_color),
+            Cell::new(path_display),
+            add,
+            del,
         ]);
+    }
+
+    if files.len() > 10 {
+        table.add_row(vec![
+            Cell::new(""),
+            Cell::new(format!("... {} more files", files.len() - 10)).fg(dim),
+            Cell::new(""),
+            Cell::new(""),
+        ]);
+    }
 
-        println!("{table}");
+    table.add_row(vec![
+        Cell::new("total").add_attribute(Attribute::Bold),
+        Cell::new(format!("{} files", format_count(files.len()))).add_attribute(Attribute::Bold),
+        Cell::new(format!("+{}", format_count(total_add)))
+            .fg(green)
+            .add_attribute(Attribute::Bold),
+        Cell::new(format!("-{}", format_count(total_del)))
+            .fg(red)
+            .add_attribute(Attribute::Bold),
+    ]);
 
-        println!();
+    format!("{table}\n")
+}
 
-        let status = if result.dry_run {
-            "\x1b[38;5;214mscent marked (dry-run)\x1b[0m"
+fn render_history(result: &AppResult, p: &Palette) -> String {
+    let mut out = String::new();
+
+    out.push('\n');
+    out.push_str(&format!(
+        "  {}{}{} yeti{} {}{} {}{}{}\n",
+        p.bold, p.orange, MASCOT_MINI, p.reset, p.dim, p.reset, p.blue, result.branch, p.reset
+    ));
+    out.push('\n');
+
+    out.push_str(&render_file_table(&result.files, p));
+    out.push('\n');
+
+    let status = if result.dry_run {
+        format!("{}scent marked (dry-run){}", p.yellow, p.reset)
+    } else {
+        format!("{}territory marked{}", p.green, p.reset)
+    };
+
+    out.push_str(&format!(
+        "  {}  {}{}{}\n",
+        status,
+        p.dim,
+        format_timestamp(result.finished_at, result.twelve_hour_clock),
+        p.reset
+    ));
+    if let Some(warning) = &result.config_warning {
+        out.push_str(&format!("  {}{}{}\n", p.yellow, warning, p.reset));
+    }
+    if let Some(author) = &result.author {
+        let missing = author.missing_keys();
+        if missing.is_empty() {
+            out.push_str(&format!(
+                "  {}as {} <{}>{}\n",
+                p.dim,
+                author.name.as_deref().unwrap_or_default(),
+                author.email.as_deref().unwrap_or_default(),
+                p.reset
+            ));
         } else {
-            "\x1b[38;5;142mterritory marked\x1b[0m"
-        };
+            out.push_str(&format!(
+                "  {}{} unset; git commit will fail or guess an author{}\n",
+                p.yellow,
+                missing.join(" and "),
+                p.reset
+            ));
+        }
+    }
+    if result.duplicate_subject {
+        out.push_str(&format!(
+            "  {}same subject as the previous commit; consider regenerating{}\n",
+            p.yellow, p.reset
+        ));
+    }
+    if !result.rewritten_by_hooks.is_empty() {
+        out.push_str(&format!(
+            "  {}commit hooks changed {}; their edits were re-staged and committed{}\n",
+            p.yellow,
+            result.rewritten_by_hooks.join(", "),
+            p.reset
+        ));
+    }
+    out.push('\n');
 
-        println!("  {}", status);
-
-        println!();
-
-        let dim_code = "\x1b[38;5;246m";
-        let mascot_code = "\x1b[1m\x1b[38;5;208m";
-        let reset = "\x1b[0m";
-        let bold_code = "\x1b[1m";
-
-        let max_width = 72usize;
-
-        let mut wrapped_lines = Vec::new();
-        for line in result.message.lines() {
-            if line.chars().count() <= max_width {
-                wrapped_lines.push(line.to_string());
-            } else {
-                let mut current = String::new();
-                for ch in line.chars() {
-                    current.push(ch);
-                    if current.chars().count() >= max_width {
-                        wrapped_lines.push(current.clone());
-                        current.clear();
-                    }
-                }
-                if !current.is_empty() {
-                    wrapped_lines.push(current);
+    let max_width = 72usize;
+
+    let mut wrapped_lines = Vec::new();
+    for line in result.message.lines() {
+        if line.width() <= max_width {
+            wrapped_lines.push(line.to_string());
+        } else {
+            let mut current = String::new();
+            let mut current_width = 0;
+            for ch in line.chars() {
+                let ch_width = ch.width().unwrap_or(0);
+                if current_width + ch_width > max_width {
+                    wrapped_lines.push(std::mem::take(&mut current));
+                    current_width = 0;
                 }
+                current.push(ch);
+                current_width += ch_width;
+            }
+            if !current.is_empty() {
+                wrapped_lines.push(current);
             }
         }
+    }
 
-        let max_msg_len = wrapped_lines
-            .iter()
-            .map(|l| l.chars().count())
-            .max()
-            .unwrap_or(40)
-            .min(max_width);
-
-        let mut msg_box_lines = Vec::new();
-        msg_box_lines.push(format!(
-            "{}╭{}╮{}",
-            dim_code,
-            "─".repeat(max_msg_len + 2),
-            reset
-        ));
-        for (i, line) in wrapped_lines.iter().enumerate() {
-            let line_len = line.chars().count();
-            let padding = max_msg_len.saturating_sub(line_len) + 1;
-            if i == 0 {
-                msg_box_lines.push(format!(
-                    "{}│{} {}{}{}{}│{}",
-                    dim_code,
-                    reset,
-                    bold_code,
-                    line,
-                    reset,
-                    " ".repeat(padding),
-                    reset
-                ));
-            } else {
-                msg_box_lines.push(format!(
-                    "{}│{} {}{}│{}",
-                    dim_code,
-                    reset,
-                    line,
-                    " ".repeat(padding),
-                    reset
-                ));
-            }
+    let hidden = match result.max_message_lines {
+        Some(max) if wrapped_lines.len() > max => {
+            let hidden = wrapped_lines.len() - max;
+            wrapped_lines.truncate(max);
+            hidden
         }
-        msg_box_lines.push(format!(
-            "{}╰{}╯{}",
-            dim_code,
-            "─".repeat(max_msg_len + 2),
-            reset
-        ));
+        _ => 0,
+    };
+    if hidden > 0 {
+        wrapped_lines.push(format!("(+{} more lines)", hidden));
+    }
 
-        println!(
-            "  {}commit message{}   {}{}{}",
-            dim_code, reset, mascot_code, MASCOT_MINI, reset
-        );
-        let rows = msg_box_lines.len().max(MASCOT_LINES.len());
-        for i in 0..rows {
-            let left = msg_box_lines
-                .get(i)
-                .cloned()
-                .unwrap_or_else(|| " ".repeat(max_msg_len + 4));
-            let mascot = MASCOT_LINES.get(i).copied().unwrap_or("");
-            if mascot.is_empty() {
-                println!("  {}", left);
-            } else {
-                println!("  {}   {}{}{}", left, mascot_code, mascot, reset);
-            }
+    let max_msg_len = wrapped_lines
+        .iter()
+        .map(|l| l.width())
+        .max()
+        .unwrap_or(40)
+        .min(max_width);
+
+    let mut msg_box_lines = Vec::new();
+    msg_box_lines.push(format!(
+        "{}╭{}╮{}",
+        p.dim,
+        "─".repeat(max_msg_len + 2),
+        p.reset
+    ));
+    for (i, line) in wrapped_lines.iter().enumerate() {
+        let padding = box_padding(line, max_msg_len);
+        if hidden > 0 && i == wrapped_lines.len() - 1 {
+            msg_box_lines.push(format!(
+                "{}│{} {}{}{}{}{}│{}",
+                p.dim,
+                p.reset,
+                p.dim,
+                line,
+                p.reset,
+                " ".repeat(padding),
+                p.dim,
+                p.reset
+            ));
+        } else if i == 0 {
+            msg_box_lines.push(format!(
+                "{}│{} {}{}{}{}{}│{}",
+                p.dim,
+                p.reset,
+                p.bold,
+                line,
+                p.reset,
+                " ".repeat(padding),
+                p.dim,
+                p.reset
+            ));
+        } else {
+            msg_box_lines.push(format!(
+                "{}│{} {}{}{}│{}",
+                p.dim,
+                p.reset,
+                line,
+                " ".repeat(padding),
+                p.dim,
+                p.reset
+            ));
         }
+    }
+    msg_box_lines.push(format!(
+        "{}╰{}╯{}",
+        p.dim,
+        "─".repeat(max_msg_len + 2),
+        p.reset
+    ));
 
-        println!();
+    out.push_str(&format!(
+        "  {}commit message{}   {}{}{}{}\n",
+        p.dim, p.reset, p.bold, p.orange, MASCOT_MINI, p.reset
+    ));
+    let rows = msg_box_lines.len().max(MASCOT_LINES.len());
+    for i in 0..rows {
+        let left = msg_box_lines
+            .get(i)
+            .cloned()
+            .unwrap_or_else(|| " ".repeat(max_msg_len + 4));
+        let mascot = MASCOT_LINES.get(i).copied().unwrap_or("");
+        if mascot.is_empty() {
+            out.push_str(&format!("  {}\n", left));
+        } else {
+            out.push_str(&format!(
+                "  {}   {}{}{}{}\n",
+                left, p.bold, p.orange, mascot, p.reset
+            ));
+        }
     }
+
+    out.push('\n');
+    out
 }
 
 impl Drop for Tui {
@@ -233,3 +376,134 @@ impl Drop for Tui {
         let _ = self.terminal.show_cursor();
     }
 }
+
+/// Spaces needed after `line` to reach the right border, measured in terminal
+/// cells so wide (CJK, emoji) glyphs don't push the border out of line.
+fn box_padding(line: &str, inner_width: usize) -> usize {
+    inner_width.saturating_sub(line.width()) + 1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{AppResult, box_padding, render_history, summary_line};
+    use crate::color::Palette;
+    use crate::git::Identity;
+    use crate::prompt::{FileInfo, FileStatus};
+    use unicode_width::UnicodeWidthStr;
+
+    fn sample_result() -> AppResult {
+        AppResult {
+            branch: "main".to_string(),
+            files: vec![FileInfo {
+                additions: 1_200,
+                deletions: 3,
+                ..FileInfo::new("src/lib.rs", FileStatus::Modified)
+            }],
+            message: "feat[CORE]: add thing\n\nExplain the thing.".to_string(),
+            dry_run: true,
+            finished_at: git2::Time::new(0, 0),
+            twelve_hour_clock: false,
+            max_message_lines: Some(12),
+            duplicate_subject: false,
+            author: None,
+            config_warning: None,
+            sha: None,
+            rewritten_by_hooks: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn summary_line_is_pipe_separated_with_the_title_last() {
+        let mut result = sample_result();
+        assert_eq!(
+            summary_line(&result),
+            "yeti|main|-|1 file|+1200 -3|feat[CORE]: add thing"
+        );
+
+        result.dry_run = false;
+        result.sha = Some("3f2a9c1".to_string());
+        result.files.push(result.files[0].clone());
+        assert_eq!(
+            summary_line(&result),
+            "yeti|main|3f2a9c1|2 files|+2400 -6|feat[CORE]: add thing"
+        );
+    }
+
+    #[test]
+    fn history_output_has_no_escape_codes_when_color_is_disabled() {
+        let plain = render_history(&sample_result(), &Palette::plain());
+
+        assert!(!plain.contains('\x1b'));
+        assert!(plain.contains("feat[CORE]: add thing"));
+        assert!(plain.contains("+1,200"));
+
+        let colored = render_history(&sample_result(), &Palette::ansi());
+        assert!(colored.contains('\x1b'));
+    }
+
+    #[test]
+    fn dry_run_summary_shows_author_or_warns_when_unset() {
+        let mut result = sample_result();
+        result.author = Some(Identity {
+            name: Some("Yeti Tester".to_string()),
+            email: Some("yeti@example.com".to_string()),
+        });
+        let out = render_history(&result, &Palette::plain());
+        assert!(out.contains("as Yeti Tester <yeti@example.com>"));
+
+        result.author = Some(Identity::default());
+        let out = render_history(&result, &Palette::plain());
+        assert!(out.contains("user.name and user.email unset"));
+    }
+
+    #[test]
+    fn long_messages_are_cut_with_a_more_lines_indicator() {
+        let mut result = sample_result();
+        let body: Vec<String> = (1..=20).map(|n| format!("body line {}", n)).collect();
+        result.message = format!("feat[CORE]: add thing\n\n{}", body.join("\n"));
+        result.max_message_lines = Some(5);
+
+        let output = render_history(&result, &Palette::plain());
+        assert!(output.contains("body line 3"));
+        assert!(!output.contains("body line 4"));
+        assert!(output.contains("(+17 more lines)"));
+
+        result.max_message_lines = None;
+        let output = render_history(&result, &Palette::plain());
+        assert!(output.contains("body line 20"));
+        assert!(!output.contains("more lines"));
+    }
+
+    #[test]
+    fn box_padding_counts_wide_characters_as_two_cells() {
+        assert_eq!(box_padding("abcd", 10), 7);
+        // Four CJK glyphs occupy eight cells.
+        assert_eq!(box_padding("修复问题", 10), 3);
+        assert_eq!(box_padding("fix: 修复", 10), 2);
+    }
+
+    #[test]
+    fn history_box_borders_align_for_wide_messages() {
+        let mut result = sample_result();
+        result.message = "fix[CORE]: 修复换行问题\n\nplain ascii body".to_string();
+        let out = render_history(&result, &Palette::plain());
+
+        let widths: Vec<usize> = out
+            .lines()
+            .skip_while(|l| !l.contains("commit message"))
+            .filter(|l| l.trim_start().starts_with(['│', '╭', '╰']))
+            .map(|l| {
+                // Measure up to the box's own right border; the mascot drawn
+                // alongside contains box-drawing characters too.
+                let inner = &l.trim_start()[3..];
+                let end = inner
+                    .find(['│', '╮', '╯'])
+                    .expect("box line has right border");
+                inner[..end].width()
+            })
+            .collect();
+
+        assert!(widths.len() >= 4);
+        assert!(widths.iter().all(|w| *w == widths[0]));
+    }
+}
@@ -21,6 +21,18 @@ impl Theme {
         }
     }
 
+    /// Terminal default colors for `--no-color`/`NO_COLOR`; emphasis stays via modifiers.
+    pub fn plain() -> Self {
+        Self {
+            fg: Color::Reset,
+            dim: Color::Reset,
+            accent: Color::Reset,
+            green: Color::Reset,
+            red: Color::Reset,
+            yellow: Color::Reset,
+        }
+    }
+
     pub fn fg_style(&self) -> Style {
         Style::default().fg(self.fg)
     }
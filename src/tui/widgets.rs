@@ -1,9 +1,10 @@
 use crate::args::{MASCOT_LINES, MASCOT_MINI};
+use crate::prompt::FileInfo;
 use crate::tui::Theme;
 use ratatui::{
     Frame,
-    layout::Rect,
-    style::Style,
+    layout::{Constraint, Layout, Rect},
+    style::{Modifier, Style},
     text::{Line, Span},
     widgets::{Block, BorderType, Clear, Padding, Paragraph, Wrap},
 };
@@ -14,6 +15,7 @@ pub fn draw_key_input(
     input: &str,
     cursor: usize,
     error: Option<&str>,
+    endpoint: &str,
 ) {
     let area = centered_rect(66, 42, f.area());
     f.render_widget(Clear, area);
@@ -37,9 +39,17 @@ pub fn draw_key_input(
         )),
         Line::from(Span::styled(MASCOT_LINES[1], theme.dim_style())),
         Line::from(Span::styled(
-            "No API key found. Add your Cerebras key to start generating commit messages.",
+            if endpoint == crate::cerebras::API_URL {
+                "No API key found. Add your Cerebras key to start generating commit messages."
+            } else {
+                "No API key found. Add the key for your endpoint to start generating commit messages."
+            },
             theme.fg_style(),
         )),
+        Line::from(vec![
+            Span::styled("endpoint ", theme.dim_style()),
+            Span::styled(endpoint.to_string(), theme.fg_style()),
+        ]),
         Line::from(""),
         Line::from(vec![
             Span::styled("key ", theme.dim_style()),
@@ -53,10 +63,12 @@ pub fn draw_key_input(
     }
 
     lines.push(Line::from(""));
-    lines.push(Line::from(Span::styled(
-        "cloud.cerebras.ai/account/api-keys",
-        theme.dim_style(),
-    )));
+    if endpoint == crate::cerebras::API_URL {
+        lines.push(Line::from(Span::styled(
+            "cloud.cerebras.ai/account/api-keys",
+            theme.dim_style(),
+        )));
+    }
     lines.push(Line::from(Span::styled(
         "Enter save  ·  Esc cancel",
         theme.dim_style(),
@@ -72,7 +84,16 @@ pub fn draw_key_input(
     f.render_widget(para, area);
 }
 
-pub fn draw_error(f: &mut Frame, theme: &Theme, message: &str, retryable: bool) {
+/// `manual` offers writing the message in `$EDITOR` after a failed generation.
+/// `cached`: a retry can reuse the last message, so `G` forces a new one.
+pub fn draw_error(
+    f: &mut Frame,
+    theme: &Theme,
+    message: &str,
+    retryable: bool,
+    manual: bool,
+    cached: bool,
+) {
     let area = centered_rect(66, 38, f.area());
     f.render_widget(Clear, area);
 
@@ -91,12 +112,24 @@ pub fn draw_error(f: &mut Frame, theme: &Theme, message: &str, retryable: bool)
     );
     lines.push(Line::from(""));
 
-    let hint = if retryable {
-        "R retry  ·  K new key  ·  Q exit"
-    } else {
-        "Q exit"
-    };
-    lines.push(Line::from(Span::styled(hint, theme.dim_style())));
+    let mut keys = Vec::new();
+    if retryable {
+        keys.push("R retry");
+        if cached {
+            keys.push("G retry with a new message");
+        }
+    }
+    if manual {
+        keys.push("M write it in $EDITOR");
+    }
+    if retryable {
+        keys.push("K new key");
+    }
+    keys.push("Q exit");
+    lines.push(Line::from(Span::styled(
+        keys.join("  ·  "),
+        theme.dim_style(),
+    )));
 
     let para = Paragraph::new(lines).wrap(Wrap { trim: true }).block(
         Block::bordered()
@@ -141,6 +174,231 @@ pub fn draw_status_panel(
     f.render_widget(Paragraph::new(lines).wrap(Wrap { trim: true }), inner);
 }
 
+/// The `--select` checklist of `(label, checked)` rows; the list scrolls to
+/// keep `cursor` in view.
+pub fn draw_file_picker(f: &mut Frame, theme: &Theme, rows: &[(String, bool)], cursor: usize) {
+    let area = centered_rect(66, 60, f.area());
+    f.render_widget(Clear, area);
+
+    let picked = rows.iter().filter(|(_, checked)| *checked).count();
+    let block = Block::bordered()
+        .title(Span::styled(
+            format!(" select files {}/{} ", picked, rows.len()),
+            theme.accent_style(),
+        ))
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.accent))
+        .padding(Padding::new(1, 1, 0, 0));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let [list_area, hint_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+    let height = list_area.height as usize;
+    let start = cursor.saturating_sub(height.saturating_sub(1));
+    let lines: Vec<Line> = rows
+        .iter()
+        .enumerate()
+        .skip(start)
+        .take(height)
+        .map(|(i, (label, checked))| {
+            let mark = if *checked { "[x]" } else { "[ ]" };
+            let style = if i == cursor {
+                theme.accent_style().add_modifier(Modifier::BOLD)
+            } else if *checked {
+                theme.fg_style()
+            } else {
+                theme.dim_style()
+            };
+            Line::from(Span::styled(format!("{} {}", mark, label), style))
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), list_area);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "Space toggle  ·  A all  ·  Enter stage selected  ·  Esc/Q exit",
+            theme.dim_style(),
+        )),
+        hint_area,
+    );
+}
+
+/// `--pick`: the generated titles to choose from, with the body of
+/// the one under `cursor` shown below the list.
+pub fn draw_message_picker(
+    f: &mut Frame,
+    theme: &Theme,
+    candidates: &[(String, Option<String>)],
+    cursor: usize,
+) {
+    let area = centered_rect(76, 70, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(Span::styled(
+            format!(" pick a message {}/{} ", cursor + 1, candidates.len()),
+            theme.accent_style(),
+        ))
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.accent))
+        .padding(Padding::new(1, 1, 0, 0));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let [list_area, body_area, hint_area] = Layout::vertical([
+        Constraint::Length(candidates.len() as u16 + 1),
+        Constraint::Min(1),
+        Constraint::Length(1),
+    ])
+    .areas(inner);
+    let lines: Vec<Line> = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, (title, _))| {
+            if i == cursor {
+                Line::from(Span::styled(
+                    format!("> {}", title),
+                    theme.accent_style().add_modifier(Modifier::BOLD),
+                ))
+            } else {
+                Line::from(Span::styled(format!("  {}", title), theme.fg_style()))
+            }
+        })
+        .collect();
+    f.render_widget(Paragraph::new(lines), list_area);
+    let body = candidates[cursor].1.as_deref().unwrap_or("(no body)");
+    f.render_widget(
+        Paragraph::new(body)
+            .style(theme.dim_style())
+            .wrap(Wrap { trim: false }),
+        body_area,
+    );
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "Up/Down choose  ·  Enter commit  ·  Esc/Q exit",
+            theme.dim_style(),
+        )),
+        hint_area,
+    );
+}
+
+/// One file's stored patch with `+`/`-` lines colored, scrolled down by
+/// `scroll` lines; `position` is `(index, total)` for the title.
+pub fn draw_diff_view(
+    f: &mut Frame,
+    theme: &Theme,
+    file: &FileInfo,
+    position: (usize, usize),
+    scroll: u16,
+) {
+    let area = centered_rect(90, 90, f.area());
+    f.render_widget(Clear, area);
+
+    let block = Block::bordered()
+        .title(Span::styled(
+            format!(
+                " {}  {}/{} ",
+                file.annotated_path(),
+                position.0 + 1,
+                position.1
+            ),
+            theme.accent_style(),
+        ))
+        .border_type(BorderType::Rounded)
+        .border_style(Style::default().fg(theme.accent))
+        .padding(Padding::new(1, 1, 0, 0));
+    let inner = block.inner(area);
+    f.render_widget(block, area);
+
+    let [diff_area, hint_area] =
+        Layout::vertical([Constraint::Min(1), Constraint::Length(1)]).areas(inner);
+    let lines: Vec<Line> = if file.diff.is_empty() {
+        vec![Line::from(Span::styled(
+            "No patch text for this file (binary, LFS pointer, symlink or mode change).",
+            theme.dim_style(),
+        ))]
+    } else {
+        file.diff
+            .lines()
+            .map(|line| {
+                let style = match line.chars().next() {
+                    Some('+') => theme.green_style(),
+                    Some('-') => theme.red_style(),
+                    _ => theme.fg_style(),
+                };
+                Line::from(Span::styled(line, style))
+            })
+            .collect()
+    };
+    f.render_widget(Paragraph::new(lines).scroll((scroll, 0)), diff_area);
+    f.render_widget(
+        Paragraph::new(Span::styled(
+            "PgUp/PgDn scroll  ·  Tab next file  ·  Esc back",
+            theme.dim_style(),
+        )),
+        hint_area,
+    );
+}
+
+pub fn format_count(n: usize) -> String {
+    let digits = n.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, ch) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(ch);
+    }
+    out
+}
+
+pub fn format_timestamp(time: git2::Time, twelve_hour: bool) -> String {
+    let offset = time.offset_minutes() as i64;
+    let local = time.seconds() + offset * 60;
+    let days = local.div_euclid(86_400);
+    let secs_of_day = local.rem_euclid(86_400);
+    let (year, month, day) = civil_from_days(days);
+    let (hour, minute, second) = (
+        secs_of_day / 3600,
+        (secs_of_day % 3600) / 60,
+        secs_of_day % 60,
+    );
+
+    let sign = if offset < 0 { '-' } else { '+' };
+    let zone = format!("{}{:02}:{:02}", sign, offset.abs() / 60, offset.abs() % 60);
+
+    if twelve_hour {
+        let meridiem = if hour < 12 { "AM" } else { "PM" };
+        let hour12 = match hour % 12 {
+            0 => 12,
+            h => h,
+        };
+        format!(
+            "{:04}-{:02}-{:02} {}:{:02}:{:02} {} {}",
+            year, month, day, hour12, minute, second, meridiem, zone
+        )
+    } else {
+        format!(
+            "{:04}-{:02}-{:02} {:02}:{:02}:{:02} {}",
+            year, month, day, hour, minute, second, zone
+        )
+    }
+}
+
+// Days since the Unix epoch to a proleptic Gregorian date (Howard Hinnant's algorithm).
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
 fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
     let popup_layout = ratatui::layout::Layout::default()
         .direction(ratatui::layout::Direction::Vertical)
@@ -162,3 +420,30 @@ fn centered_rect(percent_x: u16, percent_y: u16, r: Rect) -> Rect {
 
     popup_layout[1]
 }
+
+#[cfg(test)]
+mod tests {
+    use super::{format_count, format_timestamp};
+
+    #[test]
+    fn format_count_inserts_thousands_separators() {
+        assert_eq!(format_count(0), "0");
+        assert_eq!(format_count(999), "999");
+        assert_eq!(format_count(1_000), "1,000");
+        assert_eq!(format_count(12_345), "12,345");
+        assert_eq!(format_count(1_234_567), "1,234,567");
+    }
+
+    #[test]
+    fn format_timestamp_applies_offset_and_clock_style() {
+        // 2024-02-29 23:30:05 UTC
+        let time = git2::Time::new(1_709_249_405, 120);
+
+        assert_eq!(format_timestamp(time, false), "2024-03-01 01:30:05 +02:00");
+        assert_eq!(format_timestamp(time, true), "2024-03-01 1:30:05 AM +02:00");
+        assert_eq!(
+            format_timestamp(git2::Time::new(1_709_249_405, -300), true),
+            "2024-02-29 6:30:05 PM -05:00"
+        );
+    }
+}
@@ -0,0 +1,118 @@
+use ignore::gitignore::{Gitignore, GitignoreBuilder};
+use std::path::Path;
+
+pub const FILE_NAME: &str = ".yetiignore";
+
+/// Gitignore patterns from `.yetiignore` at the repository root; paths they
+/// match are never staged by yeti nor shown to the model.
+#[derive(Debug, Clone)]
+pub struct YetiIgnore {
+    rules: Gitignore,
+}
+
+impl Default for YetiIgnore {
+    fn default() -> Self {
+        Self {
+            rules: Gitignore::empty(),
+        }
+    }
+}
+
+impl YetiIgnore {
+    /// Reads `.yetiignore` from `workdir`; a missing file excludes nothing.
+    pub fn load(workdir: &Path) -> Self {
+        std::fs::read_to_string(workdir.join(FILE_NAME))
+            .map(|text| Self::parse(&text))
+            .unwrap_or_default()
+    }
+
+    /// Lines that aren't valid globs are skipped, as git does.
+    pub fn parse(text: &str) -> Self {
+        let mut builder = GitignoreBuilder::new("");
+        for line in text.lines() {
+            let _ = builder.add_line(None, line);
+        }
+        Self {
+            rules: builder.build().unwrap_or_else(|_| Gitignore::empty()),
+        }
+    }
+
+    /// Whether `path` (relative, `/`-separated, `dir/` for a directory) is
+    /// excluded. As in gitignore, the last matching rule wins, and nothing
+    /// inside an excluded directory can be re-included.
+    pub fn is_excluded(&self, path: &str) -> bool {
+        let is_dir = path.ends_with('/');
+        let path = path.trim_end_matches('/');
+        let mut parent_end = 0;
+        while let Some(slash) = path[parent_end..].find('/') {
+            parent_end += slash;
+            if self.rules.matched(&path[..parent_end], true).is_ignore() {
+                return true;
+            }
+            parent_end += 1;
+        }
+        self.rules.matched(path, is_dir).is_ignore()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::YetiIgnore;
+
+    #[test]
+    fn patterns_follow_gitignore_rules() {
+        let ignore = YetiIgnore::parse(
+            "# build output\n\
+             target/\n\
+             *.snap\n\
+             !keep.snap\n\
+             /fixtures/large/**\n\
+             docs/**/generated.md\n",
+        );
+
+        assert!(ignore.is_excluded("target/debug/yeti"));
+        assert!(ignore.is_excluded("crates/core/target/out.o"));
+        assert!(!ignore.is_excluded("src/target.rs"));
+
+        assert!(ignore.is_excluded("tests/ui.snap"));
+        assert!(!ignore.is_excluded("tests/keep.snap"));
+
+        assert!(ignore.is_excluded("fixtures/large/blob.bin"));
+        assert!(!ignore.is_excluded("src/fixtures/large/blob.bin"));
+
+        assert!(ignore.is_excluded("docs/generated.md"));
+        assert!(ignore.is_excluded("docs/api/v2/generated.md"));
+        assert!(!ignore.is_excluded("docs/guide.md"));
+
+        // Nothing under an excluded directory comes back.
+        let nested = YetiIgnore::parse("vendor/\n!vendor/patched.rs\n");
+        assert!(nested.is_excluded("vendor/patched.rs"));
+        assert!(!YetiIgnore::parse("").is_excluded("src/main.rs"));
+        assert!(YetiIgnore::parse("build/\n").is_excluded("build/"));
+    }
+
+    #[test]
+    fn classes_escapes_and_deep_globs_match_like_git() {
+        let ignore = YetiIgnore::parse(
+            "*.[oa]\n\
+             log[0-9].txt\n\
+             \\#notes\n\
+             literal\\*star\n\
+             **/a/**/b/**/c/**/d\n",
+        );
+
+        assert!(ignore.is_excluded("lib/core.o"));
+        assert!(ignore.is_excluded("lib/core.a"));
+        assert!(!ignore.is_excluded("lib/core.so"));
+        assert!(ignore.is_excluded("log7.txt"));
+        assert!(!ignore.is_excluded("logs.txt"));
+        assert!(ignore.is_excluded("#notes"));
+        assert!(ignore.is_excluded("literal*star"));
+        assert!(!ignore.is_excluded("literal-star"));
+
+        // Used to backtrack exponentially on a long near-miss.
+        let deep = format!("{}d-not", "a/b/c/".repeat(40));
+        assert!(!ignore.is_excluded(&deep));
+        assert!(ignore.is_excluded("x/a/y/b/c/z/d"));
+    }
+}